@@ -1,8 +1,20 @@
 use anyhow::{Context, Result};
-use axum::{extract::State, routing::get, Json, Router};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
 use env_logger::Env;
 use log::info;
-use slurm_common::{db, ClusterState, Job, Node, Partition};
+use serde::{Deserialize, Serialize};
+use slurm_common::{
+    array::{self, ArrayJob},
+    db,
+    qos::{self, QosUsage},
+    Association, ClusterState, Job, JobAllocation, JobId, JobPriority, JobRecord, JobResource,
+    JobStep, Node, NodeReservation, Partition, Qos, Reservation,
+};
 
 use sqlx::{sqlite::SqlitePoolOptions, Pool, Sqlite};
 use std::net::SocketAddr;
@@ -37,7 +49,22 @@ async fn main() -> Result<()> {
         .route("/api/status", get(get_status))
         .route("/api/nodes", get(get_nodes))
         .route("/api/jobs", get(get_jobs))
+        .route("/api/jobs/:id", get(get_job))
+        .route("/api/jobs/:id/steps", get(get_job_steps))
+        .route("/api/arrays", get(get_arrays))
+        .route("/api/arrays/:id", get(get_array))
         .route("/api/partitions", get(get_partitions))
+        .route(
+            "/api/partitions/:name/resources",
+            get(get_partition_resources),
+        )
+        .route("/api/history", get(get_history))
+        .route("/api/reservations", get(get_reservations))
+        .route("/api/reservations/nodes", get(get_node_reservations))
+        .route("/api/qos", get(get_qos))
+        .route("/api/qos/usage", get(get_qos_usage))
+        .route("/api/fairshare/accounts", get(get_account_shares))
+        .route("/api/fairshare/users", get(get_user_shares))
         .layer(CorsLayer::permissive())
         .layer(TraceLayer::new_for_http())
         .with_state(state);
@@ -67,6 +94,81 @@ async fn get_jobs(State(state): State<AppState>) -> Json<Vec<Job>> {
     Json(jobs)
 }
 
+#[derive(Debug, Serialize)]
+struct JobDetail {
+    job: Job,
+    resources: Vec<JobResource>,
+    allocations: Vec<JobAllocation>,
+    // Only set while the job is pending
+    priority: Option<JobPriority>,
+}
+
+// e.g. /api/jobs/1234, /api/jobs/1234_7 for an array task or /api/jobs/1234+1 for a het job component
+async fn get_job(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<JobDetail>, StatusCode> {
+    let job_id: JobId = id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let internal_error = |_| StatusCode::INTERNAL_SERVER_ERROR;
+    let job = db::fetch_job(&state.pool, &job_id)
+        .await
+        .map_err(internal_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let resources = db::fetch_job_resources(&state.pool, &job_id)
+        .await
+        .unwrap_or(vec![]);
+    let allocations = db::fetch_job_allocations(&state.pool, &job_id)
+        .await
+        .unwrap_or(vec![]);
+    let priority = db::fetch_job_priority(&state.pool, &job_id)
+        .await
+        .unwrap_or(None);
+    Ok(Json(JobDetail {
+        job,
+        resources,
+        allocations,
+        priority,
+    }))
+}
+
+async fn get_job_steps(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<JobStep>>, StatusCode> {
+    let job_id: JobId = id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let steps = db::fetch_job_steps(&state.pool, &job_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(steps))
+}
+
+async fn get_arrays(State(state): State<AppState>) -> Json<Vec<ArrayJob>> {
+    let jobs = db::fetch_all_jobs(&state.pool).await.unwrap_or(vec![]);
+    Json(array::arrays(&jobs))
+}
+
+#[derive(Debug, Serialize)]
+struct ArrayDetail {
+    array: ArrayJob,
+    tasks: Vec<Job>,
+}
+
+async fn get_array(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<ArrayDetail>, StatusCode> {
+    let jobs = db::fetch_all_jobs(&state.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut tasks: Vec<Job> = jobs
+        .into_iter()
+        .filter(|j| j.job_id.is_array() && j.job_id.id == id)
+        .collect();
+    tasks.sort_by(|a, b| a.job_id.cmp(&b.job_id));
+    let array = array::arrays(&tasks).pop().ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(ArrayDetail { array, tasks }))
+}
+
 async fn get_partitions(State(state): State<AppState>) -> Json<Vec<Partition>> {
     let parts = db::fetch_all_partitions(&state.pool)
         .await
@@ -74,4 +176,103 @@ async fn get_partitions(State(state): State<AppState>) -> Json<Vec<Partition>> {
     Json(parts)
 }
 
+// e.g. /api/partitions/gpu/resources?kind=gres&name=gpu&type=a100 for the free A100s
+async fn get_partition_resources(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(filter): Query<db::ResourceFilter>,
+) -> Json<Vec<db::PartitionResource>> {
+    let resources = db::fetch_partition_resources(&state.pool, &name, &filter)
+        .await
+        .unwrap_or(vec![]);
+    Json(resources)
+}
+
+// e.g. /api/history?user=alice&since=2026-01-01T00:00:00Z&limit=100
+async fn get_history(
+    State(state): State<AppState>,
+    Query(filter): Query<db::HistoryFilter>,
+) -> Json<Vec<JobRecord>> {
+    let history = db::fetch_job_history(&state.pool, &filter)
+        .await
+        .unwrap_or(vec![]);
+    Json(history)
+}
+
+async fn get_reservations(State(state): State<AppState>) -> Json<Vec<Reservation>> {
+    let reservations = db::fetch_all_reservations(&state.pool)
+        .await
+        .unwrap_or(vec![]);
+    Json(reservations)
+}
+
+// The nodes that are part of an active or upcoming reservation
+async fn get_node_reservations(State(state): State<AppState>) -> Json<Vec<NodeReservation>> {
+    let items = db::fetch_all_node_reservations(&state.pool)
+        .await
+        .unwrap_or(vec![]);
+    Json(items)
+}
+
+async fn get_qos(State(state): State<AppState>) -> Json<Vec<Qos>> {
+    let qos = db::fetch_all_qos(&state.pool).await.unwrap_or(vec![]);
+    Json(qos)
+}
+
+#[derive(Debug, Deserialize)]
+struct QosUsageQuery {
+    user: Option<String>,
+}
+
+// e.g. /api/qos/usage?user=alice for alice's running jobs and resources against each QOS limit
+async fn get_qos_usage(
+    State(state): State<AppState>,
+    Query(query): Query<QosUsageQuery>,
+) -> Json<Vec<QosUsage>> {
+    let status = db::fetch_cluster_state(&state.pool)
+        .await
+        .unwrap_or_default();
+    let usage = qos::usage(&status)
+        .into_iter()
+        .filter(|u| query.user.as_ref().is_none_or(|user| &u.user == user))
+        .collect();
+    Json(usage)
+}
+
+async fn get_account_shares(State(state): State<AppState>) -> Json<Vec<Association>> {
+    let associations = db::fetch_all_associations(&state.pool)
+        .await
+        .unwrap_or(vec![]);
+    Json(
+        associations
+            .into_iter()
+            .filter(|a| a.user.is_empty())
+            .collect(),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct UserSharesQuery {
+    account: Option<String>,
+    user: Option<String>,
+}
+
+// e.g. /api/fairshare/users?user=alice for the shares of alice in each of her accounts
+async fn get_user_shares(
+    State(state): State<AppState>,
+    Query(query): Query<UserSharesQuery>,
+) -> Json<Vec<Association>> {
+    let associations = db::fetch_all_associations(&state.pool)
+        .await
+        .unwrap_or(vec![]);
+    let matches = |filter: &Option<String>, value: &str| filter.as_ref().is_none_or(|f| f == value);
+    Json(
+        associations
+            .into_iter()
+            .filter(|a| !a.user.is_empty())
+            .filter(|a| matches(&query.account, &a.account) && matches(&query.user, &a.user))
+            .collect(),
+    )
+}
+
 // Helpers removed as they are now in slurm-common
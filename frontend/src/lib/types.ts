@@ -16,11 +16,21 @@ export enum NodeStatus {
     MIX = "Mix",
     ALLOC = "Alloc",
     DOWN = "Down",
+    FUTURE = "Future",
+    UNKNOWN = "Unknown",
 }
 
+// The flags Slurm adds to the base state, e.g. "Drain" or "NotResponding"
+export type NodeFlag = string;
+
+export type NodeState = {
+    base: NodeStatus;
+    flags: NodeFlag[];
+};
+
 export type Node = {
     name: string;
-    status: NodeStatus;
+    state: NodeState;
     cpus: number;
     real_memory: number; // in MB
     resources: Record<string, Resource>;
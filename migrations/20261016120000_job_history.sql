@@ -0,0 +1,21 @@
+-- Jobs that have finished, as reported by sacct. Rows are kept after
+-- the job leaves the queue and the jobs table.
+CREATE TABLE IF NOT EXISTS job_history (
+    job_id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    user TEXT NOT NULL,
+    account TEXT,
+    partition TEXT NOT NULL,
+    status TEXT NOT NULL,
+    exit_code INTEGER NOT NULL,
+    exit_signal INTEGER NOT NULL,
+    submit_time DATETIME NOT NULL,
+    start_time DATETIME,
+    end_time DATETIME NOT NULL,
+    elapsed INTEGER NOT NULL,
+    total_cpu INTEGER NOT NULL,
+    max_rss INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS job_history_user ON job_history (user, end_time);
+CREATE INDEX IF NOT EXISTS job_history_end_time ON job_history (end_time);
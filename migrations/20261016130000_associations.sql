@@ -0,0 +1,13 @@
+-- The fairshare of accounts and of users within an account, from sshare.
+-- The user is empty for the rows of the accounts themselves.
+CREATE TABLE IF NOT EXISTS associations (
+    account TEXT NOT NULL,
+    user TEXT NOT NULL,
+    raw_shares INTEGER,
+    norm_shares REAL NOT NULL,
+    raw_usage INTEGER NOT NULL,
+    effective_usage REAL NOT NULL,
+    fairshare REAL,
+    updated_at DATETIME NOT NULL,
+    PRIMARY KEY (account, user)
+);
@@ -0,0 +1,14 @@
+-- The priority factors of pending jobs, from sprio
+CREATE TABLE IF NOT EXISTS job_priorities (
+    job_id TEXT PRIMARY KEY,
+    partition TEXT NOT NULL,
+    priority INTEGER NOT NULL,
+    age_factor INTEGER NOT NULL,
+    fairshare_factor INTEGER NOT NULL,
+    job_size_factor INTEGER NOT NULL,
+    partition_factor INTEGER NOT NULL,
+    qos_factor INTEGER NOT NULL,
+    tres_factor INTEGER NOT NULL,
+    rank INTEGER NOT NULL,
+    updated_at DATETIME NOT NULL
+);
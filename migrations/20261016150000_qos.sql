@@ -0,0 +1,19 @@
+ALTER TABLE jobs ADD COLUMN account TEXT;
+ALTER TABLE jobs ADD COLUMN qos TEXT;
+
+-- QOS definitions from sacctmgr. The TRES limits are JSON objects
+-- from the TRES name to the limit, e.g. {"cpu": 64, "gres/gpu": 8}
+CREATE TABLE IF NOT EXISTS qos (
+    name TEXT PRIMARY KEY,
+    priority INTEGER NOT NULL,
+    preempt TEXT NOT NULL,
+    preempt_mode TEXT,
+    grp_tres TEXT NOT NULL,
+    grp_jobs INTEGER,
+    max_tres_per_job TEXT NOT NULL,
+    max_tres_per_user TEXT NOT NULL,
+    max_wall INTEGER,
+    max_jobs_per_user INTEGER,
+    max_submit_per_user INTEGER,
+    updated_at DATETIME NOT NULL
+);
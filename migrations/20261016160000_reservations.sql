@@ -0,0 +1,21 @@
+-- Reservations from scontrol. The lists and the TRES are stored as JSON.
+CREATE TABLE IF NOT EXISTS reservations (
+    name TEXT PRIMARY KEY,
+    start_time DATETIME NOT NULL,
+    end_time DATETIME NOT NULL,
+    active BOOLEAN NOT NULL,
+    nodes TEXT NOT NULL,
+    partition TEXT,
+    users TEXT NOT NULL,
+    accounts TEXT NOT NULL,
+    flags TEXT NOT NULL,
+    tres TEXT NOT NULL,
+    updated_at DATETIME NOT NULL
+);
+
+-- The reservations, active or upcoming, that a node belongs to
+CREATE TABLE IF NOT EXISTS node_reservations (
+    node TEXT NOT NULL,
+    reservation TEXT NOT NULL,
+    PRIMARY KEY (node, reservation)
+);
@@ -0,0 +1,31 @@
+-- The parts of the TRES name in `resource`, e.g. kind "gres", name "gpu" and
+-- gres_type "a100" for "gres/gpu:a100", so resources can be filtered by them
+ALTER TABLE node_resources ADD COLUMN kind TEXT NOT NULL DEFAULT '';
+ALTER TABLE node_resources ADD COLUMN name TEXT;
+ALTER TABLE node_resources ADD COLUMN gres_type TEXT;
+
+ALTER TABLE job_resources ADD COLUMN kind TEXT NOT NULL DEFAULT '';
+ALTER TABLE job_resources ADD COLUMN name TEXT;
+ALTER TABLE job_resources ADD COLUMN gres_type TEXT;
+
+UPDATE node_resources SET
+    kind = CASE WHEN instr(resource, '/') > 0
+        THEN substr(resource, 1, instr(resource, '/') - 1) ELSE resource END,
+    name = CASE WHEN instr(resource, '/') > 0
+        THEN substr(resource, instr(resource, '/') + 1) END;
+UPDATE node_resources SET
+    name = substr(name, 1, instr(name, ':') - 1),
+    gres_type = substr(name, instr(name, ':') + 1)
+WHERE kind = 'gres' AND instr(name, ':') > 0;
+
+UPDATE job_resources SET
+    kind = CASE WHEN instr(resource, '/') > 0
+        THEN substr(resource, 1, instr(resource, '/') - 1) ELSE resource END,
+    name = CASE WHEN instr(resource, '/') > 0
+        THEN substr(resource, instr(resource, '/') + 1) END;
+UPDATE job_resources SET
+    name = substr(name, 1, instr(name, ':') - 1),
+    gres_type = substr(name, instr(name, ':') + 1)
+WHERE kind = 'gres' AND instr(name, ':') > 0;
+
+CREATE INDEX IF NOT EXISTS node_resources_kind ON node_resources (kind, name, gres_type);
@@ -0,0 +1,8 @@
+-- Why a node is down or drained, who set the reason and when, and when the
+-- node and its slurmd were started and last ran a job
+ALTER TABLE nodes ADD COLUMN reason TEXT;
+ALTER TABLE nodes ADD COLUMN reason_user TEXT;
+ALTER TABLE nodes ADD COLUMN reason_time DATETIME;
+ALTER TABLE nodes ADD COLUMN boot_time DATETIME;
+ALTER TABLE nodes ADD COLUMN slurmd_start_time DATETIME;
+ALTER TABLE nodes ADD COLUMN last_busy_time DATETIME;
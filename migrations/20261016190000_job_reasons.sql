@@ -0,0 +1,2 @@
+-- Why a job is pending or why it failed, e.g. "Resources" or "NonZeroExitCode"
+ALTER TABLE jobs ADD COLUMN reason TEXT;
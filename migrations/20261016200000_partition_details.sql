@@ -0,0 +1,16 @@
+-- The nodes, limits and scheduling settings of partitions.
+-- The allowed accounts and groups (null if all are allowed) and the billing
+-- weights are stored as JSON.
+ALTER TABLE partitions ADD COLUMN nodes TEXT;
+ALTER TABLE partitions ADD COLUMN total_nodes INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE partitions ADD COLUMN total_cpus INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE partitions ADD COLUMN max_time INTEGER;
+ALTER TABLE partitions ADD COLUMN default_time INTEGER;
+ALTER TABLE partitions ADD COLUMN max_nodes INTEGER;
+ALTER TABLE partitions ADD COLUMN min_nodes INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE partitions ADD COLUMN priority_tier INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE partitions ADD COLUMN preempt_mode TEXT;
+ALTER TABLE partitions ADD COLUMN over_subscribe TEXT;
+ALTER TABLE partitions ADD COLUMN allow_accounts TEXT NOT NULL DEFAULT 'null';
+ALTER TABLE partitions ADD COLUMN allow_groups TEXT NOT NULL DEFAULT 'null';
+ALTER TABLE partitions ADD COLUMN billing_weights TEXT NOT NULL DEFAULT '{}';
@@ -0,0 +1,2 @@
+-- When a running job reaches its time limit
+ALTER TABLE jobs ADD COLUMN end_time DATETIME;
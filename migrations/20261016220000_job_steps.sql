@@ -0,0 +1,17 @@
+-- The steps of running jobs from scontrol, with their live usage from sstat.
+-- The nodes are stored as JSON.
+CREATE TABLE IF NOT EXISTS job_steps (
+    job_id TEXT NOT NULL,
+    step TEXT NOT NULL,
+    name TEXT NOT NULL,
+    status TEXT NOT NULL,
+    nodes TEXT NOT NULL,
+    tasks INTEGER NOT NULL,
+    start_time DATETIME,
+    ave_cpu INTEGER,
+    max_rss INTEGER,
+    max_disk_read INTEGER,
+    max_disk_write INTEGER,
+    updated_at DATETIME NOT NULL,
+    PRIMARY KEY (job_id, step)
+);
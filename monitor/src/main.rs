@@ -4,12 +4,15 @@ use clap::Parser;
 use env_logger::Env;
 use log::{debug, error, info, warn};
 use serde::Deserialize;
-use slurm_common::{ClusterDiff, ClusterState};
+use slurm_common::protocol::{self, Hello, LogLevel, Message, Request, Resync};
+use slurm_common::JobRecord;
 use sqlx::{sqlite::SqlitePoolOptions, Pool, Sqlite};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
 use tokio::process::Command;
+use tokio::time::{self, Instant};
 
 mod ssh;
 use ssh::{Process, SshOptions};
@@ -33,6 +36,20 @@ struct Args {
     /// Whether to run the worker in mock mode
     mock: bool,
 
+    #[arg(long)]
+    /// The collector the worker should use (auto, scontrol, json or rest)
+    collector: Option<String>,
+
+    #[arg(long)]
+    /// Base url of slurmrestd for the rest collector.
+    /// The token is read by the worker from SLURM_JWT on the remote
+    slurmrestd_url: Option<String>,
+
+    #[arg(long, default_value = "300")]
+    /// Seconds without a message from the worker, not even the heartbeat it sends as
+    /// every poll starts, before it is restarted
+    worker_timeout: u64,
+
     #[clap(flatten)]
     ssh_options: SshOptions,
 }
@@ -59,71 +76,158 @@ async fn main() -> Result<()> {
     };
     info!("Using worker binary at: {:?}", worker_path);
 
-    let mut proc = match launch_worker(&args, worker_path).await {
-        Ok(proc) => proc,
-        Err(e) => {
-            error!("Failed to launch worker: {}", e);
-            return Err(e);
-        }
-    };
-    info!("Monitor started. Waiting for worker updates.");
-    monitor_loop(&mut *proc, pool).await
+    let timeout = Duration::from_secs(args.worker_timeout);
+    loop {
+        let mut proc = match launch_worker(&args, &worker_path).await {
+            Ok(proc) => proc,
+            Err(e) => {
+                error!("Failed to launch worker: {}", e);
+                return Err(e);
+            }
+        };
+        info!("Monitor started. Waiting for worker updates.");
+        monitor_loop(&mut *proc, pool.clone(), timeout).await?;
+        // Dropping the process kills the worker, if it still runs
+        drop(proc);
+        time::sleep(RESTART_DELAY).await;
+        info!("Restarting the worker.");
+    }
 }
 
-async fn launch_worker(args: &Args, worker_path: PathBuf) -> Result<Box<dyn Process>> {
+// How long to wait before a worker that died or hung is launched again
+const RESTART_DELAY: Duration = Duration::from_secs(10);
+
+async fn launch_worker(args: &Args, worker_path: &Path) -> Result<Box<dyn Process>> {
+    let mut worker_args = Vec::new();
+    if args.mock {
+        worker_args.push("--mock".to_string());
+    }
+    if let Some(collector) = &args.collector {
+        worker_args.push("--collector".to_string());
+        worker_args.push(collector.clone());
+    }
+    if let Some(url) = &args.slurmrestd_url {
+        worker_args.push("--slurmrestd-url".to_string());
+        worker_args.push(url.clone());
+    }
     if let Some(options) = &args.ssh_options.resolve()? {
-        let mut remote_args = Vec::new();
-        if args.mock {
-            remote_args.push("--mock".to_string());
-        }
         info!("Launching worker via SSH on {}", options.host);
-        let child = ssh::launch_on_remote(worker_path, remote_args, options).await?;
+        let child = ssh::launch_on_remote(worker_path.to_path_buf(), worker_args, options).await?;
         let proc: Box<dyn Process> = Box::new(child);
         Ok(proc)
     } else {
         let mut command = Command::new(worker_path);
         command.stdout(Stdio::piped());
         command.stderr(Stdio::piped());
-        if args.mock {
-            command.arg("--mock");
-        }
+        command.stdin(Stdio::piped());
+        command.kill_on_drop(true);
+        command.args(worker_args);
         let child = command.spawn().context("Failed to spawn worker process")?;
         let proc: Box<dyn Process> = Box::new(child);
         Ok(proc)
     }
 }
 
-async fn monitor_loop(child: &mut dyn Process, pool: Pool<Sqlite>) -> Result<()> {
+// Returns once the worker died or stopped sending, so it can be restarted
+async fn monitor_loop(
+    child: &mut dyn Process,
+    pool: Pool<Sqlite>,
+    timeout: Duration,
+) -> Result<()> {
     // Parse the command string (simplistic splitting)
     let stdout = child.stdout().context("Failed to open stdout")?;
     let stderr = child.stderr().context("Failed to open stderr")?;
+    let mut requests = Requests {
+        stdin: child.stdin().context("Failed to open stdin")?,
+    };
 
     // Read both the stdout and stderr asynchronously
     let mut stdout_reader = stdout.lines();
     let mut stderr_reader = stderr.lines();
 
-    let mut status = ClusterState::default();
+    // Set once the worker has sent its handshake
+    let mut worker: Option<Hello> = None;
+    let mut resync = Resync::default();
+    let mut last_message = Instant::now();
 
     loop {
         tokio::select! {
             result = stdout_reader.next_line() => {
+                last_message = Instant::now();
                 match result {
-                    Ok(Some(line)) => {
-                        if let Ok(diff) = serde_json::from_str::<ClusterDiff>(&line) {
-                            debug!("Received diff: {:#?}", diff);
-                            // Apply in-memory
-                            status.apply(diff.clone());
-
-                            // Apply to DB
-                            if let Err(e) = slurm_common::db::apply_diff(&pool, diff).await {
-                                error!("Error applying diff: {}", e);
+                    Ok(Some(line)) if worker.is_none() => {
+                        // An incompatible worker is refused
+                        match protocol::handshake(&line)? {
+                            Some(hello) => {
+                                info!(
+                                    "Worker {} on {} of cluster {}, reading Slurm {}",
+                                    hello.worker_version,
+                                    hello.hostname,
+                                    hello.cluster.as_deref().unwrap_or("(unknown)"),
+                                    hello
+                                        .slurm_version
+                                        .map_or("(unknown)".to_string(), |v| v.to_string())
+                                );
+                                worker = Some(hello);
+                            }
+                            None => debug!("Worker printed before its handshake: {}", line),
+                        }
+                    }
+                    Ok(Some(line)) => match Message::from_line(&line) {
+                        Ok(Message::Snapshot { seq, state, job_history, warnings }) => {
+                            debug!("Received snapshot {}: {:#?}", seq, state);
+                            for warning in &warnings {
+                                warn!("{}", warning);
+                            }
+                            resync.snapshot();
+                            save_history(&pool, &job_history).await;
+                            match slurm_common::db::apply_snapshot(&pool, &state).await {
+                                Ok(()) => {
+                                    info!("Updated cluster status from snapshot {}.", seq);
+                                    resync.applied(seq);
+                                }
+                                Err(e) => {
+                                    error!("Error applying snapshot {}: {}", seq, e);
+                                    requests.lost(&mut resync, "a snapshot failed to apply").await;
+                                }
+                            }
+                        }
+                        Ok(Message::Diff { seq, mut diff }) => {
+                            debug!("Received diff {}: {:#?}", seq, diff);
+                            for warning in &diff.warnings {
+                                warn!("{}", warning);
+                            }
+                            save_history(&pool, &std::mem::take(&mut diff.job_history)).await;
+                            if !resync.follows(seq) {
+                                let reason = format!("diff {} does not follow the last one", seq);
+                                requests.lost(&mut resync, &reason).await;
+                            } else if let Err(e) =
+                                slurm_common::db::apply_diff(&pool, *diff).await
+                            {
+                                error!("Error applying diff {}: {}", seq, e);
+                                requests.lost(&mut resync, "a diff failed to apply").await;
                             } else {
                                 info!("Updated cluster status.");
+                                resync.applied(seq);
                             }
-                        } else {
-                            error!("Failed to parse line as ClusterStatus: {}", line);
                         }
-                    }
+                        Ok(Message::Log { level, message }) => match level {
+                            LogLevel::Info => info!("Worker: {}", message),
+                            LogLevel::Warn => warn!("Worker: {}", message),
+                            LogLevel::Error => error!("Worker: {}", message),
+                        },
+                        Ok(Message::Error { message }) => {
+                            error!("Worker failed to poll: {}", message)
+                        }
+                        Ok(Message::Heartbeat { time }) => debug!("Worker polling at {}", time),
+                        Ok(Message::Hello(_)) => warn!("Worker sent a second handshake, ignored"),
+                        // It may have been a diff, so the next ones can't be applied. Its
+                        // finished jobs come again with the snapshot.
+                        Err(e) => {
+                            error!("Failed to parse a message from the worker: {}: {}", e, line);
+                            requests.lost(&mut resync, "a message failed to parse").await;
+                        }
+                    },
                     Ok(None) => {
                         warn!("Worker process died.");
                         break;
@@ -141,11 +245,53 @@ async fn monitor_loop(child: &mut dyn Process, pool: Pool<Sqlite>) -> Result<()>
                     Err(e) => error!("Error reading stderr: {}", e),
                 }
             }
+            // A hung worker, or a connection that dropped without closing
+            _ = time::sleep_until(last_message + timeout) => {
+                error!("Worker sent nothing for {} seconds.", timeout.as_secs());
+                break;
+            }
         }
     }
     Ok(())
 }
 
+// Finished jobs don't build on earlier diffs or on the rest of the state, so they are
+// stored first, whether or not the rest applies. The worker only sends them again
+// after the monitor asks for a snapshot.
+async fn save_history(pool: &Pool<Sqlite>, job_history: &[JobRecord]) {
+    for record in job_history {
+        if let Err(e) = slurm_common::db::upsert_job_record(pool, record).await {
+            error!("Error saving job {}: {}", record.job_id, e);
+        }
+    }
+}
+
+// Asks the worker for a snapshot on its stdin, when the Resync says to
+struct Requests {
+    stdin: Box<dyn AsyncWrite + Unpin + Send>,
+}
+
+impl Requests {
+    // The database missed a snapshot or diff
+    async fn lost(&mut self, resync: &mut Resync, reason: &str) {
+        if !resync.lost() {
+            return;
+        }
+        warn!("Requesting a snapshot from the worker, {}", reason);
+        if let Err(e) = self.send(&Request::Snapshot).await {
+            error!("Error requesting a snapshot: {}", e);
+            resync.request_failed();
+        }
+    }
+
+    async fn send(&mut self, request: &Request) -> Result<()> {
+        let line = request.to_line()? + "\n";
+        self.stdin.write_all(line.as_bytes()).await?;
+        self.stdin.flush().await?;
+        Ok(())
+    }
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(tag = "reason")]
 pub enum BuildOutput {
@@ -194,8 +340,8 @@ async fn build_worker(cargo_cmd: &str, cwd: &str) -> Result<PathBuf> {
         }
     }
     if let Some(BuildOutput::CompilerArtifact { executable, .. }) = last_artifact {
-        return Ok(PathBuf::from(executable.context("No executable found")?));
+        Ok(PathBuf::from(executable.context("No executable found")?))
     } else {
-        return Err(anyhow::anyhow!("No executable foundin build output"));
+        Err(anyhow::anyhow!("No executable found in build output"))
     }
 }
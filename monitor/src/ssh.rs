@@ -12,13 +12,14 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context as TaskContext, Poll};
 use tokio::io::AsyncRead;
-use tokio::io::{AsyncBufRead, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncBufRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
 use tokio::process::Child;
 use tokio::sync::mpsc;
 
 pub trait Process {
     fn stdout(&mut self) -> Option<Box<dyn AsyncBufRead + Unpin + Send>>;
     fn stderr(&mut self) -> Option<Box<dyn AsyncBufRead + Unpin + Send>>;
+    fn stdin(&mut self) -> Option<Box<dyn AsyncWrite + Unpin + Send>>;
 }
 
 impl Process for Child {
@@ -33,6 +34,12 @@ impl Process for Child {
             .take()
             .map(|s| Box::new(BufReader::new(s)) as Box<dyn AsyncBufRead + Unpin + Send>)
     }
+
+    fn stdin(&mut self) -> Option<Box<dyn AsyncWrite + Unpin + Send>> {
+        self.stdin
+            .take()
+            .map(|s| Box::new(s) as Box<dyn AsyncWrite + Unpin + Send>)
+    }
 }
 
 #[derive(Clone, Debug, clap::Parser)]
@@ -128,6 +135,7 @@ pub struct SshChild {
     _session: Handle<Client>,
     stdout: Option<Box<dyn AsyncBufRead + Unpin + Send>>,
     stderr: Option<Box<dyn AsyncBufRead + Unpin + Send>>,
+    stdin: Option<Box<dyn AsyncWrite + Unpin + Send>>,
 }
 
 impl Process for SshChild {
@@ -138,6 +146,10 @@ impl Process for SshChild {
     fn stderr(&mut self) -> Option<Box<dyn AsyncBufRead + Unpin + Send>> {
         self.stderr.take()
     }
+
+    fn stdin(&mut self) -> Option<Box<dyn AsyncWrite + Unpin + Send>> {
+        self.stdin.take()
+    }
 }
 
 struct Client {
@@ -243,6 +255,8 @@ pub async fn launch_on_remote(
     let mut channel = session.channel_open_session().await?;
     channel.exec(true, launch_cmd).await?;
 
+    // The writer stays usable after the channel moves into the task below
+    let stdin = Box::pin(channel.make_writer());
     let (stdout_tx, stdout_rx) = mpsc::channel(100);
     let (stderr_tx, stderr_rx) = mpsc::channel(100);
 
@@ -276,6 +290,7 @@ pub async fn launch_on_remote(
         _session: session,
         stdout: Some(Box::new(BufReader::new(ByteStream::new(stdout_rx)))),
         stderr: Some(Box::new(BufReader::new(ByteStream::new(stderr_rx)))),
+        stdin: Some(Box::new(stdin)),
     })
 }
 
@@ -0,0 +1,68 @@
+// Throughput of reading `scontrol show jobs --details` on a large cluster.
+// Run with `cargo bench -p slurm-common`.
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use slurm_common::parser::{self, RecordReader};
+use slurm_common::scontrol::JobInfo;
+
+// A running job with two allocation lines, {id} is replaced by the job id
+const JOB: &str = "JobId={id} JobName=mpi_{id}
+   UserId=bob(140002) GroupId=bob(100140002) MCS_label=N/A
+   Priority=10450 Nice=0 Account=mit_general QOS=normal
+   JobState=RUNNING Reason=None Dependency=(null)
+   Requeue=0 Restarts=0 BatchFlag=1 Reboot=0 ExitCode=0:0
+   RunTime=01:02:03 TimeLimit=2-00:00:00 TimeMin=N/A
+   SubmitTime=2026-01-31T11:00:00 EligibleTime=2026-01-31T11:00:00
+   AccrueTime=2026-01-31T11:00:00
+   StartTime=2026-01-31T11:00:01 EndTime=2026-02-02T11:00:01 Deadline=N/A
+   SuspendTime=None SecsPreSuspend=0 LastSchedEval=2026-01-31T11:00:01 Scheduler=Main
+   Partition=sched_mit_hill AllocNode:Sid=node2429:26654
+   ReqNodeList=(null) ExcNodeList=(null)
+   NodeList=node[101,205]
+   BatchHost=node101
+   NumNodes=2 NumCPUs=9 NumTasks=9 CPUs/Task=1 ReqB:S:C:T=0:0:*:*
+   ReqTRES=cpu=9,mem=18G,node=2,billing=9,gres/gpu=3
+   AllocTRES=cpu=9,mem=18G,node=2,billing=9,gres/gpu=3,gres/gpu:a100=3
+   Socks/Node=* NtasksPerN:B:S:C=0:0:*:* CoreSpec=*
+     Nodes=node101 CPU_IDs=0-3,8 Mem=10000 GRES=gpu:a100:2(IDX:0-1)
+     Nodes=node205 CPU_IDs=0-3 Mem=8000 GRES=gpu:a100:1(IDX:3)
+   MinCPUsNode=1 MinMemoryNode=2G MinTmpDiskNode=0
+   Features=(null) DelayBoot=00:00:00
+   OverSubscribe=OK Contiguous=0 Licenses=(null) LicensesAlloc=(null) Network=(null)
+   Command=/home/bob/run.sh --input /home/bob/data/{id}.h5
+   WorkDir=/home/bob
+   StdErr=/home/bob/slurm-{id}.out
+   StdOut=/home/bob/slurm-{id}.out
+";
+
+fn jobs(count: usize) -> String {
+    (0..count)
+        .map(|i| JOB.replace("{id}", &(8_600_000 + i).to_string()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn parse_jobs(c: &mut Criterion) {
+    let output = jobs(10_000);
+    let mut group = c.benchmark_group("scontrol show jobs");
+    group.throughput(Throughput::Bytes(output.len() as u64));
+    group.sample_size(20);
+    group.bench_function("from_str", |b| {
+        b.iter(|| parser::from_str::<Vec<JobInfo>>(&output).unwrap().len())
+    });
+    // The way the worker reads scontrol, one record in memory at a time
+    group.bench_function("RecordReader", |b| {
+        b.iter(|| {
+            let mut reader = RecordReader::new(output.as_bytes());
+            let mut count = 0;
+            while let Some(record) = reader.next_record().unwrap() {
+                record.deserialize::<JobInfo>().unwrap();
+                count += 1;
+            }
+            count
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse_jobs);
+criterion_main!(benches);
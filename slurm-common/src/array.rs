@@ -0,0 +1,91 @@
+// Job arrays, summarized from the array tasks in the queue
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{Job, JobStatus};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ArrayJob {
+    pub array_job_id: i64,
+    pub name: String,
+    pub user: String,
+    pub partition: String,
+    // The tasks still in the queue, pending tasks that have not been split off
+    // yet count one each
+    pub tasks: u32,
+    pub states: BTreeMap<JobStatus, u32>,
+}
+
+pub fn arrays<'a>(jobs: impl IntoIterator<Item = &'a Job>) -> Vec<ArrayJob> {
+    let mut arrays: BTreeMap<i64, ArrayJob> = BTreeMap::new();
+    for job in jobs.into_iter().filter(|j| j.job_id.is_array()) {
+        let array = arrays.entry(job.job_id.id).or_insert_with(|| ArrayJob {
+            array_job_id: job.job_id.id,
+            name: job.name.clone(),
+            user: job.user.clone(),
+            partition: job.partition.clone(),
+            tasks: 0,
+            states: BTreeMap::new(),
+        });
+        let count = job.job_id.task_count();
+        array.tasks += count;
+        *array.states.entry(job.status).or_default() += count;
+    }
+    arrays.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JobId;
+    use chrono::Utc;
+
+    fn job(job_id: JobId, status: JobStatus) -> Job {
+        Job {
+            job_id,
+            name: "sweep".to_string(),
+            user: "alice".to_string(),
+            account: None,
+            partition: "sched_mit_hill".to_string(),
+            qos: None,
+            status,
+            reason: None,
+            time_limit: None,
+            start_time: None,
+            end_time: None,
+            submit_time: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_arrays() {
+        let jobs = [
+            job(JobId::task(100, 1), JobStatus::Running),
+            job(JobId::task(100, 2), JobStatus::Running),
+            job(JobId::task(100, 3), JobStatus::Completing),
+            job(JobId::tasks(100, "4-100%2"), JobStatus::Pending),
+            job(JobId::task(200, 0), JobStatus::Pending),
+            job(JobId::new(300), JobStatus::Running),
+            job(JobId::component(400, 1), JobStatus::Running),
+        ];
+        let arrays = arrays(&jobs);
+        assert_eq!(arrays.len(), 2);
+
+        let sweep = &arrays[0];
+        assert_eq!((sweep.array_job_id, sweep.tasks), (100, 100));
+        assert_eq!(
+            sweep.states,
+            BTreeMap::from([
+                (JobStatus::Pending, 97),
+                (JobStatus::Running, 2),
+                (JobStatus::Completing, 1),
+            ])
+        );
+        assert_eq!(arrays[1].array_job_id, 200);
+        assert_eq!(
+            serde_json::to_value(&arrays[1].states).unwrap(),
+            serde_json::json!({"Pending": 1})
+        );
+    }
+}
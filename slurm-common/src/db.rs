@@ -1,19 +1,26 @@
 use crate::table::Table;
 use crate::{
-    ClusterDiff, ClusterState, Job, JobAllocation, JobId, JobResource, JobStatus, Node, NodeName,
-    NodePartition, NodeResource, NodeStatus, Partition, PartitionStatus, ResourceType,
+    Association, ClusterDiff, ClusterState, Job, JobAllocation, JobId, JobPriority, JobRecord,
+    JobResource, JobStatus, JobStep, Node, NodeName, NodePartition, NodeReservation, NodeResource,
+    NodeState, NodeStatus, Partition, PartitionStatus, Qos, Reservation, ResourceType,
+    ResourceUnit,
 };
 use anyhow::Result;
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use sqlx::{sqlite::SqliteRow, FromRow, Pool, Row, Sqlite};
+use std::collections::BTreeMap;
 
 // --- Node ---
 
 impl<'r> FromRow<'r, SqliteRow> for Node {
     fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
         let name_str: String = row.try_get("name")?;
+        // Rows written before the state had flags only have the base state
         let status_str: String = row.try_get("status")?;
-        let status = serde_json::from_str(&status_str).unwrap_or(NodeStatus::Unknown);
+        let state = serde_json::from_str::<NodeState>(&status_str)
+            .or_else(|_| serde_json::from_str::<NodeStatus>(&status_str).map(NodeState::from))
+            .unwrap_or(NodeState::from(NodeStatus::Unknown));
 
         // CPU stats
         let cpus: i64 = row.try_get("cpus")?;
@@ -29,7 +36,10 @@ impl<'r> FromRow<'r, SqliteRow> for Node {
 
         Ok(Node {
             name: NodeName(name_str),
-            status,
+            state,
+            reason: row.try_get("reason")?,
+            reason_user: row.try_get("reason_user")?,
+            reason_time: row.try_get("reason_time")?,
             cpus: cpus as u32,
             cpus_alloc: cpus_alloc as u32,
             cpus_idle: cpus_idle as u32,
@@ -37,6 +47,9 @@ impl<'r> FromRow<'r, SqliteRow> for Node {
             memory_alloc,
             memory_free,
             partitions: Vec::new(), // Populated manually if needed, or left empty knowing it's in node_partitions
+            boot_time: row.try_get("boot_time")?,
+            slurmd_start_time: row.try_get("slurmd_start_time")?,
+            last_busy_time: row.try_get("last_busy_time")?,
             updated_at,
         })
     }
@@ -50,29 +63,42 @@ pub async fn fetch_all_nodes(pool: &Pool<Sqlite>) -> Result<Vec<Node>> {
 }
 
 pub async fn upsert_node(pool: &Pool<Sqlite>, node: &Node) -> Result<()> {
-    let status = serde_json::to_string(&node.status).unwrap_or_default();
+    let status = serde_json::to_string(&node.state).unwrap_or_default();
     sqlx::query!(
         r#"
-        INSERT INTO nodes (name, status, cpus, cpus_alloc, cpus_idle, memory, memory_alloc, memory_free, updated_at)
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        INSERT INTO nodes (name, status, reason, reason_user, reason_time, cpus, cpus_alloc, cpus_idle, memory, memory_alloc, memory_free,
+                           boot_time, slurmd_start_time, last_busy_time, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         ON CONFLICT(name) DO UPDATE SET
             status = excluded.status,
+            reason = excluded.reason,
+            reason_user = excluded.reason_user,
+            reason_time = excluded.reason_time,
             cpus = excluded.cpus,
             cpus_alloc = excluded.cpus_alloc,
             cpus_idle = excluded.cpus_idle,
             memory = excluded.memory,
             memory_alloc = excluded.memory_alloc,
             memory_free = excluded.memory_free,
+            boot_time = excluded.boot_time,
+            slurmd_start_time = excluded.slurmd_start_time,
+            last_busy_time = excluded.last_busy_time,
             updated_at = excluded.updated_at
         "#,
         node.name.0,
         status,
+        node.reason,
+        node.reason_user,
+        node.reason_time,
         node.cpus,
         node.cpus_alloc,
         node.cpus_idle,
         node.memory,
         node.memory_alloc,
         node.memory_free,
+        node.boot_time,
+        node.slurmd_start_time,
+        node.last_busy_time,
         node.updated_at
     )
     .execute(pool)
@@ -147,7 +173,7 @@ impl<'r> FromRow<'r, SqliteRow> for NodeResource {
         let total: i64 = row.try_get("total")?;
         Ok(NodeResource {
             node: NodeName(node),
-            resource: ResourceType(resource),
+            resource: ResourceType::new(&resource),
             available: available as u64,
             total: total as u64,
         })
@@ -165,16 +191,21 @@ pub async fn upsert_node_resource(pool: &Pool<Sqlite>, item: &NodeResource) -> R
     // Cast u64 to i64 for sqlite
     let available = item.available as i64;
     let total = item.total as i64;
+    let resource = item.resource.to_string();
+    let kind = item.resource.kind.as_str();
     sqlx::query!(
         r#"
-        INSERT INTO node_resources (node, resource, available, total)
-        VALUES (?, ?, ?, ?)
+        INSERT INTO node_resources (node, resource, kind, name, gres_type, available, total)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
         ON CONFLICT(node, resource) DO UPDATE SET
             available = excluded.available,
             total = excluded.total
         "#,
         item.node.0,
-        item.resource.0,
+        resource,
+        kind,
+        item.resource.name,
+        item.resource.gres_type,
         available,
         total
     )
@@ -188,41 +219,121 @@ pub async fn delete_node_resource(
     node: &NodeName,
     resource: &ResourceType,
 ) -> Result<()> {
+    let resource = resource.to_string();
     sqlx::query!(
         "DELETE FROM node_resources WHERE node = ? AND resource = ?",
         node.0,
-        resource.0
+        resource
     )
     .execute(pool)
     .await?;
     Ok(())
 }
 
+// Restricts the resources of a partition by their parts, e.g. kind=gres, name=gpu
+// and type=a100 for the A100 GPUs. Unset fields match everything.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ResourceFilter {
+    pub kind: Option<String>,
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub gres_type: Option<String>,
+}
+
+// A resource added up over the nodes of a partition
+#[derive(Debug, Clone, Serialize)]
+pub struct PartitionResource {
+    pub resource: ResourceType,
+    pub unit: ResourceUnit,
+    pub nodes: i64,
+    pub total: i64,
+    // Nothing is available on nodes that are down, drained or draining
+    pub available: i64,
+}
+
+pub async fn fetch_partition_resources(
+    pool: &Pool<Sqlite>,
+    partition: &str,
+    filter: &ResourceFilter,
+) -> Result<Vec<PartitionResource>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT r.resource, r.total, r.available, n.status
+        FROM node_resources r
+        JOIN node_partitions p ON p.node = r.node
+        JOIN nodes n ON n.name = r.node
+        WHERE p.partition = ?1
+            AND (?2 IS NULL OR r.kind = ?2)
+            AND (?3 IS NULL OR r.name = ?3)
+            AND (?4 IS NULL OR r.gres_type = ?4)
+        "#,
+    )
+    .bind(partition)
+    .bind(&filter.kind)
+    .bind(&filter.name)
+    .bind(&filter.gres_type)
+    .fetch_all(pool)
+    .await?;
+
+    // Whether a node can take jobs depends on its state flags, so this is added up here
+    let mut items: BTreeMap<ResourceType, PartitionResource> = BTreeMap::new();
+    for row in rows {
+        let resource = ResourceType::new(row.try_get("resource")?);
+        let total: i64 = row.try_get("total")?;
+        let available: i64 = row.try_get("available")?;
+        let state = serde_json::from_str::<NodeState>(row.try_get("status")?)
+            .unwrap_or(NodeState::from(NodeStatus::Unknown));
+        let item = items
+            .entry(resource.clone())
+            .or_insert_with(|| PartitionResource {
+                unit: resource.unit(),
+                resource,
+                nodes: 0,
+                total: 0,
+                available: 0,
+            });
+        item.nodes += 1;
+        item.total += total;
+        if state.is_available() {
+            item.available += available;
+        }
+    }
+    Ok(items.into_values().collect())
+}
+
 // --- Job ---
 
 impl<'r> FromRow<'r, SqliteRow> for Job {
     fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
         let job_id_str: String = row.try_get("job_id")?;
         let user: String = row.try_get("user")?;
+        let account: Option<String> = row.try_get("account")?;
         let name: String = row.try_get("name")?;
         let partition: String = row.try_get("partition")?;
+        let qos: Option<String> = row.try_get("qos")?;
         let status_str: String = row.try_get("status")?;
+        let reason: Option<String> = row.try_get("reason")?;
         let time_limit: Option<i64> = row.try_get("time_limit")?;
         let start_time: Option<DateTime<Utc>> = row.try_get("start_time")?;
+        let end_time: Option<DateTime<Utc>> = row.try_get("end_time")?;
         let submit_time: DateTime<Utc> = row.try_get("submit_time")?;
         let updated_at: DateTime<Utc> = row.try_get("updated_at")?;
 
         let status = serde_json::from_str(&status_str).unwrap_or(JobStatus::Unknown);
-        let job_id_val = job_id_str.parse::<i64>().unwrap_or(0);
+        let job_id = job_id_str.parse().unwrap_or(JobId::new(0));
 
         Ok(Job {
-            job_id: JobId(job_id_val),
+            job_id,
             name,
             user,
+            account,
             partition,
+            qos,
             status,
+            reason,
             time_limit,
             start_time,
+            end_time,
             submit_time,
             updated_at,
         })
@@ -236,30 +347,46 @@ pub async fn fetch_all_jobs(pool: &Pool<Sqlite>) -> Result<Vec<Job>> {
     Ok(jobs)
 }
 
+pub async fn fetch_job(pool: &Pool<Sqlite>, job_id: &JobId) -> Result<Option<Job>> {
+    let job = sqlx::query_as::<_, Job>("SELECT * FROM jobs WHERE job_id = ?")
+        .bind(job_id.to_string())
+        .fetch_optional(pool)
+        .await?;
+    Ok(job)
+}
+
 pub async fn upsert_job(pool: &Pool<Sqlite>, job: &Job) -> Result<()> {
     let status = serde_json::to_string(&job.status).unwrap_or_default();
-    let job_id_str = job.job_id.0.to_string();
+    let job_id_str = job.job_id.to_string();
     sqlx::query!(
         r#"
-        INSERT INTO jobs (job_id, name, user, partition, status, time_limit, start_time, submit_time, updated_at)
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        INSERT INTO jobs (job_id, name, user, account, partition, qos, status, reason, time_limit, start_time, end_time, submit_time, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         ON CONFLICT(job_id) DO UPDATE SET
             name = excluded.name,
             user = excluded.user,
+            account = excluded.account,
             partition = excluded.partition,
+            qos = excluded.qos,
             status = excluded.status,
+            reason = excluded.reason,
             time_limit = excluded.time_limit,
             start_time = excluded.start_time,
+            end_time = excluded.end_time,
             submit_time = excluded.submit_time,
             updated_at = excluded.updated_at
         "#,
         job_id_str,
         job.name,
         job.user,
+        job.account,
         job.partition,
+        job.qos,
         status,
+        job.reason,
         job.time_limit,
         job.start_time,
+        job.end_time,
         job.submit_time,
         job.updated_at
     )
@@ -269,7 +396,7 @@ pub async fn upsert_job(pool: &Pool<Sqlite>, job: &Job) -> Result<()> {
 }
 
 pub async fn delete_job(pool: &Pool<Sqlite>, job_id: &JobId) -> Result<()> {
-    let job_id_str = job_id.0.to_string();
+    let job_id_str = job_id.to_string();
     sqlx::query!("DELETE FROM jobs WHERE job_id = ?", job_id_str)
         .execute(pool)
         .await?;
@@ -285,11 +412,11 @@ impl<'r> FromRow<'r, SqliteRow> for JobResource {
         let requested: i64 = row.try_get("requested")?;
         let allocated: i64 = row.try_get("allocated")?;
 
-        let job_id_val = job_id_str.parse::<i64>().unwrap_or(0);
+        let job_id = job_id_str.parse().unwrap_or(JobId::new(0));
 
         Ok(JobResource {
-            job: JobId(job_id_val),
-            resource: ResourceType(resource),
+            job: job_id,
+            resource: ResourceType::new(&resource),
             requested,
             allocated,
         })
@@ -303,18 +430,31 @@ pub async fn fetch_all_job_resources(pool: &Pool<Sqlite>) -> Result<Vec<JobResou
     Ok(items)
 }
 
+pub async fn fetch_job_resources(pool: &Pool<Sqlite>, job_id: &JobId) -> Result<Vec<JobResource>> {
+    let items = sqlx::query_as::<_, JobResource>("SELECT * FROM job_resources WHERE job_id = ?")
+        .bind(job_id.to_string())
+        .fetch_all(pool)
+        .await?;
+    Ok(items)
+}
+
 async fn upsert_job_resource(pool: &Pool<Sqlite>, item: &JobResource) -> Result<()> {
-    let job_id_str = item.job.0.to_string();
+    let job_id_str = item.job.to_string();
+    let resource = item.resource.to_string();
+    let kind = item.resource.kind.as_str();
     sqlx::query!(
         r#"
-        INSERT INTO job_resources (job_id, resource, requested, allocated)
-        VALUES (?, ?, ?, ?)
+        INSERT INTO job_resources (job_id, resource, kind, name, gres_type, requested, allocated)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
         ON CONFLICT(job_id, resource) DO UPDATE SET
             requested = excluded.requested,
             allocated = excluded.allocated
         "#,
         job_id_str,
-        item.resource.0,
+        resource,
+        kind,
+        item.resource.name,
+        item.resource.gres_type,
         item.requested,
         item.allocated
     )
@@ -328,11 +468,12 @@ async fn delete_job_resource(
     job_id: &JobId,
     resource: &ResourceType,
 ) -> Result<()> {
-    let job_id_str = job_id.0.to_string();
+    let job_id_str = job_id.to_string();
+    let resource = resource.to_string();
     sqlx::query!(
         "DELETE FROM job_resources WHERE job_id = ? AND resource = ?",
         job_id_str,
-        resource.0
+        resource
     )
     .execute(pool)
     .await?;
@@ -348,12 +489,12 @@ impl<'r> FromRow<'r, SqliteRow> for JobAllocation {
         let resource: String = row.try_get("resource")?;
         let used: i64 = row.try_get("used")?;
 
-        let job_id_val = job_id_str.parse::<i64>().unwrap_or(0);
+        let job_id = job_id_str.parse().unwrap_or(JobId::new(0));
 
         Ok(JobAllocation {
-            job: JobId(job_id_val),
+            job: job_id,
             node: NodeName(node),
-            resource: ResourceType(resource),
+            resource: ResourceType::new(&resource),
             used,
         })
     }
@@ -366,8 +507,21 @@ pub async fn fetch_all_job_allocations(pool: &Pool<Sqlite>) -> Result<Vec<JobAll
     Ok(items)
 }
 
+pub async fn fetch_job_allocations(
+    pool: &Pool<Sqlite>,
+    job_id: &JobId,
+) -> Result<Vec<JobAllocation>> {
+    let items =
+        sqlx::query_as::<_, JobAllocation>("SELECT * FROM job_allocations WHERE job_id = ?")
+            .bind(job_id.to_string())
+            .fetch_all(pool)
+            .await?;
+    Ok(items)
+}
+
 pub async fn upsert_job_allocation(pool: &Pool<Sqlite>, item: &JobAllocation) -> Result<()> {
-    let job_id_str = item.job.0.to_string();
+    let job_id_str = item.job.to_string();
+    let resource = item.resource.to_string();
     sqlx::query!(
         r#"
         INSERT INTO job_allocations (job_id, node, resource, used)
@@ -377,7 +531,7 @@ pub async fn upsert_job_allocation(pool: &Pool<Sqlite>, item: &JobAllocation) ->
         "#,
         job_id_str,
         item.node.0,
-        item.resource.0,
+        resource,
         item.used
     )
     .execute(pool)
@@ -391,12 +545,307 @@ pub async fn delete_job_allocation(
     node: &NodeName,
     resource: &ResourceType,
 ) -> Result<()> {
-    let job_id_str = job_id.0.to_string();
+    let job_id_str = job_id.to_string();
+    let resource = resource.to_string();
     sqlx::query!(
         "DELETE FROM job_allocations WHERE job_id = ? AND node = ? AND resource = ?",
         job_id_str,
         node.0,
-        resource.0
+        resource
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// --- Job Priority ---
+
+impl<'r> FromRow<'r, SqliteRow> for JobPriority {
+    fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+        let job_id_str: String = row.try_get("job_id")?;
+        let rank: i64 = row.try_get("rank")?;
+        let job_id = job_id_str.parse().unwrap_or(JobId::new(0));
+
+        Ok(JobPriority {
+            job: job_id,
+            partition: row.try_get("partition")?,
+            priority: row.try_get("priority")?,
+            age_factor: row.try_get("age_factor")?,
+            fairshare_factor: row.try_get("fairshare_factor")?,
+            job_size_factor: row.try_get("job_size_factor")?,
+            partition_factor: row.try_get("partition_factor")?,
+            qos_factor: row.try_get("qos_factor")?,
+            tres_factor: row.try_get("tres_factor")?,
+            rank: rank as u32,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+pub async fn fetch_all_job_priorities(pool: &Pool<Sqlite>) -> Result<Vec<JobPriority>> {
+    let items = sqlx::query_as::<_, JobPriority>("SELECT * FROM job_priorities")
+        .fetch_all(pool)
+        .await?;
+    Ok(items)
+}
+
+pub async fn fetch_job_priority(
+    pool: &Pool<Sqlite>,
+    job_id: &JobId,
+) -> Result<Option<JobPriority>> {
+    let item = sqlx::query_as::<_, JobPriority>("SELECT * FROM job_priorities WHERE job_id = ?")
+        .bind(job_id.to_string())
+        .fetch_optional(pool)
+        .await?;
+    Ok(item)
+}
+
+pub async fn upsert_job_priority(pool: &Pool<Sqlite>, item: &JobPriority) -> Result<()> {
+    let job_id_str = item.job.to_string();
+    sqlx::query!(
+        r#"
+        INSERT INTO job_priorities (job_id, partition, priority, age_factor, fairshare_factor, job_size_factor,
+            partition_factor, qos_factor, tres_factor, rank, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(job_id) DO UPDATE SET
+            partition = excluded.partition,
+            priority = excluded.priority,
+            age_factor = excluded.age_factor,
+            fairshare_factor = excluded.fairshare_factor,
+            job_size_factor = excluded.job_size_factor,
+            partition_factor = excluded.partition_factor,
+            qos_factor = excluded.qos_factor,
+            tres_factor = excluded.tres_factor,
+            rank = excluded.rank,
+            updated_at = excluded.updated_at
+        "#,
+        job_id_str,
+        item.partition,
+        item.priority,
+        item.age_factor,
+        item.fairshare_factor,
+        item.job_size_factor,
+        item.partition_factor,
+        item.qos_factor,
+        item.tres_factor,
+        item.rank,
+        item.updated_at
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_job_priority(pool: &Pool<Sqlite>, job_id: &JobId) -> Result<()> {
+    let job_id_str = job_id.to_string();
+    sqlx::query!("DELETE FROM job_priorities WHERE job_id = ?", job_id_str)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// --- Job Step ---
+
+impl<'r> FromRow<'r, SqliteRow> for JobStep {
+    fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+        let job_id_str: String = row.try_get("job_id")?;
+        let status_str: String = row.try_get("status")?;
+        let tasks: i64 = row.try_get("tasks")?;
+        let status = serde_json::from_str(&status_str).unwrap_or(JobStatus::Unknown);
+        let job_id = job_id_str.parse().unwrap_or(JobId::new(0));
+
+        Ok(JobStep {
+            job: job_id,
+            step: row.try_get("step")?,
+            name: row.try_get("name")?,
+            status,
+            nodes: json(row, "nodes")?,
+            tasks: tasks as u32,
+            start_time: row.try_get("start_time")?,
+            ave_cpu: row.try_get("ave_cpu")?,
+            max_rss: row.try_get("max_rss")?,
+            max_disk_read: row.try_get("max_disk_read")?,
+            max_disk_write: row.try_get("max_disk_write")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+pub async fn fetch_all_job_steps(pool: &Pool<Sqlite>) -> Result<Vec<JobStep>> {
+    let items = sqlx::query_as::<_, JobStep>("SELECT * FROM job_steps")
+        .fetch_all(pool)
+        .await?;
+    Ok(items)
+}
+
+pub async fn fetch_job_steps(pool: &Pool<Sqlite>, job_id: &JobId) -> Result<Vec<JobStep>> {
+    let items = sqlx::query_as::<_, JobStep>(
+        "SELECT * FROM job_steps WHERE job_id = ? ORDER BY start_time",
+    )
+    .bind(job_id.to_string())
+    .fetch_all(pool)
+    .await?;
+    Ok(items)
+}
+
+pub async fn upsert_job_step(pool: &Pool<Sqlite>, item: &JobStep) -> Result<()> {
+    let job_id_str = item.job.to_string();
+    let status = serde_json::to_string(&item.status).unwrap_or_default();
+    let nodes = serde_json::to_string(&item.nodes)?;
+    sqlx::query!(
+        r#"
+        INSERT INTO job_steps (job_id, step, name, status, nodes, tasks, start_time, ave_cpu, max_rss,
+            max_disk_read, max_disk_write, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(job_id, step) DO UPDATE SET
+            name = excluded.name,
+            status = excluded.status,
+            nodes = excluded.nodes,
+            tasks = excluded.tasks,
+            start_time = excluded.start_time,
+            ave_cpu = excluded.ave_cpu,
+            max_rss = excluded.max_rss,
+            max_disk_read = excluded.max_disk_read,
+            max_disk_write = excluded.max_disk_write,
+            updated_at = excluded.updated_at
+        "#,
+        job_id_str,
+        item.step,
+        item.name,
+        status,
+        nodes,
+        item.tasks,
+        item.start_time,
+        item.ave_cpu,
+        item.max_rss,
+        item.max_disk_read,
+        item.max_disk_write,
+        item.updated_at
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_job_step(pool: &Pool<Sqlite>, job_id: &JobId, step: &str) -> Result<()> {
+    let job_id_str = job_id.to_string();
+    sqlx::query!(
+        "DELETE FROM job_steps WHERE job_id = ? AND step = ?",
+        job_id_str,
+        step
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// --- Job History ---
+
+impl<'r> FromRow<'r, SqliteRow> for JobRecord {
+    fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+        let job_id_str: String = row.try_get("job_id")?;
+        let status_str: String = row.try_get("status")?;
+        let status = serde_json::from_str(&status_str).unwrap_or(JobStatus::Unknown);
+        let job_id = job_id_str.parse().unwrap_or(JobId::new(0));
+
+        Ok(JobRecord {
+            job_id,
+            name: row.try_get("name")?,
+            user: row.try_get("user")?,
+            account: row.try_get("account")?,
+            partition: row.try_get("partition")?,
+            status,
+            exit_code: row.try_get("exit_code")?,
+            exit_signal: row.try_get("exit_signal")?,
+            submit_time: row.try_get("submit_time")?,
+            start_time: row.try_get("start_time")?,
+            end_time: row.try_get("end_time")?,
+            elapsed: row.try_get("elapsed")?,
+            total_cpu: row.try_get("total_cpu")?,
+            max_rss: row.try_get("max_rss")?,
+        })
+    }
+}
+
+// Restricts the job history, unset fields match everything
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HistoryFilter {
+    pub user: Option<String>,
+    pub account: Option<String>,
+    pub partition: Option<String>,
+    // Jobs that ended in [since, until)
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+const DEFAULT_HISTORY_LIMIT: i64 = 1000;
+
+pub async fn fetch_job_history(
+    pool: &Pool<Sqlite>,
+    filter: &HistoryFilter,
+) -> Result<Vec<JobRecord>> {
+    let items = sqlx::query_as::<_, JobRecord>(
+        r#"
+        SELECT * FROM job_history
+        WHERE (?1 IS NULL OR user = ?1)
+            AND (?2 IS NULL OR account = ?2)
+            AND (?3 IS NULL OR partition = ?3)
+            AND (?4 IS NULL OR end_time >= ?4)
+            AND (?5 IS NULL OR end_time < ?5)
+        ORDER BY end_time DESC
+        LIMIT ?6
+        "#,
+    )
+    .bind(&filter.user)
+    .bind(&filter.account)
+    .bind(&filter.partition)
+    .bind(filter.since)
+    .bind(filter.until)
+    .bind(filter.limit.unwrap_or(DEFAULT_HISTORY_LIMIT))
+    .fetch_all(pool)
+    .await?;
+    Ok(items)
+}
+
+// The same job can be reported by several polls, the last report wins
+pub async fn upsert_job_record(pool: &Pool<Sqlite>, item: &JobRecord) -> Result<()> {
+    let status = serde_json::to_string(&item.status).unwrap_or_default();
+    let job_id_str = item.job_id.to_string();
+    sqlx::query!(
+        r#"
+        INSERT INTO job_history (job_id, name, user, account, partition, status, exit_code, exit_signal,
+            submit_time, start_time, end_time, elapsed, total_cpu, max_rss)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(job_id) DO UPDATE SET
+            name = excluded.name,
+            user = excluded.user,
+            account = excluded.account,
+            partition = excluded.partition,
+            status = excluded.status,
+            exit_code = excluded.exit_code,
+            exit_signal = excluded.exit_signal,
+            submit_time = excluded.submit_time,
+            start_time = excluded.start_time,
+            end_time = excluded.end_time,
+            elapsed = excluded.elapsed,
+            total_cpu = excluded.total_cpu,
+            max_rss = excluded.max_rss
+        "#,
+        job_id_str,
+        item.name,
+        item.user,
+        item.account,
+        item.partition,
+        status,
+        item.exit_code,
+        item.exit_signal,
+        item.submit_time,
+        item.start_time,
+        item.end_time,
+        item.elapsed,
+        item.total_cpu,
+        item.max_rss
     )
     .execute(pool)
     .await?;
@@ -421,6 +870,19 @@ impl<'r> FromRow<'r, SqliteRow> for Partition {
             status,
             access_qos,
             resource_qos,
+            nodes: row.try_get("nodes")?,
+            total_nodes: row.try_get("total_nodes")?,
+            total_cpus: row.try_get("total_cpus")?,
+            max_time: row.try_get("max_time")?,
+            default_time: row.try_get("default_time")?,
+            max_nodes: row.try_get("max_nodes")?,
+            min_nodes: row.try_get("min_nodes")?,
+            priority_tier: row.try_get("priority_tier")?,
+            preempt_mode: row.try_get("preempt_mode")?,
+            over_subscribe: row.try_get("over_subscribe")?,
+            allow_accounts: json(row, "allow_accounts")?,
+            allow_groups: json(row, "allow_groups")?,
+            billing_weights: json(row, "billing_weights")?,
             updated_at,
         })
     }
@@ -435,20 +897,51 @@ pub async fn fetch_all_partitions(pool: &Pool<Sqlite>) -> Result<Vec<Partition>>
 
 pub async fn upsert_partition(pool: &Pool<Sqlite>, part: &Partition) -> Result<()> {
     let status = serde_json::to_string(&part.status).unwrap_or_default();
+    let allow_accounts = serde_json::to_string(&part.allow_accounts)?;
+    let allow_groups = serde_json::to_string(&part.allow_groups)?;
+    let billing_weights = serde_json::to_string(&part.billing_weights)?;
     sqlx::query!(
         r#"
-        INSERT INTO partitions (name, status, access_qos, resource_qos, updated_at)
-        VALUES (?, ?, ?, ?, ?)
+        INSERT INTO partitions (name, status, access_qos, resource_qos, nodes, total_nodes, total_cpus,
+                                max_time, default_time, max_nodes, min_nodes, priority_tier, preempt_mode,
+                                over_subscribe, allow_accounts, allow_groups, billing_weights, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         ON CONFLICT(name) DO UPDATE SET
             status = excluded.status,
             access_qos = excluded.access_qos,
             resource_qos = excluded.resource_qos,
+            nodes = excluded.nodes,
+            total_nodes = excluded.total_nodes,
+            total_cpus = excluded.total_cpus,
+            max_time = excluded.max_time,
+            default_time = excluded.default_time,
+            max_nodes = excluded.max_nodes,
+            min_nodes = excluded.min_nodes,
+            priority_tier = excluded.priority_tier,
+            preempt_mode = excluded.preempt_mode,
+            over_subscribe = excluded.over_subscribe,
+            allow_accounts = excluded.allow_accounts,
+            allow_groups = excluded.allow_groups,
+            billing_weights = excluded.billing_weights,
             updated_at = excluded.updated_at
         "#,
         part.name,
         status,
         part.access_qos,
         part.resource_qos,
+        part.nodes,
+        part.total_nodes,
+        part.total_cpus,
+        part.max_time,
+        part.default_time,
+        part.max_nodes,
+        part.min_nodes,
+        part.priority_tier,
+        part.preempt_mode,
+        part.over_subscribe,
+        allow_accounts,
+        allow_groups,
+        billing_weights,
         part.updated_at
     )
     .execute(pool)
@@ -463,6 +956,277 @@ pub async fn delete_partition(pool: &Pool<Sqlite>, name: &str) -> Result<()> {
     Ok(())
 }
 
+// --- QOS ---
+
+// Lists and maps are stored as JSON
+fn json<T: serde::de::DeserializeOwned + Default>(
+    row: &SqliteRow,
+    column: &str,
+) -> Result<T, sqlx::Error> {
+    let value: String = row.try_get(column)?;
+    Ok(serde_json::from_str(&value).unwrap_or_default())
+}
+
+impl<'r> FromRow<'r, SqliteRow> for Qos {
+    fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+        Ok(Qos {
+            name: row.try_get("name")?,
+            priority: row.try_get("priority")?,
+            preempt: json(row, "preempt")?,
+            preempt_mode: row.try_get("preempt_mode")?,
+            grp_tres: json(row, "grp_tres")?,
+            grp_jobs: row.try_get("grp_jobs")?,
+            max_tres_per_job: json(row, "max_tres_per_job")?,
+            max_wall: row.try_get("max_wall")?,
+            max_tres_per_user: json(row, "max_tres_per_user")?,
+            max_jobs_per_user: row.try_get("max_jobs_per_user")?,
+            max_submit_per_user: row.try_get("max_submit_per_user")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+pub async fn fetch_all_qos(pool: &Pool<Sqlite>) -> Result<Vec<Qos>> {
+    let items = sqlx::query_as::<_, Qos>("SELECT * FROM qos ORDER BY name")
+        .fetch_all(pool)
+        .await?;
+    Ok(items)
+}
+
+pub async fn upsert_qos(pool: &Pool<Sqlite>, item: &Qos) -> Result<()> {
+    let preempt = serde_json::to_string(&item.preempt)?;
+    let grp_tres = serde_json::to_string(&item.grp_tres)?;
+    let max_tres_per_job = serde_json::to_string(&item.max_tres_per_job)?;
+    let max_tres_per_user = serde_json::to_string(&item.max_tres_per_user)?;
+    sqlx::query!(
+        r#"
+        INSERT INTO qos (name, priority, preempt, preempt_mode, grp_tres, grp_jobs, max_tres_per_job, max_wall,
+            max_tres_per_user, max_jobs_per_user, max_submit_per_user, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(name) DO UPDATE SET
+            priority = excluded.priority,
+            preempt = excluded.preempt,
+            preempt_mode = excluded.preempt_mode,
+            grp_tres = excluded.grp_tres,
+            grp_jobs = excluded.grp_jobs,
+            max_tres_per_job = excluded.max_tres_per_job,
+            max_wall = excluded.max_wall,
+            max_tres_per_user = excluded.max_tres_per_user,
+            max_jobs_per_user = excluded.max_jobs_per_user,
+            max_submit_per_user = excluded.max_submit_per_user,
+            updated_at = excluded.updated_at
+        "#,
+        item.name,
+        item.priority,
+        preempt,
+        item.preempt_mode,
+        grp_tres,
+        item.grp_jobs,
+        max_tres_per_job,
+        item.max_wall,
+        max_tres_per_user,
+        item.max_jobs_per_user,
+        item.max_submit_per_user,
+        item.updated_at
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_qos(pool: &Pool<Sqlite>, name: &str) -> Result<()> {
+    sqlx::query!("DELETE FROM qos WHERE name = ?", name)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// --- Reservation ---
+
+impl<'r> FromRow<'r, SqliteRow> for Reservation {
+    fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+        Ok(Reservation {
+            name: row.try_get("name")?,
+            start_time: row.try_get("start_time")?,
+            end_time: row.try_get("end_time")?,
+            active: row.try_get("active")?,
+            nodes: json(row, "nodes")?,
+            partition: row.try_get("partition")?,
+            users: json(row, "users")?,
+            accounts: json(row, "accounts")?,
+            flags: json(row, "flags")?,
+            tres: json(row, "tres")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+pub async fn fetch_all_reservations(pool: &Pool<Sqlite>) -> Result<Vec<Reservation>> {
+    let items = sqlx::query_as::<_, Reservation>("SELECT * FROM reservations ORDER BY start_time")
+        .fetch_all(pool)
+        .await?;
+    Ok(items)
+}
+
+pub async fn upsert_reservation(pool: &Pool<Sqlite>, item: &Reservation) -> Result<()> {
+    let nodes = serde_json::to_string(&item.nodes)?;
+    let users = serde_json::to_string(&item.users)?;
+    let accounts = serde_json::to_string(&item.accounts)?;
+    let flags = serde_json::to_string(&item.flags)?;
+    let tres = serde_json::to_string(&item.tres)?;
+    sqlx::query!(
+        r#"
+        INSERT INTO reservations (name, start_time, end_time, active, nodes, partition, users, accounts, flags, tres, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(name) DO UPDATE SET
+            start_time = excluded.start_time,
+            end_time = excluded.end_time,
+            active = excluded.active,
+            nodes = excluded.nodes,
+            partition = excluded.partition,
+            users = excluded.users,
+            accounts = excluded.accounts,
+            flags = excluded.flags,
+            tres = excluded.tres,
+            updated_at = excluded.updated_at
+        "#,
+        item.name,
+        item.start_time,
+        item.end_time,
+        item.active,
+        nodes,
+        item.partition,
+        users,
+        accounts,
+        flags,
+        tres,
+        item.updated_at
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_reservation(pool: &Pool<Sqlite>, name: &str) -> Result<()> {
+    sqlx::query!("DELETE FROM reservations WHERE name = ?", name)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// --- Node Reservation ---
+
+impl<'r> FromRow<'r, SqliteRow> for NodeReservation {
+    fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+        let node: String = row.try_get("node")?;
+        let reservation: String = row.try_get("reservation")?;
+        Ok(NodeReservation {
+            node: NodeName(node),
+            reservation,
+        })
+    }
+}
+
+pub async fn fetch_all_node_reservations(pool: &Pool<Sqlite>) -> Result<Vec<NodeReservation>> {
+    let items = sqlx::query_as::<_, NodeReservation>("SELECT * FROM node_reservations")
+        .fetch_all(pool)
+        .await?;
+    Ok(items)
+}
+
+pub async fn upsert_node_reservation(pool: &Pool<Sqlite>, item: &NodeReservation) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO node_reservations (node, reservation)
+        VALUES (?, ?)
+        ON CONFLICT(node, reservation) DO NOTHING
+        "#,
+        item.node.0,
+        item.reservation
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_node_reservation(
+    pool: &Pool<Sqlite>,
+    node: &NodeName,
+    reservation: &str,
+) -> Result<()> {
+    sqlx::query!(
+        "DELETE FROM node_reservations WHERE node = ? AND reservation = ?",
+        node.0,
+        reservation
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// --- Association ---
+
+impl<'r> FromRow<'r, SqliteRow> for Association {
+    fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+        Ok(Association {
+            account: row.try_get("account")?,
+            user: row.try_get("user")?,
+            raw_shares: row.try_get("raw_shares")?,
+            norm_shares: row.try_get("norm_shares")?,
+            raw_usage: row.try_get("raw_usage")?,
+            effective_usage: row.try_get("effective_usage")?,
+            fairshare: row.try_get("fairshare")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+pub async fn fetch_all_associations(pool: &Pool<Sqlite>) -> Result<Vec<Association>> {
+    let items =
+        sqlx::query_as::<_, Association>("SELECT * FROM associations ORDER BY account, user")
+            .fetch_all(pool)
+            .await?;
+    Ok(items)
+}
+
+pub async fn upsert_association(pool: &Pool<Sqlite>, item: &Association) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO associations (account, user, raw_shares, norm_shares, raw_usage, effective_usage, fairshare, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(account, user) DO UPDATE SET
+            raw_shares = excluded.raw_shares,
+            norm_shares = excluded.norm_shares,
+            raw_usage = excluded.raw_usage,
+            effective_usage = excluded.effective_usage,
+            fairshare = excluded.fairshare,
+            updated_at = excluded.updated_at
+        "#,
+        item.account,
+        item.user,
+        item.raw_shares,
+        item.norm_shares,
+        item.raw_usage,
+        item.effective_usage,
+        item.fairshare,
+        item.updated_at
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_association(pool: &Pool<Sqlite>, account: &str, user: &str) -> Result<()> {
+    sqlx::query!(
+        "DELETE FROM associations WHERE account = ? AND user = ?",
+        account,
+        user
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
 // --- Cluster Status ---
 
 pub async fn fetch_cluster_state(pool: &Pool<Sqlite>) -> Result<ClusterState> {
@@ -473,6 +1237,12 @@ pub async fn fetch_cluster_state(pool: &Pool<Sqlite>) -> Result<ClusterState> {
     let node_resources_vec = fetch_all_node_resources(pool).await?;
     let job_resources_vec = fetch_all_job_resources(pool).await?;
     let job_allocations_vec = fetch_all_job_allocations(pool).await?;
+    let associations_vec = fetch_all_associations(pool).await?;
+    let job_priorities_vec = fetch_all_job_priorities(pool).await?;
+    let job_steps_vec = fetch_all_job_steps(pool).await?;
+    let qos_vec = fetch_all_qos(pool).await?;
+    let reservations_vec = fetch_all_reservations(pool).await?;
+    let node_reservations_vec = fetch_all_node_reservations(pool).await?;
 
     // Calculate updated_at as the most recent update time
     // for any of the tables
@@ -482,7 +1252,7 @@ pub async fn fetch_cluster_state(pool: &Pool<Sqlite>) -> Result<ClusterState> {
         .chain(nodes_vec.iter().map(|n| Some(n.updated_at)))
         .chain(partitions_vec.iter().map(|p| Some(p.updated_at)))
         .max()
-        .unwrap_or_else(|| None);
+        .flatten();
 
     Ok(ClusterState {
         nodes: Table::from(nodes_vec),
@@ -492,10 +1262,23 @@ pub async fn fetch_cluster_state(pool: &Pool<Sqlite>) -> Result<ClusterState> {
         node_resources: Table::from(node_resources_vec),
         job_resources: Table::from(job_resources_vec),
         job_allocations: Table::from(job_allocations_vec),
+        associations: Table::from(associations_vec),
+        job_priorities: Table::from(job_priorities_vec),
+        job_steps: Table::from(job_steps_vec),
+        qos: Table::from(qos_vec),
+        reservations: Table::from(reservations_vec),
+        node_reservations: Table::from(node_reservations_vec),
         updated_at,
     })
 }
 
+// Makes the database hold the state, whatever it held before. Rows the state doesn't
+// have are removed, so whatever diffs were missed or failed are made up for.
+pub async fn apply_snapshot(pool: &Pool<Sqlite>, state: &ClusterState) -> Result<()> {
+    let current = fetch_cluster_state(pool).await?;
+    apply_diff(pool, current.diff(state)).await
+}
+
 pub async fn apply_diff(pool: &Pool<Sqlite>, diff: ClusterDiff) -> Result<()> {
     // Partitions
     for item in diff.partitions.added {
@@ -574,5 +1357,121 @@ pub async fn apply_diff(pool: &Pool<Sqlite>, diff: ClusterDiff) -> Result<()> {
         delete_job_allocation(pool, &key.0, &key.1, &key.2).await?;
     }
 
+    // Associations
+    for item in diff.associations.added {
+        upsert_association(pool, &item).await?;
+    }
+    for item in diff.associations.changed {
+        upsert_association(pool, &item).await?;
+    }
+    for key in diff.associations.removed {
+        delete_association(pool, &key.0, &key.1).await?;
+    }
+
+    // Job Priorities
+    for item in diff.job_priorities.added {
+        upsert_job_priority(pool, &item).await?;
+    }
+    for item in diff.job_priorities.changed {
+        upsert_job_priority(pool, &item).await?;
+    }
+    for key in diff.job_priorities.removed {
+        delete_job_priority(pool, &key).await?;
+    }
+
+    // Job Steps
+    for item in diff.job_steps.added {
+        upsert_job_step(pool, &item).await?;
+    }
+    for item in diff.job_steps.changed {
+        upsert_job_step(pool, &item).await?;
+    }
+    for key in diff.job_steps.removed {
+        delete_job_step(pool, &key.0, &key.1).await?;
+    }
+
+    // QOS
+    for item in diff.qos.added {
+        upsert_qos(pool, &item).await?;
+    }
+    for item in diff.qos.changed {
+        upsert_qos(pool, &item).await?;
+    }
+    for key in diff.qos.removed {
+        delete_qos(pool, &key).await?;
+    }
+
+    // Reservations
+    for item in diff.reservations.added {
+        upsert_reservation(pool, &item).await?;
+    }
+    for item in diff.reservations.changed {
+        upsert_reservation(pool, &item).await?;
+    }
+    for key in diff.reservations.removed {
+        delete_reservation(pool, &key).await?;
+    }
+
+    // Node Reservations
+    for item in diff.node_reservations.added {
+        upsert_node_reservation(pool, &item).await?;
+    }
+    for item in diff.node_reservations.changed {
+        upsert_node_reservation(pool, &item).await?;
+    }
+    for key in diff.node_reservations.removed {
+        delete_node_reservation(pool, &key.0, &key.1).await?;
+    }
+
+    // Job History
+    for item in diff.job_history {
+        upsert_job_record(pool, &item).await?;
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn pool() -> Pool<Sqlite> {
+        // One connection, every connection to :memory: is a database of its own
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("../migrations").run(&pool).await.unwrap();
+        pool
+    }
+
+    fn state(available: u64) -> ClusterState {
+        ClusterState {
+            node_resources: Table::from(vec![NodeResource {
+                node: NodeName::new("node101"),
+                resource: ResourceType::new("cpu"),
+                total: 64,
+                available,
+            }]),
+            ..ClusterState::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_apply_snapshot() {
+        let pool = pool().await;
+        apply_snapshot(&pool, &state(64)).await.unwrap();
+        // A row that changed, e.g. after a diff was lost
+        apply_snapshot(&pool, &state(8)).await.unwrap();
+        let resources = fetch_all_node_resources(&pool).await.unwrap();
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].available, 8);
+
+        apply_snapshot(&pool, &ClusterState::default())
+            .await
+            .unwrap();
+        assert!(fetch_all_node_resources(&pool).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,313 @@
+// Slurm hostlist expressions, e.g. "gpu[01-04,07],cpu-a[1-3]"
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+// Guard against expressions like "node[0-99999999]" exhausting memory
+const MAX_HOSTS: usize = 1 << 20;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Error {
+    message: String,
+}
+
+impl Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error {
+            message: msg.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+// Splits a hostlist on the commas that are not inside brackets,
+// i.e. "gpu[01-04,07],cpu-a[1-3]" -> ["gpu[01-04,07]", "cpu-a[1-3]"]
+pub fn split(expr: &str) -> Result<Vec<&str>> {
+    let mut items = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in expr.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| Error::custom(format!("Unbalanced ']' in hostlist: {}", expr)))?
+            }
+            ',' if depth == 0 => {
+                items.push(&expr[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    if depth != 0 {
+        return Err(Error::custom(format!(
+            "Unbalanced '[' in hostlist: {}",
+            expr
+        )));
+    }
+    items.push(&expr[start..]);
+    Ok(items
+        .into_iter()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect())
+}
+
+// Expands a hostlist into the individual host names, in order.
+// Every bracket group of a host is expanded, so "r[1-2]n[1-2]" yields r1n1, r1n2, r2n1, r2n2.
+pub fn expand(expr: &str) -> Result<Vec<String>> {
+    let mut hosts = Vec::new();
+    for item in split(expr)? {
+        let mut expanded = vec![String::new()];
+        let mut rest = item;
+        while let Some(open) = rest.find('[') {
+            let close = open
+                + rest[open..].find(']').ok_or_else(|| {
+                    Error::custom(format!("Unbalanced '[' in hostlist: {}", expr))
+                })?;
+            let prefix = &rest[..open];
+            let suffixes = expand_ranges(&rest[open + 1..close])?;
+            if expanded.len() * suffixes.len() > MAX_HOSTS {
+                return Err(Error::custom(format!("Hostlist is too large: {}", expr)));
+            }
+            expanded = expanded
+                .iter()
+                .flat_map(|head| {
+                    suffixes
+                        .iter()
+                        .map(move |s| format!("{}{}{}", head, prefix, s))
+                })
+                .collect();
+            rest = &rest[close + 1..];
+        }
+        hosts.extend(expanded.into_iter().map(|head| head + rest));
+        if hosts.len() > MAX_HOSTS {
+            return Err(Error::custom(format!("Hostlist is too large: {}", expr)));
+        }
+    }
+    Ok(hosts)
+}
+
+// Expands the inside of a bracket group, e.g. "01-04,07" -> ["01", "02", "03", "04", "07"].
+// The lower bound of a range determines the zero padding of the whole range.
+fn expand_ranges(ranges: &str) -> Result<Vec<String>> {
+    let mut values = Vec::new();
+    for range in ranges.split(',').map(|r| r.trim()) {
+        let (lo, hi) = range.split_once('-').unwrap_or((range, range));
+        let invalid = || Error::custom(format!("Invalid hostlist range: [{}]", ranges));
+        if lo.is_empty() || !lo.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let start: u64 = lo.parse().map_err(|_| invalid())?;
+        let end: u64 = hi.parse().map_err(|_| invalid())?;
+        if end < start || (end - start) as usize >= MAX_HOSTS {
+            return Err(invalid());
+        }
+        let width = lo.len();
+        values.extend((start..=end).map(|n| format!("{:0width$}", n, width = width)));
+    }
+    Ok(values)
+}
+
+// Compresses host names into a hostlist, the inverse of `expand`.
+// Hosts are grouped by what surrounds their last number (and its zero padding), then the
+// groups that only differ in the number before are grouped the same way, and so on, so
+// "r1n1,r1n2,r2n1,r2n2" gives "r[1-2]n[1-2]". Groups are kept in order of first
+// appearance and the numbers within a group are sorted.
+pub fn compress<S: AsRef<str>>(hosts: &[S]) -> String {
+    // The part of each host left to group, and the part grouped already
+    let mut items: Vec<(String, String)> = hosts
+        .iter()
+        .map(|h| (h.as_ref().to_string(), String::new()))
+        .collect();
+    while items.iter().any(|(head, _)| split_number(head).is_some()) {
+        items = compress_numbers(&items);
+    }
+    items
+        .into_iter()
+        .map(|(head, tail)| head + &tail)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// Groups the items by their head without its last number, e.g. ("rack1-node", "[01-02]")
+// and ("rack2-node", "[01-02]") become ("rack", "[1-2]-node[01-02]")
+fn compress_numbers(items: &[(String, String)]) -> Vec<(String, String)> {
+    enum Group<'a> {
+        // Nothing is left to group
+        Done(&'a str, &'a str),
+        // (prefix, width of the padded number or 0 if unpadded, what follows, numbers)
+        Numbers(&'a str, usize, String, Vec<u64>),
+    }
+
+    // Unpadded numbers can join a padded group of the same width ("10" fits "[08-10]")
+    let mut padded = HashSet::new();
+    for (head, tail) in items {
+        if let Some((prefix, digits, rest, _)) = split_number(head) {
+            if digits.len() > 1 && digits.starts_with('0') {
+                padded.insert((prefix, digits.len(), rest, tail.as_str()));
+            }
+        }
+    }
+
+    let mut groups: Vec<Group> = Vec::new();
+    let mut index = HashMap::new();
+    for (head, tail) in items {
+        let Some((prefix, digits, rest, n)) = split_number(head) else {
+            groups.push(Group::Done(head, tail));
+            continue;
+        };
+        let width = if padded.contains(&(prefix, digits.len(), rest, tail.as_str())) {
+            digits.len()
+        } else {
+            0
+        };
+        match index.get(&(prefix, width, rest, tail.as_str())) {
+            Some(&i) => {
+                if let Group::Numbers(_, _, _, numbers) = &mut groups[i] {
+                    numbers.push(n);
+                }
+            }
+            None => {
+                index.insert((prefix, width, rest, tail.as_str()), groups.len());
+                groups.push(Group::Numbers(
+                    prefix,
+                    width,
+                    rest.to_string() + tail,
+                    vec![n],
+                ));
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|group| match group {
+            Group::Done(head, tail) => (head.to_string(), tail.to_string()),
+            Group::Numbers(prefix, width, rest, numbers) => {
+                (prefix.to_string(), compress_group(width, numbers) + &rest)
+            }
+        })
+        .collect()
+}
+
+// Splits "rack1-node" around its last number, into ("rack", "1", "-node", 1),
+// or returns None if there is no number
+fn split_number(host: &str) -> Option<(&str, &str, &str, u64)> {
+    let end = host.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = host[..end]
+        .trim_end_matches(|c: char| c.is_ascii_digit())
+        .len();
+    let digits = &host[start..end];
+    digits
+        .parse::<u64>()
+        .ok()
+        .map(|n| (&host[..start], digits, &host[end..], n))
+}
+
+// The numbers of a group as they go after its prefix, e.g. "[01-04,07]", or "07" alone
+fn compress_group(width: usize, mut numbers: Vec<u64>) -> String {
+    numbers.sort_unstable();
+    numbers.dedup();
+    let format = |n: u64| format!("{:0width$}", n, width = width);
+    if numbers.len() == 1 {
+        return format(numbers[0]);
+    }
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i < numbers.len() {
+        let mut j = i;
+        while j + 1 < numbers.len() && numbers[j + 1] == numbers[j] + 1 {
+            j += 1;
+        }
+        if i == j {
+            ranges.push(format(numbers[i]));
+        } else {
+            ranges.push(format!("{}-{}", format(numbers[i]), format(numbers[j])));
+        }
+        i = j + 1;
+    }
+    format!("[{}]", ranges.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(
+            expand("gpu[01-04,07],cpu-a[1-3]").unwrap(),
+            vec!["gpu01", "gpu02", "gpu03", "gpu04", "gpu07", "cpu-a1", "cpu-a2", "cpu-a3"]
+        );
+        assert_eq!(expand("node156").unwrap(), vec!["node156"]);
+        assert_eq!(expand("node1,node2").unwrap(), vec!["node1", "node2"]);
+        assert_eq!(expand("n[8-10]").unwrap(), vec!["n8", "n9", "n10"]);
+        assert_eq!(expand("n[098-100]").unwrap(), vec!["n098", "n099", "n100"]);
+        assert_eq!(
+            expand("rack[1-2]-node[01-02].ib").unwrap(),
+            vec![
+                "rack1-node01.ib",
+                "rack1-node02.ib",
+                "rack2-node01.ib",
+                "rack2-node02.ib"
+            ]
+        );
+        assert_eq!(expand("").unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_expand_invalid() {
+        assert!(expand("gpu[01-04").is_err());
+        assert!(expand("gpu01-04]").is_err());
+        assert!(expand("gpu[04-01]").is_err());
+        assert!(expand("gpu[a-b]").is_err());
+        assert!(expand("gpu[]").is_err());
+        assert!(expand("n[0-99999999]").is_err());
+    }
+
+    #[test]
+    fn test_compress() {
+        let hosts = expand("gpu[01-04,07],cpu-a[1-3]").unwrap();
+        assert_eq!(compress(&hosts), "gpu[01-04,07],cpu-a[1-3]");
+        assert_eq!(compress(&["node156"]), "node156");
+        assert_eq!(compress(&["n3", "n1", "n2", "n2"]), "n[1-3]");
+        assert_eq!(compress(&["n08", "n09", "n10"]), "n[08-10]");
+        assert_eq!(compress(&["n9", "n10"]), "n[9-10]");
+        assert_eq!(compress(&["login", "n1", "n2"]), "login,n[1-2]");
+        assert_eq!(
+            compress(&["rack1-node01", "rack2-node01"]),
+            "rack[1-2]-node01"
+        );
+        // Only groups with the same numbers after them are merged
+        assert_eq!(compress(&["r1n1", "r1n2", "r2n1"]), "r1n[1-2],r2n1");
+        assert_eq!(compress(&["n01.ib", "n02.ib", "n03"]), "n[01-02].ib,n03");
+        assert_eq!(compress::<&str>(&[]), "");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for expr in [
+            "a[1-5,7,9-11]",
+            "gpu[001-128]",
+            "x1,y[2-3],z",
+            "n[9-12]",
+            "rack[1-2]-node[01-02].ib",
+            "r[1-2]n[1-4,7]",
+            "login1,c[1-3]-g[01-08]-n[1-2]",
+            "n[1-100000]",
+        ] {
+            assert_eq!(compress(&expand(expr).unwrap()), expr);
+        }
+    }
+}
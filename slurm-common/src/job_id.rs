@@ -0,0 +1,218 @@
+// The ids of jobs as Slurm prints them, e.g. "1234", "1234_7", "1234_[5-100%10]" or "1234+1"
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum JobPart {
+    // A job that is not part of an array or het job
+    Whole,
+    // One task of an array job, e.g. "1234_7"
+    Task(u32),
+    // The pending tasks of an array job that have not been split off yet,
+    // e.g. "5-100%10" for "1234_[5-100%10]". They are kept compressed like Slurm does.
+    Tasks(String),
+    // A component of a het job, e.g. "1234+1"
+    Component(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct JobId {
+    // The id of the job, or of the array or het job it is part of
+    pub id: i64,
+    pub part: JobPart,
+}
+
+impl JobId {
+    pub fn new(id: i64) -> Self {
+        Self {
+            id,
+            part: JobPart::Whole,
+        }
+    }
+
+    pub fn task(id: i64, task: u32) -> Self {
+        Self {
+            id,
+            part: JobPart::Task(task),
+        }
+    }
+
+    pub fn tasks(id: i64, tasks: &str) -> Self {
+        Self {
+            id,
+            part: JobPart::Tasks(tasks.to_string()),
+        }
+    }
+
+    pub fn component(id: i64, offset: u32) -> Self {
+        Self {
+            id,
+            part: JobPart::Component(offset),
+        }
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self.part, JobPart::Task(_) | JobPart::Tasks(_))
+    }
+
+    // The ids of the array tasks this stands for, e.g. [5, 15, 25] for "1234_[5-25:10%2]"
+    pub fn array_tasks(&self) -> Vec<u32> {
+        match &self.part {
+            JobPart::Task(task) => vec![*task],
+            JobPart::Tasks(tasks) => task_ranges(tasks)
+                .flat_map(|(start, end, step)| (start..=end).step_by(step as usize))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    // The number of array tasks this stands for, without expanding the ranges
+    pub fn task_count(&self) -> u32 {
+        match &self.part {
+            JobPart::Task(_) => 1,
+            JobPart::Tasks(tasks) => task_ranges(tasks)
+                .map(|(start, end, step)| (end - start) / step + 1)
+                .sum(),
+            _ => 0,
+        }
+    }
+}
+
+// The (start, end, step) of each range in a list like "1-9:2,20,30-40%5".
+// The "%5" is the limit on the number of running tasks, not part of the ranges.
+fn task_ranges(tasks: &str) -> impl Iterator<Item = (u32, u32, u32)> + '_ {
+    let tasks = tasks.split_once('%').map_or(tasks, |(ranges, _)| ranges);
+    tasks.split(',').filter_map(|range| {
+        let (range, step) = match range.split_once(':') {
+            Some((range, step)) => (range, step.parse().ok().filter(|s| *s > 0)?),
+            None => (range, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            Some((start, end)) => (start.parse().ok()?, end.parse().ok()?),
+            None => (range.parse().ok()?, range.parse().ok()?),
+        };
+        (start <= end).then_some((start, end, step))
+    })
+}
+
+impl fmt::Display for JobId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.part {
+            JobPart::Whole => write!(f, "{}", self.id),
+            JobPart::Task(task) => write!(f, "{}_{}", self.id, task),
+            JobPart::Tasks(tasks) => write!(f, "{}_[{}]", self.id, tasks),
+            JobPart::Component(offset) => write!(f, "{}+{}", self.id, offset),
+        }
+    }
+}
+
+impl std::str::FromStr for JobId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let invalid = || anyhow::anyhow!("Invalid job id: {}", s);
+        let id = |id: &str| id.parse::<i64>().map_err(|_| invalid());
+        if let Some((job, task)) = s.split_once('_') {
+            return match task.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+                Some(tasks) => Ok(JobId::tasks(id(job)?, tasks)),
+                None => Ok(JobId::task(id(job)?, task.parse().map_err(|_| invalid())?)),
+            };
+        }
+        if let Some((job, offset)) = s.split_once('+') {
+            return Ok(JobId::component(
+                id(job)?,
+                offset.parse().map_err(|_| invalid())?,
+            ));
+        }
+        Ok(JobId::new(id(s)?))
+    }
+}
+
+impl Serialize for JobId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+// Job ids used to be plain numbers, those are still accepted
+impl<'de> Deserialize<'de> for JobId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct JobIdVisitor;
+
+        impl serde::de::Visitor<'_> for JobIdVisitor {
+            type Value = JobId;
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a job id like '1234', '1234_7' or '1234+1'")
+            }
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                v.parse().map_err(E::custom)
+            }
+            fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(JobId::new(v))
+            }
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(JobId::new(v as i64))
+            }
+        }
+        deserializer.deserialize_any(JobIdVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_job_id() {
+        let parse = |s: &str| s.parse::<JobId>().unwrap();
+        assert_eq!(parse("1234"), JobId::new(1234));
+        assert_eq!(parse("1234_7"), JobId::task(1234, 7));
+        assert_eq!(parse("1234_[5-100%10]"), JobId::tasks(1234, "5-100%10"));
+        assert_eq!(parse("1234+1"), JobId::component(1234, 1));
+        for id in ["1234", "1234_7", "1234_[1-9:2,20]", "1234+0"] {
+            assert_eq!(parse(id).to_string(), id);
+        }
+        assert!("1234_x".parse::<JobId>().is_err());
+        assert!("batch".parse::<JobId>().is_err());
+
+        let job = JobId::new(1234);
+        assert_eq!(serde_json::to_string(&job).unwrap(), "\"1234\"");
+        assert_eq!(serde_json::from_str::<JobId>("1234").unwrap(), job);
+        assert_eq!(
+            serde_json::from_str::<JobId>("\"1234_7\"").unwrap(),
+            JobId::task(1234, 7)
+        );
+    }
+
+    #[test]
+    fn test_array_tasks() {
+        let tasks = JobId::tasks(1234, "5-25:10%2");
+        assert!(tasks.is_array());
+        assert_eq!(tasks.array_tasks(), vec![5, 15, 25]);
+        assert_eq!(tasks.task_count(), 3);
+
+        let tasks = JobId::tasks(1234, "1-3,7,10-11");
+        assert_eq!(tasks.array_tasks(), vec![1, 2, 3, 7, 10, 11]);
+        assert_eq!(tasks.task_count(), 6);
+        assert_eq!(JobId::tasks(1, "0-9999%100").task_count(), 10000);
+
+        assert_eq!(JobId::task(1234, 7).task_count(), 1);
+        assert_eq!(JobId::component(1234, 1).task_count(), 0);
+        assert!(!JobId::component(1234, 1).is_array());
+    }
+}
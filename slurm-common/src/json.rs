@@ -0,0 +1,972 @@
+// Collector for the structured --json output of scontrol and squeue (Slurm 21.08+).
+// The data_parser plugins changed the layout between versions, e.g. numbers are plain
+// up to v0.0.38 and {"set", "infinite", "number"} objects from v0.0.39, so the types
+// below accept both.
+use anyhow::{bail, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::resource::{parse_billing_weights, ResourceQuantity};
+use crate::scontrol::{parse_allow_list, parse_gres, split_evenly, untracked_gres};
+use crate::{
+    table::Table, Job, JobAllocation, JobId, JobResource, JobStatus, Node, NodeName, NodePartition,
+    NodeResource, NodeState, Partition, PartitionStatus, ResourceKind, ResourceType,
+};
+
+// A number that may be unset or infinite
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum NoValue {
+    Plain(i64),
+    Struct {
+        set: bool,
+        #[serde(default)]
+        infinite: bool,
+        #[serde(default)]
+        number: i64,
+    },
+}
+
+impl NoValue {
+    pub fn value(&self) -> Option<i64> {
+        match *self {
+            NoValue::Plain(n) => Some(n),
+            NoValue::Struct {
+                set: true,
+                infinite: false,
+                number,
+            } => Some(number),
+            NoValue::Struct { .. } => None,
+        }
+    }
+}
+
+// States are a single string up to v0.0.39 and a list of flags afterwards
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum StateList {
+    Single(String),
+    List(Vec<String>),
+}
+
+impl StateList {
+    // The base state, e.g. "MIXED" for ["MIXED", "DRAIN"]
+    pub fn base(&self) -> &str {
+        match self {
+            StateList::Single(s) => s,
+            StateList::List(v) => v.first().map(|s| s.as_str()).unwrap_or(""),
+        }
+    }
+
+    // The base state and flags, e.g. ["MIXED", "DRAIN"] for "MIXED+DRAIN"
+    pub fn parts(&self) -> Vec<&str> {
+        match self {
+            StateList::Single(s) => s.split('+').collect(),
+            StateList::List(v) => v.iter().map(|s| s.as_str()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NodesResponse {
+    pub nodes: Vec<NodeJson>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NodeJson {
+    pub name: String,
+    pub state: StateList,
+    // Up to v0.0.38 the flags are separate from the state
+    #[serde(default)]
+    pub state_flags: Vec<String>,
+    pub cpus: u32,
+    pub alloc_cpus: u32,
+    pub real_memory: i64,
+    pub alloc_memory: i64,
+    #[serde(alias = "free_memory")]
+    pub free_mem: Option<NoValue>,
+    #[serde(default)]
+    pub partitions: Vec<String>,
+    #[serde(default)]
+    pub tres: String,
+    #[serde(default)]
+    pub tres_used: Option<String>,
+    #[serde(default)]
+    pub gres: String,
+    #[serde(default)]
+    pub gres_used: String,
+    #[serde(default)]
+    pub reason: String,
+    #[serde(default)]
+    pub reason_set_by_user: String,
+    pub reason_changed_at: Option<NoValue>,
+    pub boot_time: Option<NoValue>,
+    pub slurmd_start_time: Option<NoValue>,
+    pub last_busy: Option<NoValue>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PartitionsResponse {
+    pub partitions: Vec<PartitionJson>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PartitionJson {
+    pub name: String,
+    // Up to v0.0.38 the state is a top-level field
+    pub state: Option<StateList>,
+    pub partition: Option<PartitionStateJson>,
+    // Up to v0.0.38 the preemption mode is a top-level string, e.g. "gang,suspend"
+    pub preemption_mode: Option<StateList>,
+    pub qos: Option<PartitionQosJson>,
+    #[serde(default)]
+    pub nodes: PartitionNodesJson,
+    #[serde(default)]
+    pub cpus: PartitionCpusJson,
+    #[serde(default)]
+    pub accounts: PartitionAllowJson,
+    #[serde(default)]
+    pub groups: PartitionAllowJson,
+    #[serde(default)]
+    pub defaults: PartitionDefaultsJson,
+    #[serde(default)]
+    pub maximums: PartitionMaximumsJson,
+    #[serde(default)]
+    pub minimums: PartitionMinimumsJson,
+    #[serde(default)]
+    pub priority: PartitionPriorityJson,
+    #[serde(default)]
+    pub tres: PartitionTresJson,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PartitionNodesJson {
+    #[serde(default)]
+    pub configured: String,
+    #[serde(default)]
+    pub total: u32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PartitionCpusJson {
+    #[serde(default)]
+    pub total: u32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PartitionAllowJson {
+    pub allowed: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PartitionDefaultsJson {
+    pub time: Option<NoValue>, // in minutes
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PartitionMaximumsJson {
+    pub time: Option<NoValue>, // in minutes
+    pub nodes: Option<NoValue>,
+    pub oversubscribe: Option<OverSubscribeJson>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OverSubscribeJson {
+    #[serde(default)]
+    pub jobs: u32,
+    #[serde(default)]
+    pub flags: Vec<String>,
+}
+
+impl OverSubscribeJson {
+    // The setting as scontrol prints it, e.g. "FORCE:4"
+    fn setting(&self) -> String {
+        let flag = |name: &str| self.flags.iter().any(|f| f.eq_ignore_ascii_case(name));
+        if flag("exclusive") {
+            "EXCLUSIVE".to_string()
+        } else if flag("force") {
+            format!("FORCE:{}", self.jobs)
+        } else if self.jobs > 1 {
+            format!("YES:{}", self.jobs)
+        } else {
+            "NO".to_string()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PartitionMinimumsJson {
+    #[serde(default)]
+    pub nodes: u32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PartitionPriorityJson {
+    #[serde(default)]
+    pub tier: u32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PartitionTresJson {
+    #[serde(default)]
+    pub billing_weights: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PartitionStateJson {
+    pub state: StateList,
+    // e.g. ["SUSPEND", "GANG"], a list like the state
+    pub preempt_mode: Option<StateList>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PartitionQosJson {
+    pub allowed: Option<String>,
+    pub assigned: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct JobsResponse {
+    pub jobs: Vec<JobJson>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct JobJson {
+    pub job_id: u64,
+    // 0 or unset if the job is not part of an array or het job
+    pub array_job_id: Option<NoValue>,
+    pub array_task_id: Option<NoValue>,
+    // The pending tasks that have not been split off yet, e.g. "2-100%10"
+    #[serde(default)]
+    pub array_task_string: String,
+    pub het_job_id: Option<NoValue>,
+    pub het_job_offset: Option<NoValue>,
+    pub name: String,
+    pub user_name: String,
+    #[serde(default)]
+    pub account: Option<String>,
+    #[serde(default)]
+    pub qos: Option<String>,
+    pub partition: String,
+    pub job_state: StateList,
+    // "None" if there is no reason
+    #[serde(default)]
+    pub state_reason: String,
+    pub time_limit: Option<NoValue>, // in minutes
+    pub submit_time: NoValue,
+    pub start_time: Option<NoValue>,
+    pub end_time: Option<NoValue>,
+    #[serde(default)]
+    pub nodes: String,
+    #[serde(default)]
+    pub tres_req_str: String,
+    #[serde(default)]
+    pub tres_alloc_str: String,
+    pub job_resources: Option<JobResourcesJson>,
+    // GRES of each allocated node, in the order of `nodes`
+    #[serde(default)]
+    pub gres_detail: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JobResourcesJson {
+    // From v0.0.40 the nodes are an object with the per-node allocation
+    pub nodes: Option<JobNodesJson>,
+    // Up to v0.0.39 the per-node allocation is a separate list
+    #[serde(default)]
+    pub allocated_nodes: Vec<NodeAllocationJson>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum JobNodesJson {
+    List(String),
+    Allocation {
+        #[serde(default)]
+        allocation: Vec<NodeAllocationJson>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NodeAllocationJson {
+    #[serde(alias = "nodename")]
+    pub name: String,
+    pub cpus: Option<CpuCount>,
+    pub memory: Option<MemoryCount>,
+    pub memory_allocated: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum CpuCount {
+    Plain(i64),
+    Struct { count: i64 },
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum MemoryCount {
+    Plain(i64),
+    Struct { allocated: i64 },
+}
+
+impl NodeAllocationJson {
+    fn cpus(&self) -> Option<i64> {
+        match self.cpus? {
+            CpuCount::Plain(n) | CpuCount::Struct { count: n } => Some(n),
+        }
+    }
+
+    // In megabytes
+    fn memory(&self) -> Option<i64> {
+        match self.memory {
+            Some(MemoryCount::Plain(n)) | Some(MemoryCount::Struct { allocated: n }) => Some(n),
+            None => self.memory_allocated,
+        }
+    }
+}
+
+async fn run(program: &str, args: &[&str]) -> Result<String> {
+    let output = tokio::process::Command::new(program)
+        .args(args)
+        .output()
+        .await?;
+    // Otherwise the error message would be a JSON error about empty output
+    if !output.status.success() {
+        bail!(
+            "{} {} failed with {}: {}",
+            program,
+            args.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+pub async fn nodes() -> Result<(Table<Node>, Table<NodeResource>, Table<NodePartition>)> {
+    let output = run("scontrol", &["show", "nodes", "--json"]).await?;
+    let response: NodesResponse = serde_json::from_str(&output)?;
+    map_nodes(response, Utc::now())
+}
+
+pub async fn partitions() -> Result<Table<Partition>> {
+    let output = run("scontrol", &["show", "partitions", "--json"]).await?;
+    let response: PartitionsResponse = serde_json::from_str(&output)?;
+    Ok(map_partitions(response, Utc::now()))
+}
+
+pub async fn jobs() -> Result<(Table<Job>, Table<JobAllocation>, Table<JobResource>)> {
+    let output = run("squeue", &["--all", "--json"]).await?;
+    let response: JobsResponse = serde_json::from_str(&output)?;
+    map_jobs(response, Utc::now())
+}
+
+// Unset times are 0
+fn timestamp(t: Option<NoValue>) -> Option<DateTime<Utc>> {
+    t.and_then(|t| t.value())
+        .filter(|t| *t > 0)
+        .and_then(|t| Utc.timestamp_opt(t, 0).single())
+}
+
+fn parse_tres(tres: &str) -> Result<BTreeMap<ResourceType, ResourceQuantity>> {
+    if tres.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    Ok(crate::parser::from_value_str(tres)?)
+}
+
+pub fn map_nodes(
+    response: NodesResponse,
+    updated_at: DateTime<Utc>,
+) -> Result<(Table<Node>, Table<NodeResource>, Table<NodePartition>)> {
+    let mut nodes = Table::new();
+    let mut resources = Table::new();
+    let mut partitions = Table::new();
+
+    for info in response.nodes {
+        let name = NodeName(info.name.clone());
+        let mut state_parts = info.state.parts();
+        state_parts.extend(info.state_flags.iter().map(|f| f.as_str()));
+
+        nodes.insert(Node {
+            name: name.clone(),
+            state: NodeState::from_parts(state_parts),
+            cpus: info.cpus,
+            cpus_alloc: info.alloc_cpus,
+            cpus_idle: info.cpus.saturating_sub(info.alloc_cpus),
+            memory: info.real_memory,
+            memory_alloc: info.alloc_memory,
+            memory_free: info.free_mem.and_then(|m| m.value()).unwrap_or(0),
+            partitions: info.partitions.clone(),
+            reason: Some(info.reason.clone()).filter(|r| !r.is_empty()),
+            reason_user: Some(info.reason_set_by_user.clone()).filter(|u| !u.is_empty()),
+            reason_time: timestamp(info.reason_changed_at),
+            boot_time: timestamp(info.boot_time),
+            slurmd_start_time: timestamp(info.slurmd_start_time),
+            last_busy_time: timestamp(info.last_busy),
+            updated_at,
+        });
+
+        for part_name in &info.partitions {
+            partitions.insert(NodePartition {
+                node: name.clone(),
+                partition: part_name.clone(),
+            });
+        }
+
+        let total = parse_tres(&info.tres)?;
+        let allocated = parse_tres(info.tres_used.as_deref().unwrap_or_default())?;
+        for (resource, total_qty) in &total {
+            let total = total_qty.0 as u64;
+            let allocated = allocated.get(resource).map(|q| q.0).unwrap_or(0);
+            resources.insert(NodeResource {
+                node: name.clone(),
+                resource: resource.clone(),
+                total,
+                available: total.saturating_sub(allocated as u64),
+            });
+        }
+        for (resource, total, available) in untracked_gres(&total, &info.gres, &info.gres_used) {
+            resources.insert(NodeResource {
+                node: name.clone(),
+                resource,
+                total,
+                available,
+            });
+        }
+    }
+
+    Ok((nodes, resources, partitions))
+}
+
+pub fn map_partitions(response: PartitionsResponse, updated_at: DateTime<Utc>) -> Table<Partition> {
+    let mut table = Table::new();
+    for info in response.partitions {
+        let state = info
+            .partition
+            .as_ref()
+            .map(|p| &p.state)
+            .or(info.state.as_ref());
+        let status = state
+            .map(|s| PartitionStatus::from(s.base()))
+            .unwrap_or(PartitionStatus::Unknown);
+        let qos = info.qos.as_ref();
+        let non_empty = |s: Option<&String>| s.filter(|s| !s.is_empty()).cloned();
+        let minutes = |t: &Option<NoValue>| t.as_ref().and_then(|t| t.value()).map(|t| t * 60);
+        let maximums = &info.maximums;
+        // As scontrol prints it, e.g. "SUSPEND,GANG"
+        let preempt_mode = info
+            .partition
+            .as_ref()
+            .and_then(|p| p.preempt_mode.as_ref())
+            .or(info.preemption_mode.as_ref())
+            .map(|m| m.parts().join(",").to_uppercase())
+            .filter(|m| !m.is_empty());
+        table.insert(Partition {
+            name: info.name,
+            status,
+            access_qos: non_empty(qos.and_then(|q| q.allowed.as_ref())),
+            resource_qos: non_empty(qos.and_then(|q| q.assigned.as_ref())),
+            nodes: non_empty(Some(&info.nodes.configured)),
+            total_nodes: info.nodes.total,
+            total_cpus: info.cpus.total,
+            max_time: minutes(&maximums.time),
+            default_time: minutes(&info.defaults.time),
+            max_nodes: maximums
+                .nodes
+                .as_ref()
+                .and_then(|n| n.value())
+                .map(|n| n as u32),
+            min_nodes: info.minimums.nodes,
+            priority_tier: info.priority.tier,
+            preempt_mode,
+            over_subscribe: maximums.oversubscribe.as_ref().map(|o| o.setting()),
+            allow_accounts: parse_allow_list(info.accounts.allowed.as_deref()),
+            allow_groups: parse_allow_list(info.groups.allowed.as_deref()),
+            billing_weights: parse_billing_weights(&info.tres.billing_weights),
+            updated_at,
+        });
+    }
+    table
+}
+
+impl JobJson {
+    // Array tasks and het job components go by the id of the whole job
+    fn id(&self) -> JobId {
+        let set = |n: Option<NoValue>| n.and_then(|n| n.value()).filter(|n| *n > 0);
+        if let Some(array_job_id) = set(self.array_job_id) {
+            if !self.array_task_string.is_empty() {
+                return JobId::tasks(array_job_id, &self.array_task_string);
+            }
+            if let Some(task) = self.array_task_id.and_then(|n| n.value()) {
+                return JobId::task(array_job_id, task as u32);
+            }
+        }
+        if let Some(het_job_id) = set(self.het_job_id) {
+            let offset = self.het_job_offset.and_then(|n| n.value()).unwrap_or(0);
+            return JobId::component(het_job_id, offset as u32);
+        }
+        JobId::new(self.job_id as i64)
+    }
+}
+
+pub fn map_jobs(
+    response: JobsResponse,
+    updated_at: DateTime<Utc>,
+) -> Result<(Table<Job>, Table<JobAllocation>, Table<JobResource>)> {
+    let mut jobs = Table::new();
+    let mut allocations = Table::new();
+    let mut resources = Table::new();
+
+    for info in response.jobs {
+        let job_id = info.id();
+        let submit_time = timestamp(Some(info.submit_time))
+            .ok_or_else(|| anyhow::anyhow!("Invalid submit_time for job {}", job_id))?;
+
+        jobs.insert(Job {
+            job_id: job_id.clone(),
+            name: info.name.clone(),
+            user: info.user_name.clone(),
+            account: info.account.clone().filter(|a| !a.is_empty()),
+            partition: info.partition.clone(),
+            qos: info.qos.clone().filter(|q| !q.is_empty()),
+            status: JobStatus::from_parts(info.job_state.parts()),
+            reason: Some(info.state_reason.clone()).filter(|r| !r.is_empty() && r != "None"),
+            time_limit: info.time_limit.and_then(|t| t.value()).map(|t| t * 60),
+            start_time: timestamp(info.start_time),
+            end_time: timestamp(info.end_time),
+            submit_time,
+            updated_at,
+        });
+
+        let requested = parse_tres(&info.tres_req_str)?;
+        let allocated = parse_tres(&info.tres_alloc_str)?;
+        let mut res_types: Vec<&ResourceType> = requested.keys().chain(allocated.keys()).collect();
+        res_types.sort();
+        res_types.dedup();
+        for resource in res_types {
+            resources.insert(JobResource {
+                job: job_id.clone(),
+                resource: resource.clone(),
+                requested: requested.get(resource).map(|q| q.0).unwrap_or(0),
+                allocated: allocated.get(resource).map(|q| q.0).unwrap_or(0),
+            });
+        }
+
+        for (node, resource, used) in node_allocations(&info, &allocated)? {
+            allocations.insert(JobAllocation {
+                job: job_id.clone(),
+                node,
+                resource,
+                used,
+            });
+        }
+    }
+
+    Ok((jobs, allocations, resources))
+}
+
+// Like the scontrol --details lines, job_resources and gres_detail give the exact
+// CPUs, memory and GRES of each node. Everything else is split evenly.
+fn node_allocations(
+    info: &JobJson,
+    allocated: &BTreeMap<ResourceType, ResourceQuantity>,
+) -> Result<Vec<(NodeName, ResourceType, i64)>> {
+    let node_list: Vec<NodeName> = crate::hostlist::expand(&info.nodes)?
+        .into_iter()
+        .map(NodeName)
+        .collect();
+    let per_node = match &info.job_resources {
+        Some(JobResourcesJson {
+            nodes: Some(JobNodesJson::Allocation { allocation }),
+            ..
+        }) => allocation.as_slice(),
+        Some(resources) => resources.allocated_nodes.as_slice(),
+        None => &[],
+    };
+
+    let mut result = Vec::new();
+    for node in per_node {
+        let name = NodeName(node.name.clone());
+        result.push((name.clone(), ResourceType::new("node"), 1));
+        if let Some(cpus) = node.cpus() {
+            result.push((name.clone(), ResourceType::new("cpu"), cpus));
+        }
+        if let Some(mem) = node.memory() {
+            // In megabytes, the same unit as the M suffix
+            result.push((name.clone(), ResourceType::new("mem"), mem * 1024 * 1024));
+        }
+    }
+    let detailed_gres = !info.gres_detail.is_empty() && info.gres_detail.len() == node_list.len();
+    if detailed_gres {
+        for (node, gres) in node_list.iter().zip(&info.gres_detail) {
+            for (resource, count) in parse_gres(gres) {
+                result.push((node.clone(), resource, count));
+            }
+        }
+    }
+
+    let remaining = allocated.iter().filter(|(resource, _)| {
+        let from_details = match resource.kind {
+            ResourceKind::Node | ResourceKind::Cpu | ResourceKind::Mem => !per_node.is_empty(),
+            ResourceKind::Gres => detailed_gres,
+            _ => false,
+        };
+        !from_details
+    });
+    result.extend(split_evenly(&node_list, remaining));
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NodeFlag, NodeStatus};
+
+    // scontrol show nodes --json, Slurm 23.11 (data_parser v0.0.40)
+    const NODES: &str = r#"{
+  "nodes": [
+    {
+      "name": "node4504",
+      "state": ["MIXED"],
+      "cpus": 64,
+      "alloc_cpus": 16,
+      "real_memory": 1031314,
+      "alloc_memory": 64000,
+      "free_mem": {"set": true, "infinite": false, "number": 77430},
+      "partitions": ["mit_preemptable", "mit_normal_gpu"],
+      "tres": "cpu=64,mem=1031314M,billing=64,gres/gpu=4,gres/gpu:l40s=4",
+      "tres_used": "cpu=16,mem=64000M,gres/gpu=1,gres/gpu:l40s=1"
+    },
+    {
+      "name": "node4505",
+      "state": ["DOWN", "NOT_RESPONDING"],
+      "cpus": 64,
+      "alloc_cpus": 0,
+      "real_memory": 1031314,
+      "alloc_memory": 0,
+      "free_mem": {"set": false, "infinite": false, "number": 0},
+      "partitions": ["mit_preemptable"],
+      "tres": "cpu=64,mem=1031314M,billing=64",
+      "tres_used": null,
+      "gres": "gpu:h100:2(S:0)",
+      "gres_used": "gpu:h100:0(IDX:N/A)",
+      "reason": "Not responding",
+      "reason_set_by_user": "slurm",
+      "reason_changed_at": {"set": true, "infinite": false, "number": 1790000000},
+      "boot_time": {"set": true, "infinite": false, "number": 1789000000},
+      "last_busy": {"set": true, "infinite": false, "number": 0}
+    }
+  ]
+}"#;
+
+    // scontrol show nodes --json, Slurm 22.05 (data_parser v0.0.38)
+    const NODES_V38: &str = r#"{
+  "nodes": [
+    {
+      "name": "node1",
+      "state": "idle",
+      "state_flags": ["DRAIN"],
+      "cpus": 32,
+      "alloc_cpus": 0,
+      "real_memory": 192000,
+      "alloc_memory": 0,
+      "free_memory": 180000,
+      "partitions": ["standard"],
+      "tres": "cpu=32,mem=192000M,billing=32",
+      "tres_used": ""
+    }
+  ]
+}"#;
+
+    const PARTITIONS: &str = r#"{
+  "partitions": [
+    {
+      "name": "mit_normal_gpu",
+      "nodes": {"allowed_allocation": "", "configured": "node[101,205]", "total": 2},
+      "accounts": {"allowed": "mit_general,mit_gpu", "deny": ""},
+      "groups": {"allowed": ""},
+      "qos": {"allowed": "normal,gpu", "deny": "", "assigned": "gpu-normal"},
+      "tres": {"billing_weights": "CPU=1.0,Mem=0.25G,GRES/gpu=2.0", "configured": "cpu=128,mem=1000G,node=2,billing=128,gres/gpu=8"},
+      "cpus": {"task_binding": 0, "total": 128},
+      "defaults": {"time": {"set": true, "infinite": false, "number": 60}},
+      "maximums": {
+        "nodes": {"set": true, "infinite": false, "number": 2},
+        "time": {"set": true, "infinite": false, "number": 2880},
+        "oversubscribe": {"jobs": 4, "flags": ["force"]}
+      },
+      "minimums": {"nodes": 0},
+      "priority": {"job_factor": 1, "tier": 10},
+      "partition": {"state": ["UP"], "preempt_mode": ["REQUEUE"]}
+    },
+    {
+      "name": "old",
+      "state": "DOWN",
+      "preemption_mode": "gang,suspend"
+    }
+  ]
+}"#;
+
+    // squeue --json, Slurm 23.11 (data_parser v0.0.40)
+    const JOBS: &str = r#"{
+  "jobs": [
+    {
+      "job_id": 8602044,
+      "name": "mpi",
+      "user_name": "bob",
+      "partition": "sched_mit_hill",
+      "job_state": ["RUNNING"],
+      "state_reason": "None",
+      "time_limit": {"set": true, "infinite": false, "number": 480},
+      "submit_time": {"set": true, "infinite": false, "number": 1769860800},
+      "start_time": {"set": true, "infinite": false, "number": 1769860801},
+      "end_time": {"set": true, "infinite": false, "number": 1769889601},
+      "nodes": "node[101,205]",
+      "tres_req_str": "cpu=9,mem=18G,node=2,billing=9,gres/gpu=3",
+      "tres_alloc_str": "cpu=9,mem=18G,node=2,billing=9,gres/gpu=3,gres/gpu:a100=3",
+      "gres_detail": ["gpu:a100:2(IDX:0-1)", "gpu:a100:1(IDX:3)"],
+      "job_resources": {
+        "nodes": {
+          "count": 2,
+          "list": "node[101,205]",
+          "allocation": [
+            {"index": 0, "name": "node101", "cpus": {"count": 5, "used": 5}, "memory": {"used": 0, "allocated": 10000}},
+            {"index": 1, "name": "node205", "cpus": {"count": 4, "used": 4}, "memory": {"used": 0, "allocated": 8000}}
+          ]
+        }
+      }
+    },
+    {
+      "job_id": 8602011,
+      "name": "train",
+      "user_name": "alice",
+      "partition": "mit_normal_gpu",
+      "job_state": ["PENDING"],
+      "state_reason": "Resources",
+      "time_limit": {"set": false, "infinite": true, "number": 0},
+      "submit_time": {"set": true, "infinite": false, "number": 1769864530},
+      "start_time": {"set": true, "infinite": false, "number": 0},
+      "nodes": "",
+      "tres_req_str": "cpu=16,mem=64G,node=1,billing=16,gres/gpu=1",
+      "tres_alloc_str": "",
+      "gres_detail": []
+    },
+    {
+      "job_id": 8602061,
+      "name": "assemble",
+      "user_name": "alice",
+      "partition": "sched_mit_hill",
+      "job_state": ["FAILED", "COMPLETING"],
+      "state_reason": "NonZeroExitCode",
+      "time_limit": {"set": true, "infinite": false, "number": 60},
+      "submit_time": {"set": true, "infinite": false, "number": 1769862600},
+      "start_time": {"set": true, "infinite": false, "number": 1769862602},
+      "nodes": "node156",
+      "tres_req_str": "cpu=4,mem=8G,node=1,billing=4",
+      "tres_alloc_str": "cpu=4,mem=8G,node=1,billing=4",
+      "gres_detail": []
+    },
+    {
+      "job_id": 8602101,
+      "array_job_id": {"set": true, "infinite": false, "number": 8602100},
+      "array_task_id": {"set": true, "infinite": false, "number": 1},
+      "array_task_string": "",
+      "het_job_id": {"set": true, "infinite": false, "number": 0},
+      "name": "array",
+      "user_name": "alice",
+      "partition": "sched_mit_hill",
+      "job_state": ["RUNNING"],
+      "submit_time": {"set": true, "infinite": false, "number": 1769864400},
+      "start_time": {"set": true, "infinite": false, "number": 1769864410},
+      "nodes": "node156",
+      "tres_req_str": "cpu=1,mem=4G,node=1,billing=1",
+      "tres_alloc_str": "cpu=1,mem=4G,node=1,billing=1"
+    },
+    {
+      "job_id": 8602100,
+      "array_job_id": {"set": true, "infinite": false, "number": 8602100},
+      "array_task_id": {"set": false, "infinite": false, "number": 0},
+      "array_task_string": "2-100%10",
+      "name": "array",
+      "user_name": "alice",
+      "partition": "sched_mit_hill",
+      "job_state": ["PENDING"],
+      "state_reason": "JobArrayTaskLimit",
+      "submit_time": {"set": true, "infinite": false, "number": 1769864400},
+      "tres_req_str": "cpu=1,mem=4G,node=1,billing=1"
+    },
+    {
+      "job_id": 8602201,
+      "array_job_id": {"set": true, "infinite": false, "number": 0},
+      "het_job_id": {"set": true, "infinite": false, "number": 8602200},
+      "het_job_offset": {"set": true, "infinite": false, "number": 1},
+      "name": "het",
+      "user_name": "bob",
+      "partition": "sched_mit_hill",
+      "job_state": ["PENDING"],
+      "state_reason": "Priority",
+      "submit_time": {"set": true, "infinite": false, "number": 1769865000}
+    }
+  ]
+}"#;
+
+    #[test]
+    fn test_map_nodes() {
+        let response: NodesResponse = serde_json::from_str(NODES).unwrap();
+        let (nodes, resources, partitions) = map_nodes(response, Utc::now()).unwrap();
+
+        let node = nodes.get(&NodeName::new("node4504")).unwrap();
+        assert_eq!(node.state, NodeState::from(NodeStatus::Mix));
+        assert_eq!((node.cpus, node.cpus_alloc, node.cpus_idle), (64, 16, 48));
+        assert_eq!(node.memory_free, 77430);
+        let down = nodes.get(&NodeName::new("node4505")).unwrap();
+        assert_eq!(
+            down.state,
+            NodeState::new(NodeStatus::Down, [NodeFlag::NotResponding])
+        );
+        assert_eq!(down.memory_free, 0);
+        assert_eq!(down.reason.as_deref(), Some("Not responding"));
+        assert_eq!(down.reason_user.as_deref(), Some("slurm"));
+        assert_eq!(down.reason_time.unwrap().timestamp(), 1790000000);
+        assert_eq!(down.boot_time.unwrap().timestamp(), 1789000000);
+        assert_eq!((down.slurmd_start_time, down.last_busy_time), (None, None));
+        assert_eq!(node.reason, None);
+
+        let gpu = resources
+            .get(&(
+                NodeName::new("node4504"),
+                ResourceType::new("gres/gpu:l40s"),
+            ))
+            .unwrap();
+        assert_eq!((gpu.total, gpu.available), (4, 3));
+        let gpu = resources
+            .get(&(
+                NodeName::new("node4505"),
+                ResourceType::new("gres/gpu:h100"),
+            ))
+            .unwrap();
+        assert_eq!((gpu.total, gpu.available), (2, 2));
+        assert_eq!(partitions.len(), 3);
+
+        let response: NodesResponse = serde_json::from_str(NODES_V38).unwrap();
+        let (nodes, _, _) = map_nodes(response, Utc::now()).unwrap();
+        let node = nodes.get(&NodeName::new("node1")).unwrap();
+        assert_eq!(
+            node.state,
+            NodeState::new(NodeStatus::Idle, [NodeFlag::Drain])
+        );
+        assert!(node.state.is_drained());
+        assert_eq!(node.memory_free, 180000);
+    }
+
+    #[test]
+    fn test_map_partitions() {
+        let response: PartitionsResponse = serde_json::from_str(PARTITIONS).unwrap();
+        let partitions = map_partitions(response, Utc::now());
+        let gpu = partitions.get(&"mit_normal_gpu".to_string()).unwrap();
+        assert_eq!(gpu.status, PartitionStatus::Up);
+        assert_eq!(gpu.access_qos.as_deref(), Some("normal,gpu"));
+        assert_eq!(gpu.resource_qos.as_deref(), Some("gpu-normal"));
+        assert_eq!(gpu.nodes.as_deref(), Some("node[101,205]"));
+        assert_eq!((gpu.total_nodes, gpu.total_cpus), (2, 128));
+        assert_eq!(gpu.max_time, Some(2 * 24 * 60 * 60));
+        assert_eq!(gpu.default_time, Some(60 * 60));
+        assert_eq!((gpu.max_nodes, gpu.min_nodes), (Some(2), 0));
+        assert_eq!(gpu.priority_tier, 10);
+        assert_eq!(gpu.over_subscribe.as_deref(), Some("FORCE:4"));
+        assert_eq!(gpu.preempt_mode.as_deref(), Some("REQUEUE"));
+        assert_eq!(
+            gpu.allow_accounts,
+            Some(vec!["mit_general".to_string(), "mit_gpu".to_string()])
+        );
+        assert_eq!(gpu.allow_groups, None);
+        assert_eq!(
+            gpu.billing_weights.get(&ResourceType::new("cpu")),
+            Some(&1.0)
+        );
+        let old = partitions.get(&"old".to_string()).unwrap();
+        assert_eq!(old.status, PartitionStatus::Down);
+        assert_eq!(old.access_qos, None);
+        assert_eq!((old.nodes.as_ref(), old.max_time), (None, None));
+        assert_eq!(old.preempt_mode.as_deref(), Some("GANG,SUSPEND"));
+    }
+
+    #[test]
+    fn test_map_jobs() {
+        let response: JobsResponse = serde_json::from_str(JOBS).unwrap();
+        let (jobs, allocations, resources) = map_jobs(response, Utc::now()).unwrap();
+
+        let running = jobs.get(&JobId::new(8602044)).unwrap();
+        assert_eq!(running.status, JobStatus::Running);
+        assert_eq!(running.reason, None);
+        assert_eq!(running.user, "bob");
+        assert_eq!(running.time_limit, Some(8 * 60 * 60));
+        assert_eq!(running.submit_time.timestamp(), 1769860800);
+        assert_eq!(running.start_time.unwrap().timestamp(), 1769860801);
+        assert_eq!(
+            running.end_time.unwrap() - running.start_time.unwrap(),
+            chrono::Duration::hours(8)
+        );
+
+        let pending = jobs.get(&JobId::new(8602011)).unwrap();
+        assert_eq!(pending.status, JobStatus::Pending);
+        assert_eq!(pending.reason.as_deref(), Some("Resources"));
+        assert_eq!(pending.time_limit, None);
+        assert_eq!(pending.start_time, None);
+        let gpu = resources
+            .get(&(JobId::new(8602011), ResourceType::new("gres/gpu")))
+            .unwrap();
+        assert_eq!((gpu.requested, gpu.allocated), (1, 0));
+
+        let used = |node: &str, res: &str| {
+            allocations
+                .get(&(
+                    JobId::new(8602044),
+                    NodeName::new(node),
+                    ResourceType::new(res),
+                ))
+                .map(|a| a.used)
+        };
+        assert_eq!(used("node101", "cpu"), Some(5));
+        assert_eq!(used("node205", "cpu"), Some(4));
+        assert_eq!(used("node101", "mem"), Some(10000 * 1024 * 1024));
+        assert_eq!(used("node101", "gres/gpu:a100"), Some(2));
+        assert_eq!(used("node205", "gres/gpu"), Some(1));
+        assert_eq!(used("node205", "billing"), Some(4));
+        assert!(allocations.iter().all(|a| a.job != JobId::new(8602011)));
+
+        // A failed job whose processes are still being cleaned up
+        let completing = jobs.get(&JobId::new(8602061)).unwrap();
+        assert_eq!(completing.status, JobStatus::Completing);
+        assert_eq!(completing.reason.as_deref(), Some("NonZeroExitCode"));
+
+        // Array tasks and het job components go by the id of the whole job
+        assert!(jobs.get(&JobId::task(8602100, 1)).is_some());
+        let tasks = jobs.get(&JobId::tasks(8602100, "2-100%10")).unwrap();
+        assert_eq!(tasks.job_id.array_tasks().len(), 99);
+        assert!(jobs.get(&JobId::component(8602200, 1)).is_some());
+        assert_eq!(jobs.len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        let output = run("echo", &[r#"{"nodes": []}"#]).await.unwrap();
+        assert_eq!(output.trim(), r#"{"nodes": []}"#);
+
+        let err = run(
+            "sh",
+            &[
+                "-c",
+                "echo 'Unable to contact slurm controller' >&2; exit 1",
+            ],
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().starts_with("sh -c"));
+        assert!(err
+            .to_string()
+            .contains("Unable to contact slurm controller"));
+    }
+}
@@ -1,24 +1,36 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
+pub mod array;
 #[cfg(feature = "db")]
 pub mod db;
+pub mod hostlist;
+pub mod job_id;
+pub mod json;
 pub mod parser;
+pub mod protocol;
+pub mod qos;
+pub mod resource;
+#[cfg(feature = "rest")]
+pub mod rest;
+pub mod sacct;
+pub mod sacctmgr;
 pub mod scontrol;
+pub mod sprio;
+pub mod sshare;
+pub mod sstat;
+pub mod state;
 pub mod table;
 
 use table::{Keyed, Table};
 
 use crate::table::TableDiff;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
-pub struct ResourceType(String);
-
-impl ResourceType {
-    pub fn new(s: &str) -> Self {
-        Self(s.to_string())
-    }
-}
+pub use job_id::{JobId, JobPart};
+pub use parser::ParseWarning;
+pub use resource::{ResourceKind, ResourceType, ResourceUnit};
+pub use state::{JobStatus, NodeFlag, NodeState, NodeStatus, PartitionStatus};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct NodeName(String);
@@ -29,28 +41,10 @@ impl NodeName {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
-pub struct JobId(i64);
-
-impl JobId {
-    pub fn new(i: i64) -> Self {
-        Self(i)
-    }
-}
-
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
-pub enum NodeStatus {
-    Idle,
-    Alloc,
-    Mix,
-    Down,
-    Unknown,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Node {
     pub name: NodeName,
-    pub status: NodeStatus,
+    pub state: NodeState,
     // CPU stats
     pub cpus: u32,
     pub cpus_alloc: u32,
@@ -61,6 +55,14 @@ pub struct Node {
     pub memory_free: i64,
     // Partitions this node belongs to
     pub partitions: Vec<String>,
+    // Why the node is down or drained, who set the reason and when
+    pub reason: Option<String>,
+    pub reason_user: Option<String>,
+    pub reason_time: Option<DateTime<Utc>>,
+    pub boot_time: Option<DateTime<Utc>>,
+    pub slurmd_start_time: Option<DateTime<Utc>>,
+    // The last time the node had a job
+    pub last_busy_time: Option<DateTime<Utc>>,
 
     pub updated_at: DateTime<Utc>,
 }
@@ -79,25 +81,22 @@ pub struct NodePartition {
     pub partition: String,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
-pub enum JobStatus {
-    Pending,
-    Running,
-    Completed,
-    Failed,
-    Cancelled,
-    Unknown,
-}
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Job {
     pub job_id: JobId,
     pub name: String,
     pub user: String,
+    pub account: Option<String>,
     pub partition: String,
+    pub qos: Option<String>,
     pub status: JobStatus,
+    // Why the job is pending or why it failed, e.g. "Resources" or "NonZeroExitCode"
+    pub reason: Option<String>,
 
     pub time_limit: Option<i64>, // in seconds
     pub start_time: Option<DateTime<Utc>>,
+    // When a running job reaches its time limit, or when a finished job ended
+    pub end_time: Option<DateTime<Utc>>,
     pub submit_time: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -118,11 +117,128 @@ pub struct JobAllocation {
     pub used: i64,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
-pub enum PartitionStatus {
-    Up,
-    Down,
-    Unknown,
+// The priority of a pending job, broken down into its weighted factors
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JobPriority {
+    pub job: JobId,
+    // The partition the job has the highest priority in
+    pub partition: String,
+    pub priority: i64,
+    pub age_factor: i64,
+    pub fairshare_factor: i64,
+    pub job_size_factor: i64,
+    pub partition_factor: i64,
+    pub qos_factor: i64,
+    pub tres_factor: i64,
+    // The position of the job among the pending jobs of the partition, starting at 1
+    pub rank: u32,
+
+    pub updated_at: DateTime<Utc>,
+}
+
+// A step of a running job: its batch script, the extern step or an srun
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JobStep {
+    pub job: JobId,
+    // e.g. "batch", "extern" or "0" for the first srun
+    pub step: String,
+    pub name: String,
+    pub status: JobStatus,
+    pub nodes: Vec<NodeName>,
+    pub tasks: u32,
+    pub start_time: Option<DateTime<Utc>>,
+
+    // The live usage from sstat, unset until sstat has reported on the step
+    pub ave_cpu: Option<i64>,        // in seconds
+    pub max_rss: Option<i64>,        // in bytes
+    pub max_disk_read: Option<i64>,  // in bytes
+    pub max_disk_write: Option<i64>, // in bytes
+
+    pub updated_at: DateTime<Utc>,
+}
+
+// A finished job, as recorded by the accounting database.
+// Unlike the other entities these are only ever added, never removed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JobRecord {
+    pub job_id: JobId,
+    pub name: String,
+    pub user: String,
+    pub account: Option<String>,
+    pub partition: String,
+    pub status: JobStatus,
+    pub exit_code: i32,
+    pub exit_signal: i32,
+
+    pub submit_time: DateTime<Utc>,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: DateTime<Utc>,
+    pub elapsed: i64,         // in seconds
+    pub total_cpu: i64,       // in seconds, summed over all steps
+    pub max_rss: Option<i64>, // in bytes, the maximum of all steps
+}
+
+// A reservation of nodes or resources for some users or accounts, e.g. a maintenance window
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Reservation {
+    pub name: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub active: bool,
+    pub nodes: Vec<NodeName>,
+    pub partition: Option<String>,
+    // Users and accounts prefixed with '-' are denied access
+    pub users: Vec<String>,
+    pub accounts: Vec<String>,
+    pub flags: Vec<String>,
+    pub tres: BTreeMap<ResourceType, i64>,
+
+    pub updated_at: DateTime<Utc>,
+}
+
+// The reservations a node is part of, active or upcoming
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NodeReservation {
+    pub node: NodeName,
+    pub reservation: String,
+}
+
+// The fairshare of an account, or of a user within an account
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Association {
+    pub account: String,
+    pub user: String, // empty for the account itself
+    // None if the association uses the shares of its parent
+    pub raw_shares: Option<i64>,
+    pub norm_shares: f64,
+    pub raw_usage: i64,
+    pub effective_usage: f64,
+    // The fairshare factor, between 0 and 1
+    pub fairshare: Option<f64>,
+
+    pub updated_at: DateTime<Utc>,
+}
+
+// A quality of service and its limits, unset limits are not enforced
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Qos {
+    pub name: String,
+    pub priority: i64,
+    // The QOS whose jobs this one can preempt
+    pub preempt: Vec<String>,
+    pub preempt_mode: Option<String>,
+    // Limits on all jobs of the QOS together
+    pub grp_tres: BTreeMap<ResourceType, i64>,
+    pub grp_jobs: Option<i64>,
+    // Limits on a single job
+    pub max_tres_per_job: BTreeMap<ResourceType, i64>,
+    pub max_wall: Option<i64>, // in seconds
+    // Limits on all jobs of a user
+    pub max_tres_per_user: BTreeMap<ResourceType, i64>,
+    pub max_jobs_per_user: Option<i64>,
+    pub max_submit_per_user: Option<i64>,
+
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -133,6 +249,28 @@ pub struct Partition {
     pub access_qos: Option<String>,
     pub resource_qos: Option<String>,
 
+    // The nodes as a hostlist, e.g. "node[101-110,205]"
+    pub nodes: Option<String>,
+    pub total_nodes: u32,
+    pub total_cpus: u32,
+
+    // Job limits and defaults, None if unlimited or not set
+    pub max_time: Option<i64>,     // in seconds
+    pub default_time: Option<i64>, // in seconds
+    pub max_nodes: Option<u32>,
+    pub min_nodes: u32,
+
+    // Jobs in partitions of a higher tier are scheduled and may preempt first
+    pub priority_tier: u32,
+    pub preempt_mode: Option<String>,
+    // e.g. "NO", "EXCLUSIVE" or "FORCE:4"
+    pub over_subscribe: Option<String>,
+    // None if all accounts or groups are allowed
+    pub allow_accounts: Option<Vec<String>>,
+    pub allow_groups: Option<Vec<String>>,
+    // The billing of one unit of a resource, e.g. of one byte of memory
+    pub billing_weights: BTreeMap<ResourceType, f64>,
+
     pub updated_at: DateTime<Utc>,
 }
 
@@ -145,6 +283,12 @@ pub struct ClusterState {
     pub node_partitions: Table<NodePartition>,
     pub job_resources: Table<JobResource>,
     pub job_allocations: Table<JobAllocation>,
+    pub associations: Table<Association>,
+    pub job_priorities: Table<JobPriority>,
+    pub job_steps: Table<JobStep>,
+    pub qos: Table<Qos>,
+    pub reservations: Table<Reservation>,
+    pub node_reservations: Table<NodeReservation>,
     // The time this state was last refreshed
     pub updated_at: Option<DateTime<Utc>>,
 }
@@ -158,6 +302,18 @@ pub struct ClusterDiff {
     pub node_partitions: TableDiff<NodePartition, (NodeName, String)>,
     pub job_resources: TableDiff<JobResource, (JobId, ResourceType)>,
     pub job_allocations: TableDiff<JobAllocation, (JobId, NodeName, ResourceType)>,
+    pub associations: TableDiff<Association, (String, String)>,
+    pub job_priorities: TableDiff<JobPriority, JobId>,
+    pub job_steps: TableDiff<JobStep, (JobId, String)>,
+    pub qos: TableDiff<Qos, String>,
+    pub reservations: TableDiff<Reservation, String>,
+    pub node_reservations: TableDiff<NodeReservation, (NodeName, String)>,
+    // Jobs that finished since the previous diff
+    #[serde(default)]
+    pub job_history: Vec<JobRecord>,
+    // Records the worker could not parse and left out of this diff
+    #[serde(default)]
+    pub warnings: Vec<ParseWarning>,
     pub updated_at: Option<DateTime<Utc>>,
 }
 
@@ -171,6 +327,14 @@ impl ClusterState {
             node_partitions: self.node_partitions.diff(&other.node_partitions),
             job_resources: self.job_resources.diff(&other.job_resources),
             job_allocations: self.job_allocations.diff(&other.job_allocations),
+            associations: self.associations.diff(&other.associations),
+            job_priorities: self.job_priorities.diff(&other.job_priorities),
+            job_steps: self.job_steps.diff(&other.job_steps),
+            qos: self.qos.diff(&other.qos),
+            reservations: self.reservations.diff(&other.reservations),
+            node_reservations: self.node_reservations.diff(&other.node_reservations),
+            job_history: Vec::new(),
+            warnings: Vec::new(),
             updated_at: other.updated_at,
         }
     }
@@ -293,3 +457,99 @@ impl Keyed for JobAllocation {
         (r.0.clone(), r.1.clone(), r.2.clone())
     }
 }
+
+impl Keyed for JobPriority {
+    type Key = JobId;
+    type KeyRef<'s>
+        = &'s JobId
+    where
+        Self: 's;
+
+    fn key(&self) -> Self::KeyRef<'_> {
+        &self.job
+    }
+
+    fn clone_key(r: Self::KeyRef<'_>) -> Self::Key {
+        r.clone()
+    }
+}
+
+impl Keyed for JobStep {
+    type Key = (JobId, String);
+    type KeyRef<'s>
+        = (&'s JobId, &'s str)
+    where
+        Self: 's;
+
+    fn key(&self) -> Self::KeyRef<'_> {
+        (&self.job, &self.step)
+    }
+
+    fn clone_key(r: Self::KeyRef<'_>) -> Self::Key {
+        (r.0.clone(), r.1.to_string())
+    }
+}
+
+impl Keyed for Qos {
+    type Key = String;
+    type KeyRef<'s>
+        = &'s str
+    where
+        Self: 's;
+
+    fn key(&self) -> Self::KeyRef<'_> {
+        &self.name
+    }
+
+    fn clone_key(r: Self::KeyRef<'_>) -> Self::Key {
+        r.to_string()
+    }
+}
+
+impl Keyed for Reservation {
+    type Key = String;
+    type KeyRef<'s>
+        = &'s str
+    where
+        Self: 's;
+
+    fn key(&self) -> Self::KeyRef<'_> {
+        &self.name
+    }
+
+    fn clone_key(r: Self::KeyRef<'_>) -> Self::Key {
+        r.to_string()
+    }
+}
+
+impl Keyed for NodeReservation {
+    type Key = (NodeName, String);
+    type KeyRef<'s>
+        = (&'s NodeName, &'s str)
+    where
+        Self: 's;
+
+    fn key(&self) -> Self::KeyRef<'_> {
+        (&self.node, &self.reservation)
+    }
+
+    fn clone_key(r: Self::KeyRef<'_>) -> Self::Key {
+        (r.0.clone(), r.1.to_string())
+    }
+}
+
+impl Keyed for Association {
+    type Key = (String, String);
+    type KeyRef<'s>
+        = (&'s str, &'s str)
+    where
+        Self: 's;
+
+    fn key(&self) -> Self::KeyRef<'_> {
+        (&self.account, &self.user)
+    }
+
+    fn clone_key(r: Self::KeyRef<'_>) -> Self::Key {
+        (r.0.to_string(), r.1.to_string())
+    }
+}
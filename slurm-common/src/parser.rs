@@ -1,24 +1,93 @@
-use regex::Regex;
-use serde::{de, forward_to_deserialize_any};
-use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use chrono::{DateTime, Local, LocalResult, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{de, forward_to_deserialize_any, ser, Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::hash_map::{Entry, HashMap};
 use std::fmt;
+use std::io::{self, BufRead};
+use std::sync::RwLock;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
 
-#[derive(Debug, PartialEq, Eq)]
+// An error with as much of its place in the output as is known
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Error {
-    message: String,
+    pub message: String,
+    // The record the error is in, starting at 1
+    pub record: Option<usize>,
+    // The line and column of the key in the output, starting at 1
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    // The key and the raw value that could not be read
+    pub key: Option<String>,
+    pub value: Option<String>,
 }
+
 impl Error {
-    fn custom<T: fmt::Display>(msg: T) -> Self {
+    pub(crate) fn custom<T: fmt::Display>(msg: T) -> Self {
         Error {
             message: msg.to_string(),
+            record: None,
+            line: None,
+            column: None,
+            key: None,
+            value: None,
+        }
+    }
+
+    // A value that was read but can't be used, e.g. a time the record can't do without
+    pub(crate) fn in_value<T: fmt::Display>(msg: T, key: &str, value: &str) -> Self {
+        Error {
+            key: Some(key.to_string()),
+            value: Some(value.to_string()),
+            ..Error::custom(msg)
+        }
+    }
+
+    // Sets where the value of a key is, unless a nested value already did
+    fn at(mut self, key: &str, value: &str, position: Position) -> Self {
+        if self.key.is_none() {
+            self.key = Some(key.to_string());
+            self.value = Some(value.to_string());
+        }
+        if self.line.is_none() {
+            self.line = Some(position.line);
+            self.column = Some(position.column);
         }
+        self
+    }
+
+    // Makes the line relative to a record starting at `first_line` (0-based) of the output
+    fn in_record(mut self, record: Option<usize>, first_line: usize) -> Self {
+        self.record = self.record.or(record);
+        self.line = Some(self.line.unwrap_or(1) + first_line);
+        self
     }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.message)
+        write!(f, "{}", self.message)?;
+        let mut context = Vec::new();
+        if let Some(record) = self.record {
+            context.push(format!("record {}", record));
+        }
+        if let Some(line) = self.line {
+            context.push(format!("line {}", line));
+        }
+        if let Some(column) = self.column {
+            context.push(format!("column {}", column));
+        }
+        if let Some(key) = &self.key {
+            context.push(format!(
+                "{}={}",
+                key,
+                self.value.as_deref().unwrap_or_default()
+            ));
+        }
+        if !context.is_empty() {
+            write!(f, " ({})", context.join(", "))?;
+        }
+        Ok(())
     }
 }
 
@@ -26,26 +95,600 @@ impl std::error::Error for Error {}
 
 impl de::Error for Error {
     fn custom<T: fmt::Display>(msg: T) -> Self {
-        Error {
-            message: msg.to_string(),
-        }
+        Error::custom(msg)
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::custom(msg)
     }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+// Where a key is in the output, starting at 1
+#[derive(Debug, Clone, Copy, Default)]
+struct Position {
+    line: usize,
+    column: usize,
+}
+
+// A record that was skipped because it could not be parsed, reported along with
+// the records that were
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseWarning {
+    // The command whose output it is, e.g. "scontrol show nodes"
+    pub source: String,
+    pub error: Error,
+}
+
+impl ParseWarning {
+    pub fn new(source: &str, error: Error) -> Self {
+        Self {
+            source: source.to_string(),
+            error,
+        }
+    }
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Skipped a record of {}: {}", self.source, self.error)
+    }
+}
+
 pub fn from_str<'de, T: de::Deserialize<'de>>(input: &'de str) -> Result<T> {
-    let deserializer = SlurmDeserializer::from_str(input);
-    T::deserialize(deserializer)
+    T::deserialize(SlurmDeserializer::from_str(input))
+}
+
+// Like `from_str` for a list of records, except that a record that can't be read is
+// skipped. Returns the records that could be read and the errors of the others.
+pub fn from_str_lenient<'de, T: de::Deserialize<'de>>(input: &'de str) -> (Vec<T>, Vec<Error>) {
+    let mut items = Vec::new();
+    let mut errors = Vec::new();
+    for record in records(input) {
+        match record.deserialize() {
+            Ok(item) => items.push(item),
+            Err(e) => errors.push(e),
+        }
+    }
+    (items, errors)
+}
+
+// A record of the output, as read by `records`, `RecordReader` or `AsyncRecordReader`
+#[derive(Debug, Clone, Copy)]
+pub struct Record<'a> {
+    text: &'a str,
+    // The number of the record, starting at 1, and the line it starts at, from 0
+    index: usize,
+    first_line: usize,
+}
+
+impl<'a> Record<'a> {
+    pub fn as_str(&self) -> &'a str {
+        self.text
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    // Errors are given the place of the record in the whole output
+    pub fn deserialize<T: de::Deserialize<'a>>(&self) -> Result<T> {
+        T::deserialize(SlurmDeserializer::from_str(self.text))
+            .map_err(|e| e.in_record(Some(self.index), self.first_line))
+    }
+}
+
+// Reads the records of scontrol output one at a time, so only one record is held in
+// memory however long the output is. See `records` for how the output is split.
+pub struct RecordReader<R> {
+    reader: R,
+    buffer: RecordBuffer,
+}
+
+impl<R: BufRead> RecordReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: RecordBuffer::default(),
+        }
+    }
+
+    // Returns None at the end of the output
+    pub fn next_record(&mut self) -> io::Result<Option<Record<'_>>> {
+        self.buffer.start();
+        while self.reader.read_line(&mut self.buffer.next)? > 0 && !self.buffer.push() {}
+        Ok(self.buffer.record())
+    }
+}
+
+// Like `RecordReader`, for the output of a command that is still running
+pub struct AsyncRecordReader<R> {
+    reader: R,
+    buffer: RecordBuffer,
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncRecordReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: RecordBuffer::default(),
+        }
+    }
+
+    // Returns None at the end of the output
+    pub async fn next_record(&mut self) -> io::Result<Option<Record<'_>>> {
+        self.buffer.start();
+        while self.reader.read_line(&mut self.buffer.next).await? > 0 && !self.buffer.push() {}
+        Ok(self.buffer.record())
+    }
+}
+
+// The record a reader is reading, a line at a time
+#[derive(Default)]
+struct RecordBuffer {
+    text: String,
+    // The line read after the record, which starts the next one if it is not blank
+    next: String,
+    // The line the record starts at, from 0, and the number of lines and records read
+    first_line: usize,
+    lines: usize,
+    records: usize,
+}
+
+impl RecordBuffer {
+    // Starts the next record with the line that ended the last one, if any
+    fn start(&mut self) {
+        self.text.clear();
+        if !self.next.is_empty() {
+            self.take_next();
+        }
+    }
+
+    // Adds the line that was read into `next`, returns whether it ended the record
+    fn push(&mut self) -> bool {
+        self.lines += 1;
+        match line_kind(&self.next, !self.text.is_empty()) {
+            Line::Blank => {
+                self.next.clear();
+                !self.text.is_empty()
+            }
+            Line::Continues => {
+                self.text.push_str(&self.next);
+                self.next.clear();
+                false
+            }
+            Line::Starts if self.text.is_empty() => {
+                self.take_next();
+                false
+            }
+            Line::Starts => true,
+        }
+    }
+
+    // Starts the record at the line in `next`, without its indentation
+    fn take_next(&mut self) {
+        self.first_line = self.lines - 1;
+        self.text.push_str(self.next.trim_start());
+        self.next.clear();
+    }
+
+    fn record(&mut self) -> Option<Record<'_>> {
+        if self.text.is_empty() {
+            return None;
+        }
+        self.records += 1;
+        Some(Record {
+            text: self.text.trim_end(),
+            index: self.records,
+            first_line: self.first_line,
+        })
+    }
+}
+
+// Like `from_str`, with other keys than TEXT_KEYS holding free text
+pub fn from_str_with<'de, T: de::Deserialize<'de>>(
+    input: &'de str,
+    text_keys: &'de [&'de str],
+) -> Result<T> {
+    T::deserialize(SlurmDeserializer { input, text_keys })
+}
+
+// Deserializes a single value, e.g. a TRES list like "cpu=1,mem=15000M"
+pub fn from_value_str<'de, T: de::Deserialize<'de>>(input: &'de str) -> Result<T> {
+    T::deserialize(SlurmValue::Single(Cow::Borrowed(input)))
+}
+
+// Deserializes the `--parsable2` output of sacct, sshare and friends: a header line
+// with the field names followed by one record per line, all separated by '|'
+pub fn from_parsable<'de, T: de::Deserialize<'de>>(input: &'de str) -> Result<Vec<T>> {
+    parsable_rows(input).collect()
+}
+
+// Like `from_parsable`, except that a row that can't be read is skipped. Returns the rows
+// that could be read and the errors of the others.
+pub fn from_parsable_lenient<'de, T: de::Deserialize<'de>>(
+    input: &'de str,
+) -> (Vec<T>, Vec<Error>) {
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    for row in parsable_rows(input) {
+        match row {
+            Ok(row) => rows.push(row),
+            Err(e) => errors.push(e),
+        }
+    }
+    (rows, errors)
+}
+
+fn parsable_rows<'de, T: de::Deserialize<'de>>(
+    input: &'de str,
+) -> impl Iterator<Item = Result<T>> + 'de {
+    let mut lines = input
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty());
+    let keys: Vec<&str> = match lines.next() {
+        Some((_, header)) => header.split('|').map(|k| k.trim()).collect(),
+        None => Vec::new(),
+    };
+    lines.enumerate().map(move |(index, (line_index, line))| {
+        let values: Vec<&str> = line.split('|').collect();
+        if values.len() != keys.len() {
+            let error = Error::custom(format!(
+                "Expected {} fields but found {}: {}",
+                keys.len(),
+                values.len(),
+                line
+            ));
+            return Err(error.in_record(Some(index + 1), line_index));
+        }
+        let mut items = Vec::new();
+        let mut column = 1;
+        for (key, value) in keys.iter().zip(values) {
+            let position = Position { line: 1, column };
+            column += value.chars().count() + 1;
+            let value = value.trim();
+            if !is_null(value) {
+                items.push((*key, SlurmValue::Single(Cow::Borrowed(value)), position));
+            }
+        }
+        T::deserialize(de::value::MapAccessDeserializer::new(SlurmRecord {
+            items,
+            current: 0,
+        }))
+        .map_err(|e| e.in_record(Some(index + 1), line_index))
+    })
+}
+
+// Values Slurm prints for a field that is not set
+fn is_null(value: &str) -> bool {
+    value.is_empty() || value == "(null)" || value == "None" || value == "N/A"
+}
+
+// Keys Slurm prints free text for, e.g. "Reason=Low RealMemory [slurm@2026-10-01T12:00:00]".
+// The text may contain spaces and "key=value" pairs of its own, see `tokenize`.
+// Job and step names are whatever the user passed to --job-name. Features, Licenses and
+// Dependency are expressions Slurm doesn't allow spaces in.
+pub const TEXT_KEYS: &[&str] = &[
+    "JobName",
+    "Name",
+    "Reason",
+    "Comment",
+    "AdminComment",
+    "SystemComment",
+    "Command",
+    "WorkDir",
+    "StdErr",
+    "StdIn",
+    "StdOut",
+    "OS",
+];
+
+// Splits the output of scontrol into records. A record starts at a line that is not
+// indented and goes on over the indented lines below it, so this reads both the records
+// of `scontrol show` that are separated by blank lines and the one-record-per-line
+// output of `scontrol -o show`. The records are found as they are read, in one pass.
+fn records(input: &str) -> Records<'_> {
+    Records {
+        input,
+        lines: input.split_inclusive('\n').enumerate(),
+        offset: 0,
+        current: None,
+        count: 0,
+    }
+}
+
+struct Records<'a> {
+    input: &'a str,
+    lines: std::iter::Enumerate<std::str::SplitInclusive<'a, char>>,
+    offset: usize,
+    // The first line, start and end of the current record
+    current: Option<(usize, usize, usize)>,
+    count: usize,
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Record<'a>;
+
+    fn next(&mut self) -> Option<Record<'a>> {
+        let mut record = None;
+        for (index, line) in self.lines.by_ref() {
+            let (start, end) = (self.offset, self.offset + line.trim_end().len());
+            self.offset += line.len();
+            match line_kind(line, self.current.is_some()) {
+                Line::Blank => record = self.current.take(),
+                Line::Continues => self.current = self.current.map(|(l, s, _)| (l, s, end)),
+                Line::Starts => {
+                    let start = start + (line.len() - line.trim_start().len());
+                    record = self.current.replace((index, start, end));
+                }
+            }
+            if record.is_some() {
+                break;
+            }
+        }
+        let (first_line, start, end) = record.or_else(|| self.current.take())?;
+        self.count += 1;
+        Some(Record {
+            text: &self.input[start..end],
+            index: self.count,
+            first_line,
+        })
+    }
+}
+
+// How a line relates to the record before it
+enum Line {
+    Blank,
+    Continues,
+    Starts,
+}
+
+fn line_kind(line: &str, in_record: bool) -> Line {
+    if line.trim().is_empty() {
+        Line::Blank
+    } else if in_record && line.starts_with(char::is_whitespace) {
+        Line::Continues
+    } else {
+        Line::Starts
+    }
+}
+
+// The whitespace separated words of a line, with their offsets
+fn words(line: &str) -> impl Iterator<Item = (usize, &str)> {
+    line.split_whitespace()
+        .map(move |word| (word.as_ptr() as usize - line.as_ptr() as usize, word))
+}
+
+// Splits a word like "AllocNode:Sid=node2429:26654" into its key and value. The keys of
+// Slurm start with an upper case letter, e.g. "CPUs/Task" or "MCS_label".
+fn split_key(word: &str) -> Option<(&str, &str)> {
+    let (key, value) = word.split_once('=')?;
+    is_key(key).then_some((key, value))
+}
+
+fn is_key(key: &str) -> bool {
+    key.starts_with(|c: char| c.is_ascii_uppercase())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_/:.-".contains(c))
+}
+
+// Splits a record into its (key, value) pairs, in order. Values are a single word,
+// except for the values of `text_keys`:
+// - a text key that starts a line of a multi-line record holds the rest of the line,
+// - anywhere else, e.g. in the output of `scontrol -o`, it holds the words up to the next
+//   key. Text like "RAM Size=1000" is then read as a key, Slurm puts those values on a
+//   line of their own unless it is asked for one line per record.
+// Any other word that is not a key=value pair is an error.
+fn tokenize<'de>(
+    record: &'de str,
+    text_keys: &[&str],
+) -> Result<Vec<(&'de str, &'de str, Position)>> {
+    let multi_line = record.contains('\n');
+    let mut items: Vec<(&str, &str, Position)> = Vec::new();
+    for (line_index, line) in record.lines().enumerate() {
+        let mut words = words(line).peekable();
+        // The offset and column of the last word, to count columns in one pass
+        let (mut offset, mut column) = (0, 1);
+        while let Some((start, word)) = words.next() {
+            column += line[offset..start].chars().count();
+            offset = start;
+            let position = Position {
+                line: line_index + 1,
+                column,
+            };
+            let Some((key, value)) = split_key(word) else {
+                return Err(match items.last() {
+                    Some((key, _, _)) => {
+                        Error::custom(format!("Unexpected '{}' after the value of {}", word, key))
+                            .at(key, word, position)
+                    }
+                    None => Error {
+                        line: Some(position.line),
+                        column: Some(position.column),
+                        ..Error::custom(format!("Expected a key=value pair but found '{}'", word))
+                    },
+                });
+            };
+            if !text_keys.contains(&key) {
+                items.push((key, value, position));
+                continue;
+            }
+            let whole_line = start == line.len() - line.trim_start().len() && multi_line;
+            let mut value_end = start + word.len();
+            while let Some((start, word)) =
+                words.next_if(|(_, word)| whole_line || split_key(word).is_none())
+            {
+                value_end = start + word.len();
+            }
+            items.push((key, &line[start + key.len() + 1..value_end], position));
+        }
+    }
+    Ok(items)
+}
+
+const OCCURRENCES: &str = "$slurm_common::parser::Occurrences";
+
+// One value per occurrence of a key in a record, in order.
+// With `--details`, scontrol repeats "Nodes=... CPU_IDs=... Mem=... GRES=..." once per
+// allocation line. A plain `Vec<T>` can't tell a single line with a list value ("node[1-2]")
+// apart from several lines, this can. Unset values are kept so the lines of different keys
+// stay aligned, use `Occurrences<Option<T>>` to read them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrences<T>(pub Vec<T>);
+
+impl<T> Default for Occurrences<T> {
+    fn default() -> Self {
+        Occurrences(Vec::new())
+    }
+}
+
+impl<'de, T: de::Deserialize<'de>> de::Deserialize<'de> for Occurrences<T> {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct OccurrencesVisitor<T>(std::marker::PhantomData<T>);
+
+        impl<'de, T: de::Deserialize<'de>> de::Visitor<'de> for OccurrencesVisitor<T> {
+            type Value = Occurrences<T>;
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("the occurrences of a key")
+            }
+            fn visit_newtype_struct<D>(
+                self,
+                deserializer: D,
+            ) -> std::result::Result<Self::Value, D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                <Vec<T> as de::Deserialize>::deserialize(deserializer).map(Occurrences)
+            }
+        }
+        deserializer
+            .deserialize_newtype_struct(OCCURRENCES, OccurrencesVisitor(std::marker::PhantomData))
+    }
+}
+
+// Written as one "key=value" per occurrence
+impl<T: Serialize> Serialize for Occurrences<T> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_newtype_struct(OCCURRENCES, &self.0)
+    }
+}
+
+// Slurm prints timestamps without a time zone, in the local time of the cluster.
+// That is the local time zone of this machine unless set otherwise.
+static TIME_ZONE: RwLock<Option<Tz>> = RwLock::new(None);
+
+pub fn set_time_zone(tz: Tz) {
+    *TIME_ZONE.write().unwrap() = Some(tz);
+}
+
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+// Parses a timestamp like "2026-01-31T12:44:31" in the cluster time zone.
+// Returns None for values like "Unknown" that are not a timestamp.
+pub(crate) fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    let naive = NaiveDateTime::parse_from_str(value, TIME_FORMAT).ok()?;
+    match *TIME_ZONE.read().unwrap() {
+        Some(tz) => from_local_time(&tz, naive),
+        None => from_local_time(&Local, naive),
+    }
+}
+
+// Formats a timestamp the way Slurm expects it on the command line
+pub(crate) fn format_time(time: DateTime<Utc>) -> String {
+    match *TIME_ZONE.read().unwrap() {
+        Some(tz) => time.with_timezone(&tz).format(TIME_FORMAT).to_string(),
+        None => time.with_timezone(&Local).format(TIME_FORMAT).to_string(),
+    }
+}
+
+// A local time in the hour repeated when the clocks go back is taken to be the first one,
+// and one in the hour skipped when they go forward to be in the offset before the change.
+fn from_local_time<Z: TimeZone>(tz: &Z, naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+    let time = match tz.from_local_datetime(&naive) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => time,
+        LocalResult::None => {
+            let hour = chrono::Duration::hours(1);
+            tz.from_local_datetime(&(naive - hour)).earliest()? + hour
+        }
+    };
+    Some(time.with_timezone(&Utc))
+}
+
+// Parses a Slurm duration ("minutes", "minutes:seconds", "hours:minutes:seconds",
+// "days-hours", "days-hours:minutes" or "days-hours:minutes:seconds") into seconds.
+// Returns None for "UNLIMITED", "Partition_Limit" and other non-durations.
+pub(crate) fn parse_duration(value: &str) -> Option<i64> {
+    let (days, rest) = match value.split_once('-') {
+        Some((days, rest)) => (days.parse::<i64>().ok()?, rest),
+        None => (0, value),
+    };
+    let parts = rest
+        .split(':')
+        .map(|p| p.parse::<i64>().ok())
+        .collect::<Option<Vec<i64>>>()?;
+    let (hours, minutes, seconds) = match (value.contains('-'), parts.as_slice()) {
+        (false, [m]) => (0, *m, 0),
+        (false, [m, s]) => (0, *m, *s),
+        (true, [h]) => (*h, 0, 0),
+        (true, [h, m]) => (*h, *m, 0),
+        (_, [h, m, s]) => (*h, *m, *s),
+        _ => return None,
+    };
+    Some(((days * 24 + hours) * 60 + minutes) * 60 + seconds)
+}
+
+// Deserializes a timestamp in the cluster time zone, "Unknown" and "None" are None.
+// Use with `#[serde(default, deserialize_with = "parser::timestamp")]`.
+pub fn timestamp<'de, D>(deserializer: D) -> std::result::Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    let value: Option<String> = de::Deserialize::deserialize(deserializer)?;
+    match value.as_deref() {
+        None | Some("Unknown") | Some("None") => Ok(None),
+        Some(value) => parse_time(value)
+            .map(Some)
+            .ok_or_else(|| de::Error::custom(format!("Invalid timestamp: {}", value))),
+    }
+}
+
+// Deserializes a duration into seconds, limits like "UNLIMITED" or "Partition_Limit" are None.
+// Use with `#[serde(default, deserialize_with = "parser::duration")]`.
+pub fn duration<'de, D>(deserializer: D) -> std::result::Result<Option<i64>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    let value: Option<String> = de::Deserialize::deserialize(deserializer)?;
+    match value.as_deref() {
+        None | Some("UNLIMITED" | "INFINITE" | "Partition_Limit" | "NONE" | "Unknown") => Ok(None),
+        Some(value) => parse_duration(value)
+            .map(Some)
+            .ok_or_else(|| de::Error::custom(format!("Invalid duration: {}", value))),
+    }
 }
 
 pub struct SlurmDeserializer<'de> {
     input: &'de str,
+    text_keys: &'de [&'de str],
 }
 
 impl<'de> SlurmDeserializer<'de> {
     fn from_str(input: &'de str) -> Self {
-        SlurmDeserializer { input }
+        SlurmDeserializer {
+            input,
+            text_keys: TEXT_KEYS,
+        }
     }
 }
 
@@ -56,14 +699,9 @@ impl<'de> de::Deserializer<'de> for SlurmDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let records: Vec<&str> = self
-            .input
-            .split("\n\n")
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .collect();
         // If there is only one record, it's a map, otherwise it's a sequence
-        if records.len() == 1 {
+        let mut records = records(self.input);
+        if records.next().is_some() && records.next().is_none() {
             self.deserialize_map(visitor)
         } else {
             self.deserialize_seq(visitor)
@@ -74,16 +712,9 @@ impl<'de> de::Deserializer<'de> for SlurmDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        // Split by blank lines (double newlines)
-        let records: Vec<&str> = self
-            .input
-            .split("\n\n")
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .collect();
         visitor.visit_seq(RecordSeq {
-            records,
-            current: 0,
+            records: records(self.input),
+            text_keys: self.text_keys,
         })
     }
 
@@ -92,59 +723,43 @@ impl<'de> de::Deserializer<'de> for SlurmDeserializer<'de> {
         V: de::Visitor<'de>,
     {
         // Deserialize the first record as a map
-        let record = self
-            .input
-            .split("\n\n")
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
+        let Record {
+            text, first_line, ..
+        } = records(self.input)
             .next()
             .ok_or_else(|| de::Error::custom("No record found"))?;
-        let mut map = HashMap::new();
-        let key_regex = Regex::new(r"(?:^|[\s])([a-zA-Z0-9_\/-:.]+)=")
-            .map_err(|e| de::Error::custom(e.to_string()))?;
-
-        let matches: Vec<_> = key_regex.find_iter(record).collect();
-
-        for i in 0..matches.len() {
-            let m = matches[i];
-            let key_capture = key_regex.captures(m.as_str()).unwrap().get(1).unwrap();
-            let key = key_capture.as_str();
-
-            let val_start = m.end();
-            let val_end = if i + 1 < matches.len() {
-                matches[i + 1].start()
-            } else {
-                record.len()
-            };
-
-            let raw_value = &record[val_start..val_end];
-            let value = raw_value.trim();
-
-            // Skip "null" or None or N/A values
-            if value == "(null)" || value == "None" || value == "N/A" {
-                continue;
-            }
-            match map.entry(key) {
-                Entry::Occupied(mut entry) => {
-                    let existing = entry.get_mut();
-                    match existing {
-                        SlurmValue::Single(s) => {
-                            *existing = SlurmValue::Repeated(vec![s, value]);
-                        }
-                        SlurmValue::Repeated(v) => v.push(value),
-                    }
-                }
+        let tokens = tokenize(text, self.text_keys).map_err(|e| e.in_record(None, first_line))?;
+        // The first value of each key at its position, and the values of any repeats.
+        // Only keys that are repeated allocate.
+        let mut keys: HashMap<&str, usize> = HashMap::with_capacity(tokens.len());
+        let mut values: Vec<(&str, &str, Position, Vec<&str>)> = Vec::with_capacity(tokens.len());
+        for (key, value, position) in tokens {
+            match keys.entry(key) {
+                Entry::Occupied(entry) => values[*entry.get()].3.push(value),
                 Entry::Vacant(entry) => {
-                    entry.insert(SlurmValue::Single(value));
+                    entry.insert(values.len());
+                    values.push((key, value, position, Vec::new()));
                 }
             }
         }
-
-        // Convert to vec for MapAccess
-        // We sort keys? No, MapAccess doesn't require order unless struct requires it?
-        // Actually standard HashMap iteration is random. Serde is fine with that for maps/structs usually.
-        let items: Vec<(&str, SlurmValue<'de>)> = map.into_iter().collect();
-        visitor.visit_map(SlurmRecord { items, current: 0 })
+        let items = values
+            .into_iter()
+            .filter_map(|(key, first, position, mut repeats)| {
+                if repeats.is_empty() {
+                    // Skip keys that are only set to empty, "null", None or N/A values
+                    let value = SlurmValue::Single(Cow::Borrowed(first));
+                    (!is_null(first)).then_some((key, value, position))
+                } else {
+                    // Repeated keys keep their unset values to stay aligned with the
+                    // other keys on their lines
+                    repeats.insert(0, first);
+                    Some((key, SlurmValue::Repeated(repeats), position))
+                }
+            })
+            .collect();
+        visitor
+            .visit_map(SlurmRecord { items, current: 0 })
+            .map_err(|e| e.in_record(None, first_line))
     }
 
     forward_to_deserialize_any! {
@@ -155,8 +770,8 @@ impl<'de> de::Deserializer<'de> for SlurmDeserializer<'de> {
 }
 
 struct RecordSeq<'de> {
-    records: Vec<&'de str>,
-    current: usize,
+    records: Records<'de>,
+    text_keys: &'de [&'de str],
 }
 
 impl<'de> de::SeqAccess<'de> for RecordSeq<'de> {
@@ -166,18 +781,20 @@ impl<'de> de::SeqAccess<'de> for RecordSeq<'de> {
     where
         T: de::DeserializeSeed<'de>,
     {
-        if self.current >= self.records.len() {
+        let Some(record) = self.records.next() else {
             return Ok(None);
-        }
-        let record = self.records[self.current];
-        self.current += 1;
-        seed.deserialize(SlurmDeserializer::from_str(record))
-            .map(Some)
+        };
+        seed.deserialize(SlurmDeserializer {
+            input: record.text,
+            text_keys: self.text_keys,
+        })
+        .map(Some)
+        .map_err(|e| e.in_record(Some(record.index), record.first_line))
     }
 }
 
 struct SlurmRecord<'de> {
-    items: Vec<(&'de str, SlurmValue<'de>)>,
+    items: Vec<(&'de str, SlurmValue<'de>, Position)>,
     current: usize,
 }
 
@@ -192,7 +809,7 @@ impl<'de> de::MapAccess<'de> for SlurmRecord<'de> {
             return Ok(None);
         }
         // Deserialize the key (which is a string)
-        seed.deserialize(de::value::StrDeserializer::new(&self.items[self.current].0))
+        seed.deserialize(de::value::StrDeserializer::new(self.items[self.current].0))
             .map(Some)
     }
 
@@ -200,19 +817,60 @@ impl<'de> de::MapAccess<'de> for SlurmRecord<'de> {
     where
         V: de::DeserializeSeed<'de>,
     {
-        let value = self.items[self.current].1.clone();
+        let (key, value, position) = &self.items[self.current];
         self.current += 1;
         // Use our ValueDeserializer that can parse strings into numbers
-        seed.deserialize(value)
+        seed.deserialize(value.clone()).map_err(|e| {
+            let raw = match value {
+                SlurmValue::Single(s) => s.to_string(),
+                SlurmValue::Repeated(v) => v.join(" "),
+            };
+            e.at(key, &raw, *position)
+        })
     }
 }
 // A value in a record
 #[derive(Clone)]
 enum SlurmValue<'de> {
-    Single(&'de str),
+    Single(Cow<'de, str>),   // Owned when produced by expanding a hostlist
     Repeated(Vec<&'de str>), // A key that appears multiple times
 }
 
+impl<'de> SlurmValue<'de> {
+    // Comma-separated values are read as a hostlist, so "gpu[01-02],cpu1"
+    // yields "gpu01", "gpu02" and "cpu1". Values without brackets are split on commas.
+    fn split_list(value: Cow<'de, str>) -> Result<Vec<Cow<'de, str>>> {
+        let expand = |item: &str| -> Result<Vec<Cow<'de, str>>> {
+            Ok(crate::hostlist::expand(item)
+                .map_err(de::Error::custom)?
+                .into_iter()
+                .map(Cow::Owned)
+                .collect())
+        };
+        match value {
+            Cow::Borrowed(s) => {
+                let mut values = Vec::new();
+                for item in crate::hostlist::split(s).map_err(de::Error::custom)? {
+                    if item.contains('[') {
+                        values.extend(expand(item)?);
+                    } else {
+                        values.push(Cow::Borrowed(item));
+                    }
+                }
+                Ok(values)
+            }
+            Cow::Owned(s) => expand(&s),
+        }
+    }
+
+    fn visit_str<V: de::Visitor<'de>>(value: Cow<'de, str>, visitor: V) -> Result<V::Value> {
+        match value {
+            Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+            Cow::Owned(s) => visitor.visit_string(s),
+        }
+    }
+}
+
 // Use paste! macro to implement the visitor for each number type
 macro_rules! impl_num_visitor {
     {$($type:ident)*} => {
@@ -241,9 +899,9 @@ impl<'de> de::Deserializer<'de> for SlurmValue<'de> {
     {
         // Default to string
         match self {
-            SlurmValue::Single(s) => visitor.visit_borrowed_str(s),
+            SlurmValue::Single(s) => SlurmValue::visit_str(s, visitor),
             SlurmValue::Repeated(v) => visitor.visit_seq(ValueSeq {
-                values: v,
+                values: v.into_iter().map(Cow::Borrowed).collect(),
                 current: 0,
             }),
         }
@@ -252,8 +910,12 @@ impl<'de> de::Deserializer<'de> for SlurmValue<'de> {
     where
         V: de::Visitor<'de>,
     {
-        // Since we filtered NULLs/Nones at map level, if we are here, it's Some.
-        visitor.visit_some(self)
+        // NULLs/Nones are filtered at the map level, except for the
+        // elements of repeated keys
+        match self {
+            SlurmValue::Single(s) if is_null(&s) => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
     }
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
@@ -281,11 +943,11 @@ impl<'de> de::Deserializer<'de> for SlurmValue<'de> {
     {
         match self {
             SlurmValue::Single(s) => visitor.visit_seq(ValueSeq {
-                values: s.split(",").collect(),
+                values: SlurmValue::split_list(s)?,
                 current: 0,
             }),
             SlurmValue::Repeated(v) => visitor.visit_seq(ValueSeq {
-                values: v,
+                values: v.into_iter().map(Cow::Borrowed).collect(),
                 current: 0,
             }),
         }
@@ -296,7 +958,7 @@ impl<'de> de::Deserializer<'de> for SlurmValue<'de> {
         V: de::Visitor<'de>,
     {
         let items = match self {
-            SlurmValue::Single(s) => s
+            SlurmValue::Single(Cow::Borrowed(s)) => s
                 .split(",")
                 .filter(|s| !s.trim().is_empty())
                 .map(|s| {
@@ -305,6 +967,12 @@ impl<'de> de::Deserializer<'de> for SlurmValue<'de> {
                         .ok_or(de::Error::custom(format!("Invalid key-value pair: {}", s)))
                 })
                 .collect::<Result<Vec<(&'de str, &'de str)>>>()?,
+            SlurmValue::Single(Cow::Owned(s)) => {
+                return Err(de::Error::custom(format!(
+                    "Expected key-value pairs, got {}",
+                    s
+                )))
+            }
             SlurmValue::Repeated(_) => return self.deserialize_any(visitor),
         };
         visitor.visit_map(ValueMap { items, current: 0 })
@@ -315,24 +983,28 @@ impl<'de> de::Deserializer<'de> for SlurmValue<'de> {
         V: de::Visitor<'de>,
     {
         match self {
-            SlurmValue::Single(s) => visitor.visit_borrowed_str(s),
+            SlurmValue::Single(s) => SlurmValue::visit_str(s, visitor),
             SlurmValue::Repeated(v) => visitor.visit_borrowed_str(v[0]),
         }
     }
 
     fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(self, name: &str, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
         match self {
-            SlurmValue::Single(s) => visitor.visit_seq(ValueSeq {
-                values: s.split(",").collect(),
-                current: 0,
-            }),
-            SlurmValue::Repeated(v) => visitor.visit_seq(ValueSeq {
-                values: v,
-                current: 0,
-            }),
+            // A key that appears once is a single occurrence, not a list
+            SlurmValue::Single(Cow::Borrowed(s)) if name == OCCURRENCES => {
+                visitor.visit_newtype_struct(SlurmValue::Repeated(vec![s]))
+            }
+            _ => visitor.visit_newtype_struct(self),
         }
     }
 
@@ -365,12 +1037,12 @@ impl<'de> de::Deserializer<'de> for SlurmValue<'de> {
     }
 
     forward_to_deserialize_any! {
-        char string str bytes byte_buf unit unit_struct newtype_struct ignored_any
+        char string str bytes byte_buf unit unit_struct ignored_any
     }
 }
 
 struct ValueEnum<'de> {
-    value: &'de str,
+    value: Cow<'de, str>,
 }
 
 impl<'de> de::EnumAccess<'de> for ValueEnum<'de> {
@@ -418,7 +1090,7 @@ impl<'de> de::VariantAccess<'de> for EnumVariant {
 
 // A sequence of values
 struct ValueSeq<'de> {
-    values: Vec<&'de str>,
+    values: Vec<Cow<'de, str>>,
     current: usize,
 }
 
@@ -432,7 +1104,7 @@ impl<'de> de::SeqAccess<'de> for ValueSeq<'de> {
         if self.current >= self.values.len() {
             return Ok(None);
         }
-        let value = self.values[self.current];
+        let value = std::mem::take(&mut self.values[self.current]);
         self.current += 1;
         seed.deserialize(SlurmValue::Single(value)).map(Some)
     }
@@ -454,7 +1126,8 @@ impl<'de> de::MapAccess<'de> for ValueMap<'de> {
             return Ok(None);
         }
         let key = self.items[self.current].0;
-        seed.deserialize(SlurmValue::Single(key)).map(Some)
+        seed.deserialize(SlurmValue::Single(Cow::Borrowed(key)))
+            .map(Some)
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
@@ -463,31 +1136,543 @@ impl<'de> de::MapAccess<'de> for ValueMap<'de> {
     {
         let value = self.items[self.current].1;
         self.current += 1;
-        seed.deserialize(SlurmValue::Single(value))
+        seed.deserialize(SlurmValue::Single(Cow::Borrowed(value)))
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde::Deserialize;
+// Serializes a record, or a list of records, in the format of `scontrol show`: fields
+// that don't fit on a line go on indented lines below it and records are separated by
+// blank lines. Fields that are None are left out. This is the inverse of `from_str`.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    write_records(value.serialize(ValueSerializer)?, false)
+}
 
-    #[test]
-    fn test_parse_node() {
-        let input = "NodeName=node4504 Arch=x86_64 CoresPerSocket=32
-   CPUAlloc=0 CPUEfctv=64 CPUTot=64 CPULoad=0.04
-   AvailableFeatures=node4504,rocky8
-   ActiveFeatures=node4504,rocky8
-   Gres=gpu:l40s:4(S:0-1)
-   NodeAddr=node4504 NodeHostName=node4504 Version=25.05.4
-   OS=Linux 4.18.0-372.9.1.el8.x86_64 #1 SMP Tue May 10 14:48:47 UTC 2022
-   RealMemory=1031314 AllocMem=0 FreeMem=77430 Sockets=2 Boards=1
-   RestrictedCoresPerGPU=16(0-63)
-   State=IDLE ThreadsPerCore=2 TmpDisk=0 Weight=1 Owner=N/A MCS_label=N/A
-   Partitions=mit_preemptable
-   BootTime=2025-07-07T16:34:43 SlurmdStartTime=2026-01-29T15:08:10
-   LastBusyTime=2026-01-29T15:08:03 ResumeAfterTime=None
-   CfgTRES=cpu=64,mem=1031314M,billing=64,gres/gpu=4,gres/gpu:l40s=4
+// Like `to_string`, with one record per line like `scontrol -o show`
+pub fn to_string_one_line<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    write_records(value.serialize(ValueSerializer)?, true)
+}
+
+// The fields of a record as "key=value" arguments, e.g. for `scontrol update`.
+// Each argument is passed on its own, so values may contain spaces.
+pub fn to_args<T: Serialize + ?Sized>(value: &T) -> Result<Vec<String>> {
+    let mut records = into_records(value.serialize(ValueSerializer)?)?;
+    if records.len() != 1 {
+        return Err(Error::custom(format!(
+            "Expected one record but found {}",
+            records.len()
+        )));
+    }
+    Ok(fields(records.remove(0))?
+        .into_iter()
+        .flat_map(|field| {
+            let key = field.key;
+            field
+                .values
+                .into_iter()
+                .map(move |value| format!("{}={}", key, value))
+        })
+        .collect())
+}
+
+// Serializes a timestamp in the cluster time zone, None as "Unknown".
+// Use with `#[serde(serialize_with = "parser::serialize_timestamp")]`.
+pub fn serialize_timestamp<S>(
+    value: &Option<DateTime<Utc>>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: ser::Serializer,
+{
+    match value {
+        Some(time) => serializer.serialize_str(&format_time(*time)),
+        None => serializer.serialize_str("Unknown"),
+    }
+}
+
+// Serializes seconds as a duration like "1-12:00:00", None as "UNLIMITED".
+// Use with `#[serde(serialize_with = "parser::serialize_duration")]`.
+pub fn serialize_duration<S>(
+    value: &Option<i64>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: ser::Serializer,
+{
+    match value {
+        Some(seconds) => serializer.serialize_str(&format_duration(*seconds)),
+        None => serializer.serialize_str("UNLIMITED"),
+    }
+}
+
+// Formats seconds the way Slurm prints a duration, "days-hours:minutes:seconds"
+// or "hours:minutes:seconds" for less than a day
+pub(crate) fn format_duration(seconds: i64) -> String {
+    let (days, rest) = (seconds / 86400, seconds % 86400);
+    let time = format!(
+        "{:02}:{:02}:{:02}",
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    );
+    if days > 0 {
+        format!("{}-{}", days, time)
+    } else {
+        time
+    }
+}
+
+// A value on its way to the output
+enum Value {
+    // None, a record leaves the key out
+    Unset,
+    Single(String),
+    List(Vec<Value>),
+    Record(Vec<(String, Value)>),
+    // The values of a key that is repeated, see `Occurrences`
+    Repeated(Vec<Value>),
+}
+
+impl Value {
+    // Writes a value the way `SlurmValue` reads it: the items of lists and the
+    // key=value pairs of maps are separated by commas
+    fn write(self) -> Result<String> {
+        match self {
+            Value::Unset => Ok("(null)".to_string()),
+            Value::Single(s) => Ok(s),
+            Value::List(items) => Ok(items
+                .into_iter()
+                .map(Value::write_item)
+                .collect::<Result<Vec<_>>>()?
+                .join(",")),
+            Value::Record(fields) => Ok(fields
+                .into_iter()
+                .map(|(key, value)| {
+                    if key.contains(['=', ',']) {
+                        return Err(Error::custom(format!("Invalid key in a value: {}", key)));
+                    }
+                    Ok(format!("{}={}", key, value.write_item()?))
+                })
+                .collect::<Result<Vec<_>>>()?
+                .join(",")),
+            Value::Repeated(_) => Err(Error::custom("Occurrences must be the value of a key")),
+        }
+    }
+
+    fn write_item(self) -> Result<String> {
+        let item = self.write()?;
+        if item.contains(',') {
+            return Err(Error::custom(format!(
+                "An item of a list can't contain a comma: {}",
+                item
+            )));
+        }
+        Ok(item)
+    }
+}
+
+fn write_records(value: Value, one_line: bool) -> Result<String> {
+    let mut records = Vec::new();
+    for (index, record) in into_records(value)?.into_iter().enumerate() {
+        let text = fields(record)
+            .and_then(|fields| write_record(&fields, one_line))
+            .map_err(|e| Error {
+                record: Some(index + 1),
+                ..e
+            })?;
+        records.push(text + "\n");
+    }
+    Ok(records.join(if one_line { "" } else { "\n" }))
+}
+
+// The records of a value, which is a record or a list of them
+fn into_records(value: Value) -> Result<Vec<Vec<(String, Value)>>> {
+    match value {
+        Value::Record(fields) => Ok(vec![fields]),
+        Value::List(items) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::Record(fields) => Ok(fields),
+                _ => Err(Error::custom("Expected a list of records")),
+            })
+            .collect(),
+        _ => Err(Error::custom("Expected a record or a list of records")),
+    }
+}
+
+// A key of a record with its written values, one per occurrence
+struct Field {
+    key: String,
+    values: Vec<String>,
+    repeated: bool,
+}
+
+fn fields(record: Vec<(String, Value)>) -> Result<Vec<Field>> {
+    let mut fields = Vec::new();
+    for (key, value) in record {
+        if !is_key(&key) {
+            return Err(Error::custom(format!("Invalid key: {}", key)));
+        }
+        let (values, repeated) = match value {
+            Value::Unset => continue,
+            Value::Repeated(values) if values.is_empty() => continue,
+            Value::Repeated(values) => (values, true),
+            value => (vec![value], false),
+        };
+        let values = values
+            .into_iter()
+            .map(Value::write)
+            .collect::<Result<Vec<_>>>()
+            .map_err(|e| Error {
+                key: Some(key.clone()),
+                ..e
+            })?;
+        fields.push(Field {
+            key,
+            values,
+            repeated,
+        });
+    }
+    Ok(fields)
+}
+
+// The width scontrol wraps the fields of a record at
+const LINE_WIDTH: usize = 80;
+
+// Writes the fields of a record so that `tokenize` reads them back. The values of
+// TEXT_KEYS get a line of their own, where they hold the rest of the line, and runs of
+// repeated keys get a line per occurrence like the allocations of `scontrol show job -d`.
+fn write_record(fields: &[Field], one_line: bool) -> Result<String> {
+    let invalid = |field: &Field, value: &str, message: &str| Error {
+        key: Some(field.key.clone()),
+        value: Some(value.to_string()),
+        ..Error::custom(format!("The value of {} {}", field.key, message))
+    };
+    let mut lines: Vec<String> = Vec::new();
+    // Whether more fields fit on the last line
+    let mut open = false;
+    let mut add = |words: String, own_line: bool| {
+        match lines.last_mut() {
+            Some(line)
+                if one_line || (open && !own_line && line.len() + words.len() < LINE_WIDTH) =>
+            {
+                line.push(' ');
+                line.push_str(&words);
+            }
+            _ => lines.push(words),
+        }
+        open = !own_line;
+    };
+    let mut texts = Vec::new();
+    let mut i = 0;
+    while i < fields.len() {
+        let field = &fields[i];
+        for value in &field.values {
+            if value.contains(['\n', '\r']) {
+                return Err(invalid(field, value, "can't span lines"));
+            }
+        }
+        if field.repeated {
+            let run = &fields[i..];
+            let run = &run[..run.iter().take_while(|f| f.repeated).count()];
+            let count = run.iter().map(|f| f.values.len()).max().unwrap_or(0);
+            for n in 0..count {
+                let mut words = Vec::new();
+                for field in run {
+                    if let Some(value) = field.values.get(n) {
+                        if value.contains(char::is_whitespace) {
+                            return Err(invalid(field, value, "can't contain spaces"));
+                        }
+                        words.push(format!("{}={}", field.key, value));
+                    }
+                }
+                add(words.join(" "), !one_line);
+            }
+            i += run.len();
+            continue;
+        }
+        let value = &field.values[0];
+        if TEXT_KEYS.contains(&field.key.as_str()) {
+            if value.trim() != value {
+                return Err(invalid(field, value, "can't start or end with spaces"));
+            }
+            texts.push((field, value));
+            add(format!("{}={}", field.key, value), !one_line);
+        } else if value.contains(char::is_whitespace) {
+            return Err(invalid(field, value, "can't contain spaces"));
+        } else {
+            add(format!("{}={}", field.key, value), false);
+        }
+        i += 1;
+    }
+    // Unless it has a line of its own, a text runs up to the next word that looks like a key
+    if lines.len() == 1 {
+        for (field, value) in texts {
+            if words(value).skip(1).any(|(_, w)| split_key(w).is_some()) {
+                return Err(invalid(
+                    field,
+                    value,
+                    "can't contain key=value pairs on one line",
+                ));
+            }
+        }
+    }
+    Ok(lines.join("\n   "))
+}
+
+// Builds the `Value` of anything that implements `Serialize`
+struct ValueSerializer;
+
+// Numbers are written the way `str::parse` reads them back
+macro_rules! impl_num_serializer {
+    {$($type:ident)*} => {
+        paste::paste! {
+            $(fn [<serialize_ $type>](self, v: $type) -> Result<Value> {
+                Ok(Value::Single(v.to_string()))
+            })*
+        }
+    }
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = ListSerializer;
+    type SerializeTuple = ListSerializer;
+    type SerializeTupleStruct = ListSerializer;
+    type SerializeTupleVariant = ser::Impossible<Value, Error>;
+    type SerializeMap = RecordSerializer;
+    type SerializeStruct = RecordSerializer;
+    type SerializeStructVariant = ser::Impossible<Value, Error>;
+
+    impl_num_serializer! {
+        u8 u16 u32 u64 u128 i8 i16 i32 i64 i128
+        f32 f64 char
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<Value> {
+        Ok(Value::Single(if v { "1" } else { "0" }.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value> {
+        Ok(Value::Single(v.to_string()))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Value> {
+        Err(Error::custom("Bytes not supported"))
+    }
+
+    fn serialize_none(self) -> Result<Value> {
+        Ok(Value::Unset)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value> {
+        Ok(Value::Unset)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value> {
+        Ok(Value::Unset)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value> {
+        Ok(Value::Single(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Value> {
+        match value.serialize(self)? {
+            Value::List(values) if name == OCCURRENCES => Ok(Value::Repeated(values)),
+            value => Ok(value),
+        }
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Value> {
+        Err(Error::custom("Newtype variant not supported"))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<ListSerializer> {
+        Ok(ListSerializer {
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ListSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<ListSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(Error::custom("Tuple variant not supported"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<RecordSerializer> {
+        Ok(RecordSerializer {
+            fields: Vec::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<RecordSerializer> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(Error::custom("Struct variant not supported"))
+    }
+}
+
+struct ListSerializer {
+    items: Vec<Value>,
+}
+
+impl ser::SerializeSeq for ListSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.items.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(Value::List(self.items))
+    }
+}
+
+impl ser::SerializeTuple for ListSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for ListSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct RecordSerializer {
+    fields: Vec<(String, Value)>,
+    // The key of a map entry whose value is next
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for RecordSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        match key.serialize(ValueSerializer)? {
+            Value::Single(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => Err(Error::custom(
+                "The keys of a map must be strings or numbers",
+            )),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error::custom("A map value without a key"))?;
+        self.fields.push((key, value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(Value::Record(self.fields))
+    }
+}
+
+impl ser::SerializeStruct for RecordSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.fields
+            .push((key.to_string(), value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(Value::Record(self.fields))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[test]
+    fn test_parse_node() {
+        let input = "NodeName=node4504 Arch=x86_64 CoresPerSocket=32
+   CPUAlloc=0 CPUEfctv=64 CPUTot=64 CPULoad=0.04
+   AvailableFeatures=node4504,rocky8
+   ActiveFeatures=node4504,rocky8
+   Gres=gpu:l40s:4(S:0-1)
+   NodeAddr=node4504 NodeHostName=node4504 Version=25.05.4
+   OS=Linux 4.18.0-372.9.1.el8.x86_64 #1 SMP Tue May 10 14:48:47 UTC 2022
+   RealMemory=1031314 AllocMem=0 FreeMem=77430 Sockets=2 Boards=1
+   RestrictedCoresPerGPU=16(0-63)
+   State=IDLE ThreadsPerCore=2 TmpDisk=0 Weight=1 Owner=N/A MCS_label=N/A
+   Partitions=mit_preemptable
+   BootTime=2025-07-07T16:34:43 SlurmdStartTime=2026-01-29T15:08:10
+   LastBusyTime=2026-01-29T15:08:03 ResumeAfterTime=None
+   CfgTRES=cpu=64,mem=1031314M,billing=64,gres/gpu=4,gres/gpu:l40s=4
    AllocTRES=
    CurrentWatts=0 AveWatts=0";
 
@@ -565,12 +1750,426 @@ mod tests {
         assert_eq!(job.ReqTRES, "cpu=1,mem=15000M,node=1,billing=1");
     }
 
+    #[test]
+    fn test_parse_text_values() {
+        let input = "NodeName=node101 Arch=x86_64
+   State=IDLE+DRAIN ThreadsPerCore=2
+   Reason=Kill task failed [root@2026-10-01T12:00:00]
+   LastBusyTime=2026-10-01T11:58:00 ResumeAfterTime=None
+
+NodeName=node102 Arch=x86_64
+   State=DOWN+DRAIN ThreadsPerCore=2
+   Reason=NHC: check_hw_mem: RAM size=1000 < expected [slurm@2026-10-02T08:00:00]
+   LastBusyTime=2026-10-02T07:00:00 ResumeAfterTime=None";
+
+        #[allow(non_snake_case)]
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Node<'a> {
+            NodeName: &'a str,
+            Reason: &'a str,
+            LastBusyTime: &'a str,
+            size: Option<&'a str>,
+        }
+
+        let nodes = Vec::<Node>::deserialize(SlurmDeserializer::from_str(input)).unwrap();
+        assert_eq!(
+            nodes[0].Reason,
+            "Kill task failed [root@2026-10-01T12:00:00]"
+        );
+        assert_eq!(nodes[0].LastBusyTime, "2026-10-01T11:58:00");
+        // The text of a reason is not split into keys
+        assert_eq!(
+            nodes[1].Reason,
+            "NHC: check_hw_mem: RAM size=1000 < expected [slurm@2026-10-02T08:00:00]"
+        );
+        assert_eq!(nodes[1].size, None);
+        assert_eq!(nodes[1].LastBusyTime, "2026-10-02T07:00:00");
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let record = "JobId=1 JobName=train model UserId=alice(1001)
+   JobState=PENDING Reason=Resources Dependency=(null)
+   Command=/home/alice/run.sh --lr=0.1 --name x
+   WorkDir=/home/alice/my project
+   Comment=
+   StdOut=/home/alice/out %j.log";
+        let items = tokenize(record, TEXT_KEYS).unwrap();
+        let get = |key: &str| items.iter().find(|(k, _, _)| *k == key).map(|(_, v, _)| *v);
+        assert_eq!(get("JobName"), Some("train model"));
+        assert_eq!(get("UserId"), Some("alice(1001)"));
+        assert_eq!(get("Reason"), Some("Resources"));
+        assert_eq!(get("Dependency"), Some("(null)"));
+        assert_eq!(get("Command"), Some("/home/alice/run.sh --lr=0.1 --name x"));
+        assert_eq!(get("WorkDir"), Some("/home/alice/my project"));
+        assert_eq!(get("Comment"), Some(""));
+        assert_eq!(get("StdOut"), Some("/home/alice/out %j.log"));
+        assert_eq!(items.len(), 10);
+
+        // Other values are a single word, anything else is an error
+        let err = tokenize("JobId=1 Partition=gpu cpu Account=a", TEXT_KEYS).unwrap_err();
+        assert_eq!(err.message, "Unexpected 'cpu' after the value of Partition");
+        assert_eq!((err.line, err.column), (Some(1), Some(23)));
+        assert!(tokenize("JobId=1 lr=0.1", TEXT_KEYS).is_err());
+        assert!(tokenize("No jobs in the system", TEXT_KEYS).is_err());
+
+        // With other text keys
+        let err = tokenize("   Features=a b\n   Note=c d", &["Features"]).unwrap_err();
+        assert_eq!(err.message, "Unexpected 'd' after the value of Note");
+    }
+
+    #[test]
+    fn test_parse_one_line() {
+        // scontrol -o prints each record on a line of its own
+        let input = "NodeName=node101 Arch=x86_64 State=IDLE+DRAIN Reason=Kill task failed [root@2026-10-01T12:00:00] Comment=none
+NodeName=node102 Arch=x86_64 State=IDLE Reason=NHC: RAM size=1000 Comment=x
+NodeName=node103 OS=Linux 4.18.0 #1 SMP State=DOWN";
+
+        #[allow(non_snake_case)]
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Node<'a> {
+            NodeName: &'a str,
+            State: &'a str,
+            Reason: Option<&'a str>,
+            OS: Option<&'a str>,
+        }
+
+        let nodes: Vec<Node> = from_str(input).unwrap();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(
+            nodes[0].Reason,
+            Some("Kill task failed [root@2026-10-01T12:00:00]")
+        );
+        assert_eq!(nodes[1].Reason, Some("NHC: RAM size=1000"));
+        assert_eq!(nodes[2].OS, Some("Linux 4.18.0 #1 SMP"));
+        assert_eq!(nodes[2].State, "DOWN");
+
+        // The indented lines of a record may follow without a blank line
+        let input = "NodeName=node1\n   State=IDLE\nNodeName=node2\n   State=MIXED\n";
+        let nodes: Vec<Node> = from_str(input).unwrap();
+        assert_eq!((nodes[0].State, nodes[1].State), ("IDLE", "MIXED"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        #[allow(non_snake_case)]
+        #[derive(Deserialize, Debug)]
+        struct Node<'a> {
+            NodeName: &'a str,
+            CPUTot: u32,
+        }
+
+        let input = "NodeName=node1 CPUTot=64
+
+NodeName=node2 Arch=x86_64
+   State=IDLE CPUTot=lots
+
+NodeName=node3 CPUTot=32
+NodeName=node4";
+
+        let err = from_str::<Vec<Node>>(input).unwrap_err();
+        assert_eq!(err.message, "Invalid number: lots");
+        assert_eq!(
+            (err.record, err.line, err.column),
+            (Some(2), Some(4), Some(15))
+        );
+        assert_eq!(
+            (err.key.as_deref(), err.value.as_deref()),
+            (Some("CPUTot"), Some("lots"))
+        );
+        assert_eq!(
+            err.to_string(),
+            "Invalid number: lots (record 2, line 4, column 15, CPUTot=lots)"
+        );
+
+        // The lenient mode skips the bad records and reads the others
+        let (nodes, errors) = from_str_lenient::<Node>(input);
+        let cpus: Vec<(&str, u32)> = nodes.iter().map(|n| (n.NodeName, n.CPUTot)).collect();
+        assert_eq!(cpus, vec![("node1", 64), ("node3", 32)]);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].record, Some(2));
+        // A missing key is reported at the start of its record
+        assert_eq!(errors[1].message, "missing field `CPUTot`");
+        assert_eq!((errors[1].record, errors[1].line), (Some(4), Some(7)));
+
+        let err = from_parsable::<Node>("NodeName|CPUTot\nnode1|64\n\nnode2|x\n").unwrap_err();
+        assert_eq!(
+            (err.record, err.line, err.column),
+            (Some(2), Some(4), Some(7))
+        );
+        assert_eq!(err.key.as_deref(), Some("CPUTot"));
+    }
+
+    #[allow(non_snake_case)]
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Record {
+        NodeName: String,
+        CPUTot: u32,
+        CPULoad: f64,
+        Weight: Option<i64>,
+        Idle: bool,
+        #[serde(default)]
+        Partitions: Vec<String>,
+        #[serde(default)]
+        CfgTRES: std::collections::BTreeMap<String, u64>,
+        Reason: Option<String>,
+        #[serde(default)]
+        Mem: Occurrences<u32>,
+        #[serde(default)]
+        GRES: Occurrences<Option<String>>,
+        #[serde(
+            default,
+            deserialize_with = "duration",
+            serialize_with = "serialize_duration"
+        )]
+        MaxTime: Option<i64>,
+    }
+
+    fn record() -> Record {
+        Record {
+            NodeName: "node101".to_string(),
+            CPUTot: 64,
+            CPULoad: 0.04,
+            Weight: None,
+            Idle: true,
+            Partitions: vec!["mit_normal".to_string(), "mit_preemptable".to_string()],
+            CfgTRES: [("cpu".to_string(), 64), ("gres/gpu".to_string(), 4)].into(),
+            Reason: Some("Kill task failed [root@2026-10-01T12:00:00]".to_string()),
+            Mem: Occurrences(vec![1000, 2000]),
+            GRES: Occurrences(vec![Some("gpu:2".to_string()), None]),
+            MaxTime: Some(36 * 3600),
+        }
+    }
+
+    #[test]
+    fn test_write_records() {
+        let node = record();
+        let text = to_string(&node).unwrap();
+        assert_eq!(
+            text,
+            "NodeName=node101 CPUTot=64 CPULoad=0.04 Idle=1
+   Partitions=mit_normal,mit_preemptable CfgTRES=cpu=64,gres/gpu=4
+   Reason=Kill task failed [root@2026-10-01T12:00:00]
+   Mem=1000 GRES=gpu:2
+   Mem=2000 GRES=(null)
+   MaxTime=1-12:00:00
+"
+        );
+        assert_eq!(from_str::<Record>(&text).unwrap(), node);
+
+        let text = to_string_one_line(&node).unwrap();
+        assert_eq!(
+            text,
+            "NodeName=node101 CPUTot=64 CPULoad=0.04 Idle=1 \
+             Partitions=mit_normal,mit_preemptable CfgTRES=cpu=64,gres/gpu=4 \
+             Reason=Kill task failed [root@2026-10-01T12:00:00] \
+             Mem=1000 GRES=gpu:2 Mem=2000 GRES=(null) MaxTime=1-12:00:00\n"
+        );
+        assert_eq!(from_str::<Record>(&text).unwrap(), node);
+
+        let nodes = vec![
+            node.clone(),
+            Record {
+                NodeName: "node102".to_string(),
+                Reason: None,
+                ..node.clone()
+            },
+        ];
+        let text = to_string(&nodes).unwrap();
+        assert!(text.contains("MaxTime=1-12:00:00\n\nNodeName=node102"));
+        assert_eq!(from_str::<Vec<Record>>(&text).unwrap(), nodes);
+        let text = to_string_one_line(&nodes).unwrap();
+        assert_eq!(text.lines().count(), 2);
+        assert_eq!(from_str::<Vec<Record>>(&text).unwrap(), nodes);
+    }
+
+    #[test]
+    fn test_write_errors() {
+        // A text with key=value pairs needs a line of its own
+        let node = Record {
+            Reason: Some("Low RealMemory Size=1000".to_string()),
+            ..record()
+        };
+        let text = to_string(&node).unwrap();
+        assert!(text.contains("\n   Reason=Low RealMemory Size=1000\n"));
+        assert_eq!(from_str::<Record>(&text).unwrap(), node);
+        let err = to_string_one_line(&node).unwrap_err();
+        assert_eq!(
+            err.message,
+            "The value of Reason can't contain key=value pairs on one line"
+        );
+        assert_eq!(err.record, Some(1));
+
+        let node = Record {
+            NodeName: "node 101".to_string(),
+            ..record()
+        };
+        let err = to_string(&node).unwrap_err();
+        assert_eq!(err.message, "The value of NodeName can't contain spaces");
+        assert_eq!(err.value.as_deref(), Some("node 101"));
+
+        let node = Record {
+            Partitions: vec!["a,b".to_string()],
+            ..record()
+        };
+        let err = to_string(&node).unwrap_err();
+        assert_eq!(err.key.as_deref(), Some("Partitions"));
+        assert!(to_string(&[1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_to_args() {
+        #[allow(non_snake_case)]
+        #[derive(Serialize)]
+        struct Update<'a> {
+            NodeName: &'a str,
+            State: Option<&'a str>,
+            Reason: Option<&'a str>,
+            Weight: Option<u32>,
+        }
+        let update = Update {
+            NodeName: "node[101-102]",
+            State: Some("DRAIN"),
+            Reason: Some("Bad DIMM"),
+            Weight: None,
+        };
+        assert_eq!(
+            to_args(&update).unwrap(),
+            vec!["NodeName=node[101-102]", "State=DRAIN", "Reason=Bad DIMM"]
+        );
+        assert!(to_args(&[&update, &update]).is_err());
+    }
+
+    proptest::prop_compose! {
+        fn any_record()(
+            name in "[a-z][a-z0-9_-]{0,15}",
+            cpus: u32,
+            load in -1e6..1e6f64,
+            weight: Option<i64>,
+            idle: bool,
+            partitions in proptest::collection::vec("[a-z][a-z0-9_]{0,10}", 0..4),
+            tres in proptest::collection::btree_map("[a-z]{1,6}(/[a-z]{1,6})?", 0..u64::MAX, 0..4),
+            reason in proptest::option::of("[a-z][A-Za-z0-9@:]*( [A-Za-z0-9@:=\\[\\]]+){0,4}"),
+            allocations in proptest::collection::vec((0..u32::MAX, "[a-z]{1,5}:[0-9]"), 0..4),
+            max_time in proptest::option::of(0..10_000_000i64),
+        ) -> Record {
+            Record {
+                NodeName: name,
+                CPUTot: cpus,
+                CPULoad: load,
+                Weight: weight,
+                Idle: idle,
+                Partitions: partitions,
+                CfgTRES: tres,
+                Reason: reason,
+                Mem: Occurrences(allocations.iter().map(|(mem, _)| *mem).collect()),
+                GRES: Occurrences(allocations.into_iter().map(|(_, gres)| Some(gres)).collect()),
+                MaxTime: max_time,
+            }
+        }
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_round_trip(records in proptest::collection::vec(any_record(), 0..4)) {
+            let text = to_string(&records).unwrap();
+            proptest::prop_assert_eq!(from_str::<Vec<Record>>(&text).unwrap(), records.clone());
+            // On one line, a text that looks like it has keys of its own can't be written
+            match to_string_one_line(&records) {
+                Ok(text) => {
+                    proptest::prop_assert_eq!(from_str::<Vec<Record>>(&text).unwrap(), records);
+                }
+                Err(e) => proptest::prop_assert_eq!(e.key.as_deref(), Some("Reason")),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30"), Some(30 * 60));
+        assert_eq!(parse_duration("30:15"), Some(30 * 60 + 15));
+        assert_eq!(parse_duration("08:00:00"), Some(8 * 60 * 60));
+        assert_eq!(parse_duration("1-12"), Some(36 * 60 * 60));
+        assert_eq!(
+            parse_duration("2-12:30"),
+            Some((2 * 24 * 60 + 12 * 60 + 30) * 60)
+        );
+        assert_eq!(parse_duration("2-12:00:00"), Some(60 * 60 * 60));
+        assert_eq!(parse_duration("UNLIMITED"), None);
+        assert_eq!(parse_duration("Partition_Limit"), None);
+    }
+
+    #[test]
+    fn test_local_time() {
+        let time = |tz: Tz, value: &str| {
+            let naive = NaiveDateTime::parse_from_str(value, TIME_FORMAT).unwrap();
+            from_local_time(&tz, naive).unwrap().to_rfc3339()
+        };
+        let boston = chrono_tz::America::New_York;
+        assert_eq!(
+            time(boston, "2026-01-31T12:00:00"),
+            "2026-01-31T17:00:00+00:00"
+        );
+        assert_eq!(
+            time(boston, "2026-07-01T12:00:00"),
+            "2026-07-01T16:00:00+00:00"
+        );
+        assert_eq!(
+            time(chrono_tz::Europe::Berlin, "2026-07-01T12:00:00"),
+            "2026-07-01T10:00:00+00:00"
+        );
+        // The clocks go forward at 2:00 on March 8th and back at 2:00 on November 1st
+        assert_eq!(
+            time(boston, "2026-03-08T02:30:00"),
+            "2026-03-08T07:30:00+00:00"
+        );
+        assert_eq!(
+            time(boston, "2026-11-01T01:30:00"),
+            "2026-11-01T05:30:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_parse_times_and_durations() {
+        let input = "JobId=1 StartTime=2026-01-31T12:45:05 EndTime=Unknown TimeLimit=2-12:00:00
+
+JobId=2 StartTime=None TimeLimit=UNLIMITED TimeMin=30:00
+
+JobId=3 TimeLimit=Partition_Limit";
+
+        #[allow(non_snake_case)]
+        #[derive(Deserialize, Debug)]
+        struct Job {
+            #[serde(default, deserialize_with = "timestamp")]
+            StartTime: Option<DateTime<Utc>>,
+            #[serde(default, deserialize_with = "timestamp")]
+            EndTime: Option<DateTime<Utc>>,
+            #[serde(default, deserialize_with = "duration")]
+            TimeLimit: Option<i64>,
+            #[serde(default, deserialize_with = "duration")]
+            TimeMin: Option<i64>,
+        }
+
+        let jobs = Vec::<Job>::deserialize(SlurmDeserializer::from_str(input)).unwrap();
+        assert_eq!(jobs[0].StartTime, parse_time("2026-01-31T12:45:05"));
+        assert!(jobs[0].StartTime.is_some());
+        assert_eq!(jobs[0].EndTime, None);
+        assert_eq!(jobs[0].TimeLimit, Some(60 * 60 * 60));
+        assert_eq!((jobs[1].StartTime, jobs[1].TimeLimit), (None, None));
+        assert_eq!(jobs[1].TimeMin, Some(30 * 60));
+        assert_eq!((jobs[2].StartTime, jobs[2].TimeLimit), (None, None));
+
+        let invalid = "JobId=1 StartTime=yesterday";
+        assert!(Vec::<Job>::deserialize(SlurmDeserializer::from_str(invalid)).is_err());
+    }
+
     #[test]
     fn test_parse_tres() {
         let input = "cpu=64,mem=1031314M,billing=64,gres/gpu=4,gres/gpu:l40s=4";
 
         // Dynamic map, expecting raw strings content
-        let map = HashMap::<&str, &str>::deserialize(SlurmValue::Single(input)).unwrap();
+        let map =
+            HashMap::<&str, &str>::deserialize(SlurmValue::Single(Cow::Borrowed(input))).unwrap();
         assert_eq!(map.get("cpu").unwrap(), &"64");
         assert_eq!(map.get("mem").unwrap(), &"1031314M");
         assert_eq!(map.get("gres/gpu").unwrap(), &"4");
@@ -596,11 +2195,176 @@ mod tests {
         assert_eq!(nodes[1].State, "ALLOCATED");
     }
 
+    // Blank lines, records without one in between and a bad record on line 8
+    const STREAM: &str = "
+NodeName=node1
+   State=IDLE Reason=Low RealMemory
+
+NodeName=node2 State=MIXED
+NodeName=node3
+   State=DOWN
+
+
+NodeName=node4 CPUTot=many
+NodeName=node5 State=IDLE
+";
+
+    #[allow(non_snake_case)]
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct StreamNode<'a> {
+        NodeName: &'a str,
+        State: Option<&'a str>,
+        Reason: Option<&'a str>,
+        CPUTot: Option<u32>,
+    }
+
+    // The names of the records read, or the line and record of the error
+    fn read_stream(record: super::Record) -> std::result::Result<String, (usize, usize)> {
+        match record.deserialize::<StreamNode>() {
+            Ok(node) => Ok(node.NodeName.to_string()),
+            Err(e) => Err((e.record.unwrap(), e.line.unwrap())),
+        }
+    }
+
+    #[test]
+    fn test_record_reader() {
+        let expected = vec![
+            Ok("node1".to_string()),
+            Ok("node2".to_string()),
+            Ok("node3".to_string()),
+            Err((4, 10)),
+            Ok("node5".to_string()),
+        ];
+        assert_eq!(
+            records(STREAM).map(read_stream).collect::<Vec<_>>(),
+            expected
+        );
+
+        let mut reader = RecordReader::new(STREAM.as_bytes());
+        let mut read = Vec::new();
+        while let Some(record) = reader.next_record().unwrap() {
+            read.push(read_stream(record));
+        }
+        assert_eq!(read, expected);
+        assert!(reader.next_record().unwrap().is_none());
+
+        let mut reader = RecordReader::new(STREAM.as_bytes());
+        let record = reader.next_record().unwrap().unwrap();
+        assert_eq!(
+            record.as_str(),
+            "NodeName=node1\n   State=IDLE Reason=Low RealMemory"
+        );
+        let node: StreamNode = record.deserialize().unwrap();
+        assert_eq!(node.Reason, Some("Low RealMemory"));
+    }
+
+    #[tokio::test]
+    async fn test_async_record_reader() {
+        let mut reader = AsyncRecordReader::new(tokio::io::BufReader::new(STREAM.as_bytes()));
+        let mut read = Vec::new();
+        while let Some(record) = reader.next_record().await.unwrap() {
+            read.push(read_stream(record));
+        }
+        assert_eq!(read, records(STREAM).map(read_stream).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_parse_hostlist() {
+        let input = "JobId=1 NodeList=gpu[01-04,07],cpu-a[1-3] Partitions=gpu,cpu";
+
+        #[allow(non_snake_case)]
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Job<'a> {
+            NodeList: Vec<crate::NodeName>,
+            #[serde(borrow)]
+            Partitions: Vec<&'a str>,
+        }
+
+        let job: Job = Job::deserialize(SlurmDeserializer::from_str(input)).unwrap();
+        let expected: Vec<crate::NodeName> = [
+            "gpu01", "gpu02", "gpu03", "gpu04", "gpu07", "cpu-a1", "cpu-a2", "cpu-a3",
+        ]
+        .iter()
+        .map(|n| crate::NodeName::new(n))
+        .collect();
+        assert_eq!(job.NodeList, expected);
+        assert_eq!(job.Partitions, vec!["gpu", "cpu"]);
+
+        let invalid = "JobId=1 NodeList=gpu[01-04";
+        assert!(Job::deserialize(SlurmDeserializer::from_str(invalid)).is_err());
+    }
+
+    #[test]
+    fn test_parse_occurrences() {
+        let input = "JobId=1 NodeList=node[1-3]
+     Nodes=node[1-2] CPU_IDs=0-3 Mem=1024 GRES=
+     Nodes=node3 CPU_IDs=0 Mem=512 GRES=gpu:1(IDX:0)";
+
+        #[allow(non_snake_case)]
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Job<'a> {
+            #[serde(borrow)]
+            Nodes: Occurrences<&'a str>,
+            Mem: Occurrences<u32>,
+            GRES: Occurrences<Option<&'a str>>,
+            #[serde(default)]
+            Missing: Occurrences<&'a str>,
+        }
+
+        let job: Job = Job::deserialize(SlurmDeserializer::from_str(input)).unwrap();
+        assert_eq!(job.Nodes.0, vec!["node[1-2]", "node3"]);
+        assert_eq!(job.Mem.0, vec![1024, 512]);
+        assert_eq!(job.GRES.0, vec![None, Some("gpu:1(IDX:0)")]);
+        assert_eq!(job.Missing.0, Vec::<&str>::new());
+
+        // A single line is one occurrence, even though the value is a list
+        let input = "JobId=1 Nodes=node[1-2] Mem=1024 GRES=gpu:1";
+        let job: Job = Job::deserialize(SlurmDeserializer::from_str(input)).unwrap();
+        assert_eq!(job.Nodes.0, vec!["node[1-2]"]);
+        assert_eq!(job.GRES.0, vec![Some("gpu:1")]);
+    }
+
+    #[test]
+    fn test_parse_parsable() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Row<'a> {
+            #[serde(rename = "JobIDRaw")]
+            job_id: &'a str,
+            #[serde(rename = "User")]
+            user: Option<&'a str>,
+            #[serde(rename = "NNodes")]
+            nodes: u32,
+            #[serde(rename = "NodeList")]
+            node_list: Vec<String>,
+        }
+
+        let input = "JobIDRaw|User|NNodes|NodeList\n\
+            8602044|bob|2|node[101,205]\n\
+            8602044.batch||1|node101\n";
+        let rows: Vec<Row> = from_parsable(input).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].user, Some("bob"));
+        assert_eq!(rows[0].node_list, vec!["node101", "node205"]);
+        assert_eq!(rows[1].job_id, "8602044.batch");
+        assert_eq!(rows[1].user, None);
+
+        assert!(from_parsable::<Row>("").unwrap().is_empty());
+        assert!(from_parsable::<Row>("JobIDRaw|User|NNodes|NodeList\n1|a|b\n").is_err());
+
+        let input = "JobIDRaw|User|NNodes|NodeList\n1|a|x|node1\n2|b|1|node2\n3|c\n";
+        let (rows, errors) = from_parsable_lenient::<Row>(input);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].job_id, "2");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].key.as_deref(), Some("NNodes"));
+        assert_eq!(errors[1].record, Some(3));
+    }
+
     #[test]
     fn test_parse_enum() {
         let input = "RUNNING";
 
-        #[allow(non_snake_case)]
+        #[allow(non_snake_case, clippy::upper_case_acronyms)]
         #[derive(Deserialize, Debug, PartialEq)]
         enum JobState {
             RUNNING,
@@ -608,7 +2372,7 @@ mod tests {
             PENDING,
         }
 
-        let job_state = JobState::deserialize(SlurmValue::Single(input)).unwrap();
+        let job_state = JobState::deserialize(SlurmValue::Single(Cow::Borrowed(input))).unwrap();
         assert_eq!(job_state, JobState::RUNNING);
     }
 }
@@ -0,0 +1,428 @@
+// What the worker tells the monitor: one message per line of its stdout, as JSON.
+// The worker starts with a Hello, then sends a Snapshot of the whole state and a Diff
+// after every poll that follows, with a Snapshot every so often or when the monitor
+// sends a Request for one on the worker's stdin.
+use crate::scontrol::SlurmVersion;
+use crate::{ClusterDiff, ClusterState, JobRecord, ParseWarning};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+// Bumped whenever a message changes in a way the other side can't read
+pub const PROTOCOL_VERSION: u32 = 2;
+
+// The handshake, the first message of a worker
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hello {
+    pub protocol: u32,
+    // The version of the worker crate
+    pub worker_version: String,
+    // Unset if the worker could not tell, or does not run scontrol
+    pub slurm_version: Option<SlurmVersion>,
+    // The ClusterName of slurm.conf
+    pub cluster: Option<String>,
+    pub hostname: String,
+}
+
+impl Hello {
+    pub fn new(
+        worker_version: &str,
+        slurm_version: Option<SlurmVersion>,
+        cluster: Option<String>,
+        hostname: &str,
+    ) -> Self {
+        Self {
+            protocol: PROTOCOL_VERSION,
+            worker_version: worker_version.to_string(),
+            slurm_version,
+            cluster,
+            hostname: hostname.to_string(),
+        }
+    }
+
+    // A worker that speaks another version of the protocol has to be replaced
+    pub fn check(&self) -> Result<()> {
+        if self.protocol != PROTOCOL_VERSION {
+            bail!(
+                "Worker {} on {} speaks protocol version {}, but the monitor speaks version {}. \
+                 Rebuild the worker, or remove the cached worker binary so it is uploaded again",
+                self.worker_version,
+                self.hostname,
+                self.protocol,
+                PROTOCOL_VERSION
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "body", rename_all = "snake_case")]
+pub enum Message {
+    Hello(Hello),
+    // Snapshots and diffs are numbered in the order they are sent, starting at 1.
+    // A diff holds the changes since the snapshot or diff numbered one before it.
+    // Like a diff, a snapshot has the jobs that finished since the last message and
+    // the records that were left out of it.
+    Snapshot {
+        seq: u64,
+        state: Box<ClusterState>,
+        #[serde(default)]
+        job_history: Vec<JobRecord>,
+        #[serde(default)]
+        warnings: Vec<ParseWarning>,
+    },
+    Diff {
+        seq: u64,
+        diff: Box<ClusterDiff>,
+    },
+    Log {
+        level: LogLevel,
+        message: String,
+    },
+    // A poll that failed, the worker tries again at the next one
+    Error {
+        message: String,
+    },
+    // Sent as every poll starts, so a slow poll can be told from a hung worker
+    Heartbeat {
+        time: DateTime<Utc>,
+    },
+}
+
+impl Message {
+    pub fn to_line(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_line(line: &str) -> Result<Message> {
+        Ok(serde_json::from_str(line)?)
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Message::Hello(_) => "hello",
+            Message::Snapshot { .. } => "snapshot",
+            Message::Diff { .. } => "diff",
+            Message::Log { .. } => "log",
+            Message::Error { .. } => "error",
+            Message::Heartbeat { .. } => "heartbeat",
+        }
+    }
+}
+
+// Numbers the states the worker polls and makes the message for each of them: a snapshot
+// when one is due, a diff from the state before otherwise
+pub struct Sequencer {
+    seq: u64,
+    last_state: Option<ClusterState>,
+    // Unset until the first snapshot, and again when one is asked for
+    last_snapshot: Option<Instant>,
+    snapshot_interval: Duration,
+}
+
+impl Sequencer {
+    pub fn new(snapshot_interval: Duration) -> Self {
+        Self {
+            seq: 0,
+            last_state: None,
+            last_snapshot: None,
+            snapshot_interval,
+        }
+    }
+
+    // Makes the next message a snapshot, e.g. when the monitor asks for one
+    pub fn request_snapshot(&mut self) {
+        self.last_snapshot = None;
+    }
+
+    pub fn next(
+        &mut self,
+        state: ClusterState,
+        job_history: Vec<JobRecord>,
+        warnings: Vec<ParseWarning>,
+    ) -> Message {
+        self.seq += 1;
+        let snapshot_due = self
+            .last_snapshot
+            .is_none_or(|t| t.elapsed() >= self.snapshot_interval);
+        let message = match &self.last_state {
+            Some(last_state) if !snapshot_due => {
+                let mut diff = last_state.diff(&state);
+                diff.job_history = job_history;
+                diff.warnings = warnings;
+                Message::Diff {
+                    seq: self.seq,
+                    diff: Box::new(diff),
+                }
+            }
+            _ => {
+                self.last_snapshot = Some(Instant::now());
+                Message::Snapshot {
+                    seq: self.seq,
+                    state: Box::new(state.clone()),
+                    job_history,
+                    warnings,
+                }
+            }
+        };
+        self.last_state = Some(state);
+        message
+    }
+}
+
+// What the monitor makes of the snapshots and diffs it reads: whether a diff applies,
+// and when to ask the worker for a snapshot
+#[derive(Debug, Default)]
+pub struct Resync {
+    // The seq of the last snapshot or diff that made it into the database. Diffs only
+    // apply on top of it, so after a gap or a failure nothing is applied until the
+    // next snapshot.
+    applied: Option<u64>,
+    // Whether a snapshot was asked for and has not come yet
+    requested: bool,
+}
+
+impl Resync {
+    // Whether the diff numbered `seq` applies on top of the database
+    pub fn follows(&self, seq: u64) -> bool {
+        self.applied.is_some_and(|last| last + 1 == seq)
+    }
+
+    // The snapshot or diff numbered `seq` made it into the database
+    pub fn applied(&mut self, seq: u64) {
+        self.applied = Some(seq);
+    }
+
+    // A snapshot came, whether or not it applies
+    pub fn snapshot(&mut self) {
+        self.requested = false;
+    }
+
+    // The database missed a snapshot or diff, one that did not follow the last, failed
+    // to apply or to parse. Returns whether to ask for a snapshot, which is asked for
+    // once until it comes.
+    pub fn lost(&mut self) -> bool {
+        self.applied = None;
+        !std::mem::replace(&mut self.requested, true)
+    }
+
+    // The request did not reach the worker, so the next loss asks again
+    pub fn request_failed(&mut self) {
+        self.requested = false;
+    }
+}
+
+// What the monitor asks of the worker, one per line of the worker's stdin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "body", rename_all = "snake_case")]
+pub enum Request {
+    // Send a snapshot at once, e.g. after a diff was lost or failed to apply
+    Snapshot,
+}
+
+impl Request {
+    pub fn to_line(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_line(line: &str) -> Result<Request> {
+        Ok(serde_json::from_str(line)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+// Reads a line the worker printed before its handshake. Lines that are not JSON, like
+// the banner of a login shell, are not part of the protocol and give None.
+pub fn handshake(line: &str) -> Result<Option<Hello>> {
+    if !line.trim_start().starts_with('{') {
+        return Ok(None);
+    }
+    match Message::from_line(line) {
+        Ok(Message::Hello(hello)) => {
+            hello.check()?;
+            Ok(Some(hello))
+        }
+        Ok(message) => bail!("Worker sent a {} before its handshake", message.kind()),
+        // Workers from before the protocol print bare diffs
+        Err(_) => bail!(
+            "Worker sent no handshake, so it is older than the monitor. \
+             Rebuild the worker, or remove the cached worker binary so it is uploaded again"
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::Table;
+    use crate::{JobId, JobStatus, NodeName, NodeResource, ResourceType};
+
+    fn hello() -> Hello {
+        Hello::new(
+            "0.1.0",
+            Some(SlurmVersion::new(23, 11, 10)),
+            Some("engaging".to_string()),
+            "login1",
+        )
+    }
+
+    #[test]
+    fn test_handshake() {
+        let line = Message::Hello(hello()).to_line().unwrap();
+        assert_eq!(handshake(&line).unwrap(), Some(hello()));
+        assert_eq!(handshake("Welcome to login1").unwrap(), None);
+
+        let old = Hello {
+            protocol: 0,
+            ..hello()
+        };
+        let line = Message::Hello(old).to_line().unwrap();
+        assert!(handshake(&line).is_err());
+
+        // A bare diff, as workers before the protocol print them
+        let diff = ClusterState::default().diff(&ClusterState::default());
+        assert!(handshake(&serde_json::to_string(&diff).unwrap()).is_err());
+        let heartbeat = Message::Heartbeat { time: Utc::now() };
+        assert!(handshake(&heartbeat.to_line().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        // Tables with tuple keys, which can't be the keys of a JSON object
+        let state = ClusterState {
+            node_resources: Table::from(vec![NodeResource {
+                node: NodeName::new("node101"),
+                resource: ResourceType::new("cpu"),
+                total: 64,
+                available: 32,
+            }]),
+            ..ClusterState::default()
+        };
+        let line = Message::Snapshot {
+            seq: 7,
+            state: Box::new(state),
+            job_history: Vec::new(),
+            warnings: Vec::new(),
+        }
+        .to_line()
+        .unwrap();
+        assert!(!line.contains('\n'));
+        let Message::Snapshot { seq, state, .. } = Message::from_line(&line).unwrap() else {
+            panic!("not a snapshot: {}", line);
+        };
+        assert_eq!(seq, 7);
+        let key = (NodeName::new("node101"), ResourceType::new("cpu"));
+        assert_eq!(state.node_resources.get(&key).unwrap().available, 32);
+
+        let line = r#"{"type":"log","body":{"level":"warn","message":"sprio failed"}}"#;
+        let Message::Log { level, message } = Message::from_line(line).unwrap() else {
+            panic!("not a log: {}", line);
+        };
+        assert_eq!((level, message.as_str()), (LogLevel::Warn, "sprio failed"));
+    }
+
+    fn record(id: i64) -> JobRecord {
+        let end_time = Utc::now();
+        JobRecord {
+            job_id: JobId::new(id),
+            name: "train".to_string(),
+            user: "alice".to_string(),
+            account: None,
+            partition: "gpu".to_string(),
+            status: JobStatus::Completed,
+            exit_code: 0,
+            exit_signal: 0,
+            submit_time: end_time,
+            start_time: None,
+            end_time,
+            elapsed: 0,
+            total_cpu: 0,
+            max_rss: None,
+        }
+    }
+
+    #[test]
+    fn test_sequencer() {
+        let mut sequencer = Sequencer::new(Duration::from_secs(3600));
+        let history = |message: &Message| match message {
+            Message::Snapshot {
+                seq, job_history, ..
+            } => ("snapshot", *seq, job_history.len()),
+            Message::Diff { seq, diff } => ("diff", *seq, diff.job_history.len()),
+            _ => panic!("not a snapshot or diff: {:?}", message),
+        };
+        let state = ClusterState::default;
+        let message = sequencer.next(state(), vec![record(1)], Vec::new());
+        assert_eq!(history(&message), ("snapshot", 1, 1));
+        let message = sequencer.next(state(), vec![record(2), record(3)], Vec::new());
+        assert_eq!(history(&message), ("diff", 2, 2));
+        // The jobs that finished since the last diff come with a snapshot that was asked for
+        sequencer.request_snapshot();
+        let message = sequencer.next(state(), vec![record(4)], Vec::new());
+        assert_eq!(history(&message), ("snapshot", 3, 1));
+        let message = sequencer.next(state(), Vec::new(), Vec::new());
+        assert_eq!(history(&message), ("diff", 4, 0));
+
+        // Snapshots that are due
+        let mut sequencer = Sequencer::new(Duration::ZERO);
+        for seq in 1..=2 {
+            let message = sequencer.next(state(), vec![record(1)], Vec::new());
+            assert_eq!(history(&message), ("snapshot", seq, 1));
+        }
+    }
+
+    #[test]
+    fn test_resync() {
+        let mut resync = Resync::default();
+        // Nothing applies before the first snapshot
+        assert!(!resync.follows(1));
+        resync.snapshot();
+        resync.applied(1);
+        assert!(resync.follows(2));
+        resync.applied(2);
+
+        // A gap, diff 3 was lost
+        assert!(!resync.follows(4));
+        assert!(resync.lost());
+        // Diffs that come before the snapshot don't apply, and ask for no more snapshots
+        assert!(!resync.follows(5));
+        assert!(!resync.lost());
+        resync.snapshot();
+        resync.applied(6);
+        assert!(resync.follows(7));
+
+        // A diff that failed to apply
+        assert!(resync.lost());
+        assert!(!resync.follows(8));
+        resync.snapshot();
+        resync.applied(8);
+
+        // A message that failed to parse, and a request that did not reach the worker
+        assert!(resync.lost());
+        resync.request_failed();
+        assert!(!resync.follows(10));
+        assert!(resync.lost());
+
+        // A snapshot that failed to apply clears the request all the same
+        resync.snapshot();
+        assert!(resync.lost());
+        assert!(!resync.follows(12));
+    }
+
+    #[test]
+    fn test_request() {
+        let line = Request::Snapshot.to_line().unwrap();
+        assert_eq!(line, r#"{"type":"snapshot"}"#);
+        assert_eq!(Request::from_line(&line).unwrap(), Request::Snapshot);
+        assert!(Request::from_line(r#"{"type":"restart"}"#).is_err());
+    }
+}
@@ -0,0 +1,236 @@
+// The usage of each user against the per-user limits of their QOS
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{ClusterState, JobId, JobResource, ResourceType};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TresUsage {
+    pub resource: ResourceType,
+    pub used: i64,
+    pub limit: Option<i64>,
+}
+
+// What a user's running and pending jobs count against a QOS, e.g.
+// 6 of the 8 GPUs allowed by MaxTRESPerUser of qos=gpu-normal
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QosUsage {
+    pub user: String,
+    pub qos: String,
+    pub running_jobs: i64,
+    pub max_jobs: Option<i64>,
+    // Running and pending jobs
+    pub submitted_jobs: i64,
+    pub max_submit: Option<i64>,
+    // The allocated resources of the running jobs, for every limited resource
+    // and every resource in use
+    pub tres: Vec<TresUsage>,
+}
+
+// A job counts against both its own QOS and the QOS of its partition, as in Slurm
+pub fn usage(state: &ClusterState) -> Vec<QosUsage> {
+    let mut job_resources: HashMap<&JobId, Vec<&JobResource>> = HashMap::new();
+    for resource in state.job_resources.iter().filter(|r| r.allocated > 0) {
+        job_resources
+            .entry(&resource.job)
+            .or_default()
+            .push(resource);
+    }
+
+    let mut usages: BTreeMap<(&str, &str), QosUsage> = BTreeMap::new();
+    for job in state.jobs.iter() {
+        if job.status.is_finished() {
+            continue;
+        }
+        let partition_qos = state
+            .partitions
+            .get(&job.partition)
+            .and_then(|p| p.resource_qos.as_deref());
+        let mut qos_names: Vec<&str> = job.qos.as_deref().into_iter().collect();
+        qos_names.extend(partition_qos.filter(|q| Some(*q) != job.qos.as_deref()));
+
+        for qos_name in qos_names {
+            let usage = usages
+                .entry((&job.user, qos_name))
+                .or_insert_with(|| QosUsage {
+                    user: job.user.clone(),
+                    qos: qos_name.to_string(),
+                    running_jobs: 0,
+                    max_jobs: None,
+                    submitted_jobs: 0,
+                    max_submit: None,
+                    tres: Vec::new(),
+                });
+            usage.submitted_jobs += 1;
+            if !job.status.is_running() {
+                continue;
+            }
+            usage.running_jobs += 1;
+            let allocated = job_resources.get(&job.job_id).into_iter().flatten();
+            for resource in allocated {
+                match usage
+                    .tres
+                    .iter_mut()
+                    .find(|t| t.resource == resource.resource)
+                {
+                    Some(tres) => tres.used += resource.allocated,
+                    None => usage.tres.push(TresUsage {
+                        resource: resource.resource.clone(),
+                        used: resource.allocated,
+                        limit: None,
+                    }),
+                }
+            }
+        }
+    }
+
+    let mut usages: Vec<QosUsage> = usages.into_values().collect();
+    for usage in &mut usages {
+        if let Some(qos) = state.qos.get(&usage.qos) {
+            usage.max_jobs = qos.max_jobs_per_user;
+            usage.max_submit = qos.max_submit_per_user;
+            for (resource, limit) in &qos.max_tres_per_user {
+                match usage.tres.iter_mut().find(|t| &t.resource == resource) {
+                    Some(tres) => tres.limit = Some(*limit),
+                    None => usage.tres.push(TresUsage {
+                        resource: resource.clone(),
+                        used: 0,
+                        limit: Some(*limit),
+                    }),
+                }
+            }
+        }
+        usage.tres.sort_by(|a, b| a.resource.cmp(&b.resource));
+    }
+    usages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::Table;
+    use crate::{Job, JobStatus, Partition, PartitionStatus, Qos};
+    use chrono::Utc;
+
+    fn job(id: i64, user: &str, partition: &str, qos: &str, status: JobStatus) -> Job {
+        Job {
+            job_id: JobId::new(id),
+            name: "job".to_string(),
+            user: user.to_string(),
+            account: None,
+            partition: partition.to_string(),
+            qos: Some(qos.to_string()),
+            status,
+            reason: None,
+            time_limit: None,
+            start_time: None,
+            end_time: None,
+            submit_time: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn resource(id: i64, name: &str, allocated: i64) -> JobResource {
+        JobResource {
+            job: JobId::new(id),
+            resource: ResourceType::new(name),
+            requested: allocated,
+            allocated,
+        }
+    }
+
+    #[test]
+    fn test_usage() {
+        let now = Utc::now();
+        let state = ClusterState {
+            partitions: Table::from(vec![Partition {
+                name: "gpu".to_string(),
+                status: PartitionStatus::Up,
+                access_qos: None,
+                resource_qos: Some("gpu-normal".to_string()),
+                nodes: None,
+                total_nodes: 0,
+                total_cpus: 0,
+                max_time: None,
+                default_time: None,
+                max_nodes: None,
+                min_nodes: 0,
+                priority_tier: 0,
+                preempt_mode: None,
+                over_subscribe: None,
+                allow_accounts: None,
+                allow_groups: None,
+                billing_weights: BTreeMap::new(),
+                updated_at: now,
+            }]),
+            qos: Table::from(vec![Qos {
+                name: "gpu-normal".to_string(),
+                priority: 0,
+                preempt: vec![],
+                preempt_mode: None,
+                grp_tres: BTreeMap::new(),
+                grp_jobs: None,
+                max_tres_per_job: BTreeMap::new(),
+                max_wall: None,
+                max_tres_per_user: BTreeMap::from([
+                    (ResourceType::new("gres/gpu"), 8),
+                    (ResourceType::new("cpu"), 64),
+                ]),
+                max_jobs_per_user: Some(4),
+                max_submit_per_user: None,
+                updated_at: now,
+            }]),
+            jobs: Table::from(vec![
+                job(1, "alice", "gpu", "normal", JobStatus::Running),
+                job(2, "alice", "gpu", "normal", JobStatus::Running),
+                job(3, "alice", "gpu", "normal", JobStatus::Pending),
+                job(4, "alice", "gpu", "normal", JobStatus::Completed),
+                job(5, "bob", "cpu", "normal", JobStatus::Running),
+            ]),
+            job_resources: Table::from(vec![
+                resource(1, "gres/gpu", 4),
+                resource(2, "gres/gpu", 2),
+                resource(2, "cpu", 8),
+                resource(3, "gres/gpu", 0),
+                resource(4, "gres/gpu", 4),
+                resource(5, "cpu", 16),
+            ]),
+            ..Default::default()
+        };
+
+        let usages = usage(&state);
+        assert_eq!(usages.len(), 3);
+        let find = |user: &str, qos: &str| {
+            usages
+                .iter()
+                .find(|u| u.user == user && u.qos == qos)
+                .unwrap()
+        };
+
+        let alice = find("alice", "gpu-normal");
+        assert_eq!((alice.running_jobs, alice.max_jobs), (2, Some(4)));
+        assert_eq!(alice.submitted_jobs, 3);
+        assert_eq!(
+            alice.tres,
+            vec![
+                TresUsage {
+                    resource: ResourceType::new("cpu"),
+                    used: 8,
+                    limit: Some(64)
+                },
+                TresUsage {
+                    resource: ResourceType::new("gres/gpu"),
+                    used: 6,
+                    limit: Some(8)
+                },
+            ]
+        );
+
+        // Unknown QOS have no limits
+        let alice = find("alice", "normal");
+        assert_eq!(alice.max_jobs, None);
+        assert_eq!(alice.tres.len(), 2);
+        let bob = find("bob", "normal");
+        assert_eq!(bob.tres[0].used, 16);
+    }
+}
@@ -0,0 +1,350 @@
+// The trackable resources (TRES) of Slurm, e.g. "cpu", "mem", "gres/gpu:a100" or "license/matlab"
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ResourceKind {
+    Cpu,
+    Mem,
+    Node,
+    Billing,
+    Energy,
+    Gres,
+    License,
+    // Any other TRES by its prefix, e.g. "fs" for "fs/disk" or "vmem"
+    Other(String),
+}
+
+impl ResourceKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ResourceKind::Cpu => "cpu",
+            ResourceKind::Mem => "mem",
+            ResourceKind::Node => "node",
+            ResourceKind::Billing => "billing",
+            ResourceKind::Energy => "energy",
+            ResourceKind::Gres => "gres",
+            ResourceKind::License => "license",
+            ResourceKind::Other(prefix) => prefix,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourceUnit {
+    Count,
+    Bytes,
+    Joules,
+}
+
+// A TRES name split into its parts. It is written and read as the name Slurm uses,
+// so it can be the key of a map in JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ResourceType {
+    pub kind: ResourceKind,
+    // The GRES, license or file system, e.g. "gpu" for "gres/gpu:a100"
+    pub name: Option<String>,
+    // The type of a GRES, e.g. "a100" for "gres/gpu:a100"
+    pub gres_type: Option<String>,
+}
+
+impl ResourceType {
+    // Parses a TRES name, unknown prefixes are kept as ResourceKind::Other
+    pub fn new(s: &str) -> Self {
+        let (prefix, rest) = match s.split_once('/') {
+            Some((prefix, rest)) => (prefix, Some(rest)),
+            None => (s, None),
+        };
+        let kind = match prefix {
+            "cpu" => ResourceKind::Cpu,
+            "mem" => ResourceKind::Mem,
+            "node" => ResourceKind::Node,
+            "billing" => ResourceKind::Billing,
+            "energy" => ResourceKind::Energy,
+            "gres" => ResourceKind::Gres,
+            "license" => ResourceKind::License,
+            other => ResourceKind::Other(other.to_string()),
+        };
+        let (name, gres_type) = match (&kind, rest) {
+            (ResourceKind::Gres, Some(rest)) => match rest.split_once(':') {
+                Some((name, gres_type)) => (Some(name), Some(gres_type)),
+                None => (Some(rest), None),
+            },
+            (_, rest) => (rest, None),
+        };
+        Self {
+            kind,
+            name: name.map(|n| n.to_string()),
+            gres_type: gres_type.map(|t| t.to_string()),
+        }
+    }
+
+    pub fn gres(name: &str, gres_type: Option<&str>) -> Self {
+        Self {
+            kind: ResourceKind::Gres,
+            name: Some(name.to_string()),
+            gres_type: gres_type.map(|t| t.to_string()),
+        }
+    }
+
+    pub fn unit(&self) -> ResourceUnit {
+        match &self.kind {
+            ResourceKind::Mem => ResourceUnit::Bytes,
+            ResourceKind::Energy => ResourceUnit::Joules,
+            ResourceKind::Other(prefix) if matches!(prefix.as_str(), "fs" | "bb" | "vmem") => {
+                ResourceUnit::Bytes
+            }
+            _ => ResourceUnit::Count,
+        }
+    }
+}
+
+impl fmt::Display for ResourceType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.kind.as_str())?;
+        if let Some(name) = &self.name {
+            write!(f, "/{}", name)?;
+        }
+        if let Some(gres_type) = &self.gres_type {
+            write!(f, ":{}", gres_type)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&str> for ResourceType {
+    fn from(s: &str) -> Self {
+        Self::new(s)
+    }
+}
+
+impl Serialize for ResourceType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ResourceType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct TypeVisitor;
+
+        impl serde::de::Visitor<'_> for TypeVisitor {
+            type Value = ResourceType;
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a TRES name like 'cpu' or 'gres/gpu:a100'")
+            }
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(ResourceType::new(v))
+            }
+        }
+        deserializer.deserialize_str(TypeVisitor)
+    }
+}
+
+// An amount of a resource, e.g. "4", "1.5K" or "512000M".
+// Like Slurm, the K, M, G, T and P suffixes are powers of 1024.
+#[derive(Debug, Clone)]
+pub struct ResourceQuantity(pub(crate) i64);
+
+impl From<ResourceQuantity> for i64 {
+    fn from(value: ResourceQuantity) -> Self {
+        value.0
+    }
+}
+
+impl std::str::FromStr for ResourceQuantity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let value = s.trim();
+        let exponent = match value.chars().last() {
+            Some('K') => 1,
+            Some('M') => 2,
+            Some('G') => 3,
+            Some('T') => 4,
+            Some('P') => 5,
+            _ => 0,
+        };
+        let number = if exponent > 0 {
+            &value[..value.len() - 1]
+        } else {
+            value
+        };
+        let number = number
+            .parse::<f64>()
+            .map_err(|_| anyhow::anyhow!("Invalid resource quantity: {}", s))?;
+        Ok(ResourceQuantity(
+            (number * 1024f64.powi(exponent)).round() as i64
+        ))
+    }
+}
+
+// Written with the largest suffix from M up that keeps it whole, e.g. "512000M" or "4G",
+// so counts stay plain numbers
+impl Serialize for ResourceQuantity {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let suffix = [('P', 5), ('T', 4), ('G', 3), ('M', 2)]
+            .into_iter()
+            .find(|(_, exponent)| self.0 != 0 && self.0 % (1 << (10 * exponent)) == 0);
+        match suffix {
+            Some((suffix, exponent)) => {
+                serializer.collect_str(&format_args!("{}{}", self.0 >> (10 * exponent), suffix))
+            }
+            None => serializer.collect_str(&self.0),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ResourceQuantity {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct ResVisitor;
+
+        impl serde::de::Visitor<'_> for ResVisitor {
+            type Value = ResourceQuantity;
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string like '100M' or '1G' or a raw number")
+            }
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                v.parse().map_err(E::custom)
+            }
+        }
+        deserializer.deserialize_str(ResVisitor)
+    }
+}
+
+// Parses the TRESBillingWeights of a partition, e.g. "CPU=1.0,Mem=0.25G,GRES/gpu=2.0",
+// into the billing of one unit of each resource. Slurm weighs memory per megabyte
+// unless the weight has a suffix, so "Mem=0.25G" is 0.25 per gigabyte.
+pub(crate) fn parse_billing_weights(weights: &str) -> BTreeMap<ResourceType, f64> {
+    let mut result = BTreeMap::new();
+    for (name, weight) in weights.split(',').filter_map(|w| w.split_once('=')) {
+        // The names are case insensitive, except for the names of GRES and licenses
+        let name = match name.split_once('/') {
+            Some((prefix, rest)) => format!("{}/{}", prefix.to_ascii_lowercase(), rest),
+            None => name.to_ascii_lowercase(),
+        };
+        let resource = ResourceType::new(&name);
+        let (number, exponent) = match weight.chars().last() {
+            Some('K') => (&weight[..weight.len() - 1], 1),
+            Some('M') => (&weight[..weight.len() - 1], 2),
+            Some('G') => (&weight[..weight.len() - 1], 3),
+            Some('T') => (&weight[..weight.len() - 1], 4),
+            _ if resource.unit() == ResourceUnit::Bytes => (weight, 2),
+            _ => (weight, 0),
+        };
+        let Ok(number) = number.parse::<f64>() else {
+            continue;
+        };
+        result.insert(resource, number / 1024f64.powi(exponent));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_type() {
+        let a100 = ResourceType::new("gres/gpu:a100");
+        assert_eq!(a100.kind, ResourceKind::Gres);
+        assert_eq!(a100.name.as_deref(), Some("gpu"));
+        assert_eq!(a100.gres_type.as_deref(), Some("a100"));
+        assert_eq!(a100, ResourceType::gres("gpu", Some("a100")));
+        assert_eq!(a100.unit(), ResourceUnit::Count);
+
+        let mem = ResourceType::new("mem");
+        assert_eq!(
+            (mem.kind.clone(), mem.name.clone()),
+            (ResourceKind::Mem, None)
+        );
+        assert_eq!(mem.unit(), ResourceUnit::Bytes);
+
+        let license = ResourceType::new("license/matlab");
+        assert_eq!(license.kind, ResourceKind::License);
+        assert_eq!(license.name.as_deref(), Some("matlab"));
+
+        let disk = ResourceType::new("fs/disk");
+        assert_eq!(disk.kind, ResourceKind::Other("fs".to_string()));
+        assert_eq!(disk.unit(), ResourceUnit::Bytes);
+
+        for name in [
+            "cpu",
+            "billing",
+            "gres/gpu",
+            "gres/gpu:h100",
+            "license/matlab",
+            "fs/disk",
+        ] {
+            assert_eq!(ResourceType::new(name).to_string(), name);
+        }
+        assert_eq!(serde_json::to_string(&a100).unwrap(), "\"gres/gpu:a100\"");
+        assert_eq!(
+            serde_json::from_str::<ResourceType>("\"gres/gpu:a100\"").unwrap(),
+            a100
+        );
+    }
+
+    #[test]
+    fn test_resource_quantity() {
+        let parse = |s: &str| s.parse::<ResourceQuantity>().unwrap().0;
+        assert_eq!(parse("4"), 4);
+        assert_eq!(parse("1.5K"), 1536);
+        assert_eq!(parse("512000M"), 512000 * 1024 * 1024);
+        assert_eq!(parse("18.50G"), 37 * 512 * 1024 * 1024);
+        assert_eq!(parse("2T"), 2 << 40);
+        assert_eq!(parse("1P"), 1 << 50);
+        assert!("lots".parse::<ResourceQuantity>().is_err());
+
+        let write =
+            |s: &str| serde_json::to_string(&s.parse::<ResourceQuantity>().unwrap()).unwrap();
+        assert_eq!(write("64"), "\"64\"");
+        assert_eq!(write("1031314M"), "\"1031314M\"");
+        assert_eq!(write("4096M"), "\"4G\"");
+        assert_eq!(write("1.5K"), "\"1536\"");
+    }
+
+    #[test]
+    fn test_parse_billing_weights() {
+        let weights = parse_billing_weights("CPU=1.0,Mem=0.25G,GRES/gpu:A100=2.0,License/matlab=5");
+        assert_eq!(weights.get(&ResourceType::new("cpu")), Some(&1.0));
+        assert_eq!(
+            weights.get(&ResourceType::new("mem")),
+            Some(&(0.25 / (1u64 << 30) as f64))
+        );
+        assert_eq!(
+            weights.get(&ResourceType::gres("gpu", Some("A100"))),
+            Some(&2.0)
+        );
+        assert_eq!(
+            weights.get(&ResourceType::new("license/matlab")),
+            Some(&5.0)
+        );
+        // Memory is weighed per megabyte by default
+        assert_eq!(
+            parse_billing_weights("Mem=1").get(&ResourceType::new("mem")),
+            Some(&(1.0 / (1u64 << 20) as f64))
+        );
+        assert!(parse_billing_weights("").is_empty());
+    }
+}
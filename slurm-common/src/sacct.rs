@@ -0,0 +1,289 @@
+// Job accounting through sacct, for the jobs that have already left the queue
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::parser::{self, format_time, parse_duration, ParseWarning};
+use crate::resource::ResourceQuantity;
+use crate::{JobId, JobRecord, JobStatus};
+
+const FORMAT: &str =
+    "JobID,JobName,User,Account,Partition,State,ExitCode,Submit,Start,End,Elapsed,TotalCPU,MaxRSS";
+
+// The states of a job that has finished
+const FINISHED_STATES: &str = "BF,CA,CD,DL,F,NF,OOM,PR,TO";
+
+// A line of `sacct --parsable2`, either a job or one of its steps
+#[derive(Debug, Clone, Deserialize)]
+struct SacctRow<'src> {
+    // e.g. "1234", "1234_7" or "1240+1", and "1234_7.batch" for a step
+    #[serde(rename = "JobID")]
+    job_id: &'src str,
+    #[serde(rename = "JobName", default)]
+    name: &'src str,
+    #[serde(rename = "User")]
+    user: Option<&'src str>,
+    #[serde(rename = "Account")]
+    account: Option<&'src str>,
+    #[serde(rename = "Partition")]
+    partition: Option<&'src str>,
+    #[serde(rename = "State")]
+    state: &'src str,
+    #[serde(rename = "ExitCode")]
+    exit_code: Option<&'src str>,
+    #[serde(rename = "Submit", default, deserialize_with = "parser::timestamp")]
+    submit_time: Option<DateTime<Utc>>,
+    #[serde(rename = "Start", default, deserialize_with = "parser::timestamp")]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(rename = "End", default, deserialize_with = "parser::timestamp")]
+    end_time: Option<DateTime<Utc>>,
+    #[serde(rename = "Elapsed", default, deserialize_with = "parser::duration")]
+    elapsed: Option<i64>,
+    #[serde(rename = "TotalCPU")]
+    total_cpu: Option<&'src str>,
+    #[serde(rename = "MaxRSS")]
+    max_rss: Option<ResourceQuantity>,
+}
+
+const SOURCE: &str = "sacct";
+
+// Fetches the jobs of all users that finished between `since` and `until`.
+// Rows that can't be read are left out and added to the warnings.
+pub async fn history(
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<Vec<JobRecord>> {
+    let output = tokio::process::Command::new("sacct")
+        .args(["--allusers", "--parsable2"])
+        .arg(format!("--format={}", FORMAT))
+        .arg(format!("--state={}", FINISHED_STATES))
+        .arg(format!("--starttime={}", format_time(since)))
+        .arg(format!("--endtime={}", format_time(until)))
+        .output()
+        .await?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "sacct failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let output = String::from_utf8(output.stdout)?;
+    Ok(parse_history(&output, warnings))
+}
+
+// The span of time whose finished jobs the next poll fetches. It starts where the last
+// fetch ended, so the jobs of every poll reach the monitor once. When the monitor asks
+// for a snapshot, something it was sent got lost, so the span starts where the one of
+// the last snapshot did and the jobs since are sent again.
+#[derive(Debug, Clone, Copy)]
+pub struct HistoryWindow {
+    since: DateTime<Utc>,
+    // Where the span of the last snapshot started
+    snapshot_since: DateTime<Utc>,
+}
+
+impl HistoryWindow {
+    pub fn new(since: DateTime<Utc>) -> Self {
+        Self {
+            since,
+            snapshot_since: since,
+        }
+    }
+
+    // The span to fetch until `until`. Spans overlap by a second, so a job that ends
+    // as one span does is not missed, and the monitor drops the duplicates.
+    pub fn span(&self, until: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+        (self.since - chrono::Duration::seconds(1), until)
+    }
+
+    // The jobs until `until` were sent, with a snapshot or a diff
+    pub fn sent(&mut self, until: DateTime<Utc>, snapshot: bool) {
+        if snapshot {
+            self.snapshot_since = self.since;
+        }
+        self.since = until;
+    }
+
+    pub fn rewind(&mut self) {
+        self.since = self.snapshot_since;
+    }
+}
+
+fn parse_history(output: &str, warnings: &mut Vec<ParseWarning>) -> Vec<JobRecord> {
+    let (rows, errors) = parser::from_parsable_lenient::<SacctRow>(output);
+    warnings.extend(errors.into_iter().map(|e| ParseWarning::new(SOURCE, e)));
+
+    // MaxRSS is only reported for the steps ("1234.batch", "1234_7.0", ...) of a job
+    let mut max_rss: BTreeMap<&str, i64> = BTreeMap::new();
+    for row in &rows {
+        if let (Some((job_id, _)), Some(rss)) = (row.job_id.split_once('.'), &row.max_rss) {
+            let max = max_rss.entry(job_id).or_default();
+            *max = (*max).max(rss.0);
+        }
+    }
+
+    let mut records = Vec::new();
+    for row in rows.iter().filter(|r| !r.job_id.contains('.')) {
+        let (job_id, submit_time, end_time) = match check_row(row) {
+            Ok(checked) => checked,
+            Err(e) => {
+                warnings.push(ParseWarning::new(SOURCE, e));
+                continue;
+            }
+        };
+        let (exit_code, exit_signal) = row
+            .exit_code
+            .and_then(|c| c.split_once(':'))
+            .and_then(|(code, signal)| Some((code.parse().ok()?, signal.parse().ok()?)))
+            .unwrap_or((0, 0));
+
+        records.push(JobRecord {
+            job_id,
+            name: row.name.to_string(),
+            user: row.user.unwrap_or_default().to_string(),
+            account: row.account.map(|a| a.to_string()),
+            partition: row.partition.unwrap_or_default().to_string(),
+            status: JobStatus::from(row.state),
+            exit_code,
+            exit_signal,
+            submit_time,
+            start_time: row.start_time,
+            end_time,
+            elapsed: row.elapsed.unwrap_or(0),
+            total_cpu: row.total_cpu.and_then(parse_cpu_time).unwrap_or(0),
+            max_rss: max_rss.get(row.job_id).copied(),
+        });
+    }
+    records
+}
+
+// The fields a record can't do without
+fn check_row(row: &SacctRow) -> Result<(JobId, DateTime<Utc>, DateTime<Utc>), parser::Error> {
+    let error = parser::Error::in_value::<String>;
+    let job_id: JobId = row
+        .job_id
+        .parse()
+        .map_err(|e| error(format!("{}", e), "JobID", row.job_id))?;
+    let submit_time = row
+        .submit_time
+        .ok_or_else(|| error(format!("Job {} has no submit time", job_id), "Submit", ""))?;
+    let end_time = row
+        .end_time
+        .ok_or_else(|| error(format!("Job {} has no end time", job_id), "End", ""))?;
+    Ok((job_id, submit_time, end_time))
+}
+
+// TotalCPU (and AveCPU of sstat) is a duration with milliseconds, e.g. "01:02.345"
+// or "1-02:03:04", the milliseconds are dropped
+pub(crate) fn parse_cpu_time(value: &str) -> Option<i64> {
+    parse_duration(value.split_once('.').map_or(value, |(t, _)| t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_time;
+
+    const HISTORY: &str = "JobID|JobName|User|Account|Partition|State|ExitCode|Submit|Start|End|Elapsed|TotalCPU|MaxRSS
+8601779|train|alice|mit_general|sched_mit_hill|COMPLETED|0:0|2026-01-31T12:44:31|2026-01-31T12:45:05|2026-01-31T14:45:05|02:00:00|1-01:59:58|
+8601779.batch|batch||mit_general||COMPLETED|0:0|2026-01-31T12:45:05|2026-01-31T12:45:05|2026-01-31T14:45:05|02:00:00|1-01:59:58|15000K
+8601779.extern|extern||mit_general||COMPLETED|0:0|2026-01-31T12:45:05|2026-01-31T12:45:05|2026-01-31T14:45:05|02:00:00|00:00.002|120K
+8602011|prep|bob|mit_general|mit_normal_gpu|CANCELLED by 1234|0:15|2026-01-31T13:00:00|None|2026-01-31T13:10:00|00:00:00|00:00:00|
+8602044|mpi|bob||sched_mit_hill|OUT_OF_MEMORY|0:125|2026-01-31T13:00:00|2026-01-31T13:00:01|2026-01-31T13:05:01|00:05:00|09:59.500|
+8602044.0|hydra||||OUT_OF_MEMORY|0:125|2026-01-31T13:00:01|2026-01-31T13:00:01|2026-01-31T13:05:01|00:05:00|09:59.500|18.50G
+8602100_1|array|alice|mit_general|sched_mit_hill|COMPLETED|0:0|2026-01-31T13:00:00|2026-01-31T13:00:10|2026-01-31T13:10:10|00:10:00|00:09:30|
+8602100_1.batch|batch||mit_general||COMPLETED|0:0|2026-01-31T13:00:10|2026-01-31T13:00:10|2026-01-31T13:10:10|00:10:00|00:09:30|4000K
+8602200+1|het|bob|mit_general|sched_mit_hill|TIMEOUT|0:0|2026-01-31T13:10:00|2026-01-31T13:10:05|2026-01-31T14:10:05|01:00:00|00:59:00|
+";
+
+    #[test]
+    fn test_parse_history() {
+        let warnings = &mut Vec::new();
+        let records = parse_history(HISTORY, warnings);
+        assert!(warnings.is_empty());
+        assert_eq!(records.len(), 5);
+
+        let completed = &records[0];
+        assert_eq!(completed.job_id, JobId::new(8601779));
+        assert_eq!(completed.status, JobStatus::Completed);
+        assert_eq!(completed.account.as_deref(), Some("mit_general"));
+        assert_eq!(completed.elapsed, 2 * 60 * 60);
+        assert_eq!(completed.total_cpu, 26 * 60 * 60 - 2);
+        assert_eq!(completed.max_rss, Some(15000 * 1024));
+        assert_eq!(
+            completed.end_time - completed.start_time.unwrap(),
+            chrono::Duration::hours(2)
+        );
+
+        let cancelled = &records[1];
+        assert_eq!(cancelled.status, JobStatus::Cancelled);
+        assert_eq!((cancelled.exit_code, cancelled.exit_signal), (0, 15));
+        assert_eq!(cancelled.start_time, None);
+        assert_eq!(cancelled.max_rss, None);
+
+        let oom = &records[2];
+        assert_eq!(oom.status, JobStatus::OutOfMemory);
+        assert_eq!(oom.account, None);
+        assert_eq!(oom.total_cpu, 9 * 60 + 59);
+        assert_eq!(oom.max_rss, Some(37 << 29));
+
+        let task = &records[3];
+        assert_eq!(task.job_id, JobId::task(8602100, 1));
+        assert_eq!(task.max_rss, Some(4000 * 1024));
+        assert_eq!(records[4].job_id, JobId::component(8602200, 1));
+        assert_eq!(records[4].status, JobStatus::Timeout);
+    }
+
+    #[test]
+    fn test_parse_no_history() {
+        let header = format!("{}\n", FORMAT.replace(',', "|"));
+        assert!(parse_history(&header, &mut Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_parse_bad_rows() {
+        // A bad JobID, a job still completing without an end time, a row cut short and
+        // a bad timestamp, among good rows
+        let mut lines: Vec<&str> = HISTORY.lines().collect();
+        lines.insert(2, "x123|bad|alice|a|p|COMPLETED|0:0|2026-01-31T12:00:00|None|2026-01-31T13:00:00|00:00:00|00:00:00|");
+        lines.insert(4, "8602010|completing|bob|a|p|COMPLETED|0:0|2026-01-31T12:00:00|2026-01-31T12:00:05|Unknown|00:00:00|00:00:00|");
+        lines.push("8602300|cut|bob|a");
+        lines.push("8602301|late|bob|a|p|COMPLETED|0:0|yesterday|None|2026-01-31T13:00:00|00:00:00|00:00:00|");
+        let warnings = &mut Vec::new();
+        let records = parse_history(&lines.join("\n"), warnings);
+        assert_eq!(records.len(), 5);
+        assert_eq!(records[0].job_id, JobId::new(8601779));
+        assert_eq!(records[0].max_rss, Some(15000 * 1024));
+        assert_eq!(warnings.len(), 4);
+        assert!(warnings.iter().all(|w| w.source == "sacct"));
+        assert_eq!(warnings[0].error.record, Some(12));
+        assert_eq!(warnings[1].error.key.as_deref(), Some("Submit"));
+        let keys: Vec<_> = warnings[2..]
+            .iter()
+            .map(|w| w.error.key.as_deref())
+            .collect();
+        assert_eq!(keys, vec![Some("JobID"), Some("End")]);
+    }
+
+    #[test]
+    fn test_history_window() {
+        let time = |hour| parse_time(&format!("2026-01-31T{:02}:00:00", hour)).unwrap();
+        let second = chrono::Duration::seconds(1);
+        let mut window = HistoryWindow::new(time(0));
+        assert_eq!(window.span(time(1)), (time(0) - second, time(1)));
+        window.sent(time(1), true);
+        assert_eq!(window.span(time(2)), (time(1) - second, time(2)));
+        window.sent(time(2), false);
+        window.sent(time(3), false);
+
+        // A diff got lost, so the jobs since the snapshot are sent again
+        window.rewind();
+        assert_eq!(window.span(time(4)), (time(0) - second, time(4)));
+        window.sent(time(4), true);
+        window.sent(time(5), true);
+        window.rewind();
+        assert_eq!(window.span(time(6)), (time(4) - second, time(6)));
+    }
+}
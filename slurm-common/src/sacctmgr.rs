@@ -0,0 +1,124 @@
+// QOS definitions from the accounting database, through sacctmgr
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::parser;
+use crate::resource::ResourceQuantity;
+use crate::{table::Table, Qos, ResourceType};
+
+const FORMAT: &str = "Name,Priority,Preempt,PreemptMode,GrpTRES,GrpJobs,MaxTRES,MaxWall,MaxTRESPerUser,MaxJobsPerUser,MaxSubmitJobsPerUser";
+
+// A line of `sacctmgr show qos -P`. The header abbreviates some of the format fields.
+#[derive(Debug, Clone, Deserialize)]
+struct QosInfo<'src> {
+    #[serde(rename = "Name")]
+    name: &'src str,
+    #[serde(rename = "Priority", default)]
+    priority: i64,
+    #[serde(rename = "Preempt", default)]
+    preempt: Vec<String>,
+    #[serde(rename = "PreemptMode")]
+    preempt_mode: Option<&'src str>,
+    #[serde(rename = "GrpTRES", default)]
+    grp_tres: BTreeMap<ResourceType, ResourceQuantity>,
+    #[serde(rename = "GrpJobs")]
+    grp_jobs: Option<i64>,
+    #[serde(rename = "MaxTRES", default)]
+    max_tres: BTreeMap<ResourceType, ResourceQuantity>,
+    #[serde(rename = "MaxWall", default, deserialize_with = "parser::duration")]
+    max_wall: Option<i64>,
+    #[serde(rename = "MaxTRESPU", alias = "MaxTRESPerUser", default)]
+    max_tres_per_user: BTreeMap<ResourceType, ResourceQuantity>,
+    #[serde(rename = "MaxJobsPU", alias = "MaxJobsPerUser")]
+    max_jobs_per_user: Option<i64>,
+    #[serde(rename = "MaxSubmitPU", alias = "MaxSubmitJobsPerUser")]
+    max_submit_per_user: Option<i64>,
+}
+
+pub async fn qos() -> Result<Table<Qos>> {
+    let output = tokio::process::Command::new("sacctmgr")
+        .args(["show", "qos", "--parsable2"])
+        .arg(format!("format={}", FORMAT))
+        .output()
+        .await?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "sacctmgr failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let output = String::from_utf8(output.stdout)?;
+    parse_qos(&output, Utc::now())
+}
+
+fn parse_qos(output: &str, updated_at: DateTime<Utc>) -> Result<Table<Qos>> {
+    let infos: Vec<QosInfo> = crate::parser::from_parsable(output)?;
+    let tres = |limits: BTreeMap<ResourceType, ResourceQuantity>| {
+        limits
+            .into_iter()
+            .map(|(resource, quantity)| (resource, quantity.into()))
+            .collect()
+    };
+    let mut table = Table::new();
+    for info in infos {
+        table.insert(Qos {
+            name: info.name.to_string(),
+            priority: info.priority,
+            preempt: info.preempt,
+            preempt_mode: info.preempt_mode.map(|m| m.to_string()),
+            grp_tres: tres(info.grp_tres),
+            grp_jobs: info.grp_jobs,
+            max_tres_per_job: tres(info.max_tres),
+            max_wall: info.max_wall,
+            max_tres_per_user: tres(info.max_tres_per_user),
+            max_jobs_per_user: info.max_jobs_per_user,
+            max_submit_per_user: info.max_submit_per_user,
+            updated_at,
+        });
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QOS: &str = "Name|Priority|Preempt|PreemptMode|GrpTRES|GrpJobs|MaxTRES|MaxWall|MaxTRESPU|MaxJobsPU|MaxSubmitPU
+normal|0||cluster|||||||
+gpu-normal|100|scavenger,low|cluster|gres/gpu=64|200|cpu=32,gres/gpu=4|2-00:00:00|gres/gpu=8,mem=500G|10|50
+scavenger|0||requeue|||||||
+";
+
+    #[test]
+    fn test_parse_qos() {
+        let table = parse_qos(QOS, Utc::now()).unwrap();
+        assert_eq!(table.len(), 3);
+
+        let normal = table.get(&"normal".to_string()).unwrap();
+        assert_eq!(normal.preempt, Vec::<String>::new());
+        assert_eq!(normal.preempt_mode.as_deref(), Some("cluster"));
+        assert!(normal.max_tres_per_user.is_empty());
+        assert_eq!(normal.max_wall, None);
+
+        let gpu = table.get(&"gpu-normal".to_string()).unwrap();
+        assert_eq!(gpu.priority, 100);
+        assert_eq!(gpu.preempt, vec!["scavenger", "low"]);
+        let gpus = ResourceType::new("gres/gpu");
+        assert_eq!(gpu.grp_tres.get(&gpus), Some(&64));
+        assert_eq!(gpu.grp_jobs, Some(200));
+        assert_eq!(
+            gpu.max_tres_per_job.get(&ResourceType::new("cpu")),
+            Some(&32)
+        );
+        assert_eq!(gpu.max_wall, Some(2 * 24 * 60 * 60));
+        assert_eq!(gpu.max_tres_per_user.get(&gpus), Some(&8));
+        assert_eq!(
+            gpu.max_tres_per_user.get(&ResourceType::new("mem")),
+            Some(&(500 << 30))
+        );
+        assert_eq!(gpu.max_jobs_per_user, Some(10));
+        assert_eq!(gpu.max_submit_per_user, Some(50));
+    }
+}
@@ -1,36 +1,33 @@
-use anyhow::Result;
-use serde::Deserialize;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::process::Stdio;
+use tokio::io::AsyncReadExt;
+use tokio::process::Command;
 
+use crate::parser::{self, parse_time, AsyncRecordReader, Occurrences, ParseWarning, Record};
+use crate::resource::{parse_billing_weights, ResourceQuantity};
 use crate::{
-    table::Table, Job, JobAllocation, JobResource, Node, NodePartition, NodeResource, Partition,
-    PartitionStatus,
+    table::Table, Job, JobAllocation, JobId, JobResource, JobStatus, JobStep, Node, NodeName,
+    NodePartition, NodeReservation, NodeResource, NodeState, Partition, PartitionStatus,
+    Reservation, ResourceKind, ResourceType,
 };
 
-#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
-pub enum NodeStateInfo {
-    #[serde(rename = "IDLE")]
-    Idle,
-    #[serde(rename = "ALLOCATED")]
-    Allocated,
-    #[serde(rename = "MIX")]
-    Mix,
-    #[serde(rename = "DOWN")]
-    Down,
-    #[serde(rename = "UNKNOWN", other)]
-    Unknown,
-}
-
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NodeInfo<'src> {
     #[serde(rename = "NodeName")]
     pub name: &'src str,
+    // e.g. "MIXED+DRAIN" or "DOWN*"
     #[serde(rename = "State")]
-    pub state: NodeStateInfo,
+    pub state: &'src str,
     #[serde(rename = "CPUAlloc")]
     pub cpu_alloc: u32,
+    // The CPUs jobs can use, see `Adapt for NodeInfo`
     #[serde(rename = "CPUTot")]
     pub cpus: u32,
+    #[serde(rename = "CPUEfctv")]
+    pub cpus_effective: Option<u32>,
     #[serde(rename = "RealMemory")]
     pub real_memory: u32,
     #[serde(rename = "AllocMem")]
@@ -41,97 +38,599 @@ pub struct NodeInfo<'src> {
     #[serde(rename = "Partitions")]
     pub partitions: Vec<&'src str>,
     #[serde(rename = "CfgTRES")]
-    pub resources: BTreeMap<&'src str, ResourceQuantity>,
-    #[serde(rename = "AllocTRES")]
-    pub allocated: BTreeMap<&'src str, ResourceQuantity>,
+    pub resources: BTreeMap<ResourceType, ResourceQuantity>,
+    #[serde(rename = "AllocTRES", default)]
+    pub allocated: BTreeMap<ResourceType, ResourceQuantity>,
+    // e.g. "gpu:a100:4(S:0-1)"
+    #[serde(rename = "Gres")]
+    pub gres: Option<&'src str>,
+    #[serde(rename = "GresUsed")]
+    pub gres_used: Option<&'src str>,
+    // e.g. "Kill task failed [root@2026-10-01T12:00:00]"
+    #[serde(rename = "Reason")]
+    pub reason: Option<&'src str>,
+    #[serde(
+        rename = "BootTime",
+        default,
+        deserialize_with = "parser::timestamp",
+        serialize_with = "parser::serialize_timestamp"
+    )]
+    pub boot_time: Option<DateTime<Utc>>,
+    #[serde(
+        rename = "SlurmdStartTime",
+        default,
+        deserialize_with = "parser::timestamp",
+        serialize_with = "parser::serialize_timestamp"
+    )]
+    pub slurmd_start_time: Option<DateTime<Utc>>,
+    #[serde(
+        rename = "LastBusyTime",
+        default,
+        deserialize_with = "parser::timestamp",
+        serialize_with = "parser::serialize_timestamp"
+    )]
+    pub last_busy_time: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PartitionInfo<'src> {
     #[serde(rename = "PartitionName")]
     pub name: &'src str,
     #[serde(rename = "State")]
-    pub state: NodeStateInfo,
+    pub state: &'src str,
     #[serde(rename = "AllowQos")]
     pub allow_qos: Option<&'src str>,
     #[serde(rename = "QoS")]
     pub qos: Option<&'src str>,
+    #[serde(rename = "AllowAccounts")]
+    pub allow_accounts: Option<&'src str>,
+    #[serde(rename = "AllowGroups")]
+    pub allow_groups: Option<&'src str>,
+    #[serde(rename = "Nodes")]
+    pub nodes: Option<&'src str>,
+    #[serde(rename = "TotalNodes", default)]
+    pub total_nodes: u32,
+    #[serde(rename = "TotalCPUs", default)]
+    pub total_cpus: u32,
+    #[serde(
+        rename = "MaxTime",
+        default,
+        deserialize_with = "parser::duration",
+        serialize_with = "parser::serialize_duration"
+    )]
+    pub max_time: Option<i64>,
+    #[serde(
+        rename = "DefaultTime",
+        default,
+        deserialize_with = "parser::duration",
+        serialize_with = "parser::serialize_duration"
+    )]
+    pub default_time: Option<i64>,
+    // A number or "UNLIMITED"
+    #[serde(rename = "MaxNodes")]
+    pub max_nodes: Option<&'src str>,
+    #[serde(rename = "MinNodes", default)]
+    pub min_nodes: u32,
+    #[serde(rename = "PriorityTier", default)]
+    pub priority_tier: u32,
+    #[serde(rename = "PreemptMode")]
+    pub preempt_mode: Option<&'src str>,
+    // Called Shared before Slurm 16.05
+    #[serde(rename = "OverSubscribe", alias = "Shared")]
+    pub over_subscribe: Option<&'src str>,
+    // e.g. "CPU=1.0,Mem=0.25G,GRES/gpu=2.0"
+    #[serde(rename = "TRESBillingWeights")]
+    pub billing_weights: Option<&'src str>,
 }
 
-#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
-pub enum JobStateInfo {
-    #[serde(rename = "RUNNING")]
-    Running,
-    #[serde(rename = "PENDING")]
-    Pending,
-    #[serde(rename = "COMPLETED")]
-    Completed,
-    #[serde(rename = "FAILED")]
-    Failed,
-    #[serde(rename = "UNKNOWN", other)]
-    Unknown,
-}
-
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JobInfo<'src> {
     #[serde(rename = "JobId")]
     pub job_id: u64,
+    // Set for the tasks of an array job, ArrayTaskId is a range like "5-100%10"
+    // for the pending tasks that have not been split off yet
+    #[serde(rename = "ArrayJobId")]
+    pub array_job_id: Option<i64>,
+    #[serde(rename = "ArrayTaskId")]
+    pub array_task_id: Option<&'src str>,
+    // Set for the components of a het job
+    #[serde(rename = "HetJobId")]
+    pub het_job_id: Option<i64>,
+    #[serde(rename = "HetJobOffset")]
+    pub het_job_offset: Option<u32>,
     #[serde(rename = "JobName")]
     pub name: &'src str,
     #[serde(rename = "Partition")]
     pub partition: &'src str,
     #[serde(rename = "UserId")]
     pub user: &'src str,
+    #[serde(rename = "Account")]
+    pub account: Option<&'src str>,
+    #[serde(rename = "QOS")]
+    pub qos: Option<&'src str>,
+    // e.g. "RUNNING", "OUT_OF_MEMORY" or "COMPLETING"
     #[serde(rename = "JobState")]
-    pub state: JobStateInfo,
+    pub state: &'src str,
+    // e.g. "Resources" or "NonZeroExitCode", "None" if there is no reason
+    #[serde(rename = "Reason")]
+    pub reason: Option<&'src str>,
     #[serde(rename = "NumCPUs")]
     pub num_cpus: u32,
     #[serde(rename = "NumNodes")]
     pub num_nodes: String, // sometimes weird, like 2-2 or 1-1
-    #[serde(rename = "NodeList")]
-    pub node_list: Vec<String>,
+    #[serde(rename = "NodeList", default)]
+    pub node_list: Vec<NodeName>,
     #[serde(rename = "ReqTRES")]
-    pub req_res: Option<BTreeMap<&'src str, ResourceQuantity>>,
+    pub req_res: Option<BTreeMap<ResourceType, ResourceQuantity>>,
     #[serde(rename = "AllocTRES")]
-    pub alloc_res: Option<BTreeMap<&'src str, ResourceQuantity>>,
-    #[serde(rename = "SubmitTime")]
-    pub submit_time: &'src str,
-    #[serde(rename = "StartTime")]
-    pub start_time: Option<&'src str>,
-    #[serde(rename = "TimeLimit")]
-    pub time_limit: Option<&'src str>,
+    pub alloc_res: Option<BTreeMap<ResourceType, ResourceQuantity>>,
+    // Before 21.08, the only TRES of a job, see `Adapt for JobInfo`
+    #[serde(rename = "TRES")]
+    pub tres: Option<BTreeMap<ResourceType, ResourceQuantity>>,
+    #[serde(
+        rename = "SubmitTime",
+        default,
+        deserialize_with = "parser::timestamp",
+        serialize_with = "parser::serialize_timestamp"
+    )]
+    pub submit_time: Option<DateTime<Utc>>,
+    #[serde(
+        rename = "StartTime",
+        default,
+        deserialize_with = "parser::timestamp",
+        serialize_with = "parser::serialize_timestamp"
+    )]
+    pub start_time: Option<DateTime<Utc>>,
+    // The expected end of running jobs
+    #[serde(
+        rename = "EndTime",
+        default,
+        deserialize_with = "parser::timestamp",
+        serialize_with = "parser::serialize_timestamp"
+    )]
+    pub end_time: Option<DateTime<Utc>>,
+    #[serde(
+        rename = "TimeLimit",
+        default,
+        deserialize_with = "parser::duration",
+        serialize_with = "parser::serialize_duration"
+    )]
+    pub time_limit: Option<i64>,
+
+    // The per-node allocation lines printed with --details
+    #[serde(rename = "Nodes", default)]
+    pub detail_nodes: Occurrences<Vec<NodeName>>,
+    #[serde(rename = "CPU_IDs", default)]
+    pub detail_cpu_ids: Occurrences<Option<&'src str>>,
+    #[serde(rename = "Mem", default)]
+    pub detail_mem: Occurrences<Option<i64>>, // in megabytes
+    #[serde(rename = "GRES", default)]
+    pub detail_gres: Occurrences<Option<&'src str>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReservationInfo<'src> {
+    #[serde(rename = "ReservationName")]
+    pub name: &'src str,
+    #[serde(
+        rename = "StartTime",
+        default,
+        deserialize_with = "parser::timestamp",
+        serialize_with = "parser::serialize_timestamp"
+    )]
+    pub start_time: Option<DateTime<Utc>>,
+    #[serde(
+        rename = "EndTime",
+        default,
+        deserialize_with = "parser::timestamp",
+        serialize_with = "parser::serialize_timestamp"
+    )]
+    pub end_time: Option<DateTime<Utc>>,
+    #[serde(rename = "Nodes", default)]
+    pub nodes: Vec<NodeName>,
+    #[serde(rename = "PartitionName")]
+    pub partition: Option<&'src str>,
+    #[serde(rename = "Flags", default)]
+    pub flags: Vec<String>,
+    #[serde(rename = "TRES", default)]
+    pub tres: BTreeMap<ResourceType, ResourceQuantity>,
+    // Names prefixed with '-' are excluded from the reservation
+    #[serde(rename = "Users", default)]
+    pub users: Vec<String>,
+    #[serde(rename = "Accounts", default)]
+    pub accounts: Vec<String>,
+    #[serde(rename = "State")]
+    pub state: Option<&'src str>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepInfo<'src> {
+    // e.g. "1234.batch", "1234_7.0" or "1240+1.extern"
+    #[serde(rename = "StepId")]
+    pub step_id: &'src str,
+    #[serde(rename = "Name", default)]
+    pub name: &'src str,
+    #[serde(rename = "State")]
+    pub state: &'src str,
+    #[serde(rename = "NodeList", default)]
+    pub node_list: Vec<NodeName>,
+    #[serde(rename = "Tasks", default)]
+    pub tasks: u32,
+    #[serde(
+        rename = "StartTime",
+        default,
+        deserialize_with = "parser::timestamp",
+        serialize_with = "parser::serialize_timestamp"
+    )]
+    pub start_time: Option<DateTime<Utc>>,
+}
+
+// The changes `scontrol update` makes to nodes, fields that are None are left as they are.
+// e.g. State=DRAIN with a Reason drains the nodes and State=RESUME brings them back.
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeUpdate<'a> {
+    // A node or a hostlist like "node[101-104]"
+    #[serde(rename = "NodeName")]
+    pub nodes: &'a str,
+    #[serde(rename = "State")]
+    pub state: Option<&'a str>,
+    #[serde(rename = "Reason")]
+    pub reason: Option<&'a str>,
+    #[serde(rename = "Weight")]
+    pub weight: Option<u32>,
+}
+
+// The release series whose output is known to be read right, oldest first
+pub const SUPPORTED_VERSIONS: &[SlurmVersion] = &[
+    SlurmVersion::new(20, 11, 0),
+    SlurmVersion::new(21, 8, 0),
+    SlurmVersion::new(22, 5, 0),
+    SlurmVersion::new(23, 2, 0),
+    SlurmVersion::new(23, 11, 0),
+    SlurmVersion::new(24, 5, 0),
+    SlurmVersion::new(24, 11, 0),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SlurmVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
 }
 
-pub async fn nodes() -> Result<(Table<Node>, Table<NodeResource>, Table<NodePartition>)> {
+impl SlurmVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    // The --json output of scontrol, squeue and sinfo was added in 21.08
+    pub fn supports_json(&self) -> bool {
+        *self >= SlurmVersion::new(21, 8, 0)
+    }
+
+    // Whether this is a release of one of the SUPPORTED_VERSIONS
+    pub fn is_supported(&self) -> bool {
+        SUPPORTED_VERSIONS
+            .iter()
+            .any(|v| (v.major, v.minor) == (self.major, self.minor))
+    }
+}
+
+impl std::fmt::Display for SlurmVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}.{:02}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl std::str::FromStr for SlurmVersion {
+    type Err = anyhow::Error;
+
+    // Parses the output of "scontrol --version", e.g. "slurm 23.02.7" or "slurm-wlm 21.08.5"
+    fn from_str(s: &str) -> Result<Self> {
+        let version = s.split_whitespace().last().unwrap_or_default();
+        let mut parts = version.split(['.', '-']).map(|p| p.parse::<u32>().ok());
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(Some(major)), Some(Some(minor)), patch) => Ok(SlurmVersion::new(
+                major,
+                minor,
+                patch.flatten().unwrap_or(0),
+            )),
+            _ => Err(anyhow::anyhow!("Invalid Slurm version: {}", s)),
+        }
+    }
+}
+
+impl Serialize for SlurmVersion {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SlurmVersion {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let version: &str = Deserialize::deserialize(deserializer)?;
+        version.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// Asks `scontrol --version`, or `scontrol show config` if that prints something else,
+// e.g. for a build with a patched version string
+pub async fn version() -> Result<SlurmVersion> {
+    let output = tokio::process::Command::new("scontrol")
+        .arg("--version")
+        .output()
+        .await?;
+    if let Ok(version) = String::from_utf8_lossy(&output.stdout).trim().parse() {
+        return Ok(version);
+    }
+    parse_config_version(&show_config().await?)
+}
+
+// The ClusterName of slurm.conf
+pub async fn cluster_name() -> Result<String> {
+    let config = show_config().await?;
+    let name =
+        config_value(&config, "ClusterName").context("No ClusterName in scontrol show config")?;
+    Ok(name.to_string())
+}
+
+async fn show_config() -> Result<String> {
     let output = tokio::process::Command::new("scontrol")
         .arg("show")
-        .arg("nodes")
+        .arg("config")
+        .output()
+        .await?;
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+// Finds the line "SLURM_VERSION = 23.02.7" of `scontrol show config`
+fn parse_config_version(config: &str) -> Result<SlurmVersion> {
+    config_value(config, "SLURM_VERSION")
+        .context("No SLURM_VERSION in scontrol show config")?
+        .parse()
+}
+
+// The value of a "Key = value" line of `scontrol show config`
+fn config_value<'a>(config: &'a str, key: &str) -> Option<&'a str> {
+    config.lines().find_map(|line| {
+        let (k, value) = line.split_once('=')?;
+        (k.trim() == key).then_some(value.trim())
+    })
+}
+
+pub async fn nodes(
+    version: Option<SlurmVersion>,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<(Table<Node>, Table<NodeResource>, Table<NodePartition>)> {
+    show::<NodeTables>(version, warnings).await
+}
+
+pub async fn partitions(
+    version: Option<SlurmVersion>,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<Table<Partition>> {
+    show::<PartitionTables>(version, warnings).await
+}
+
+pub async fn reservations(
+    version: Option<SlurmVersion>,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<(Table<Reservation>, Table<NodeReservation>)> {
+    show::<ReservationTables>(version, warnings).await
+}
+
+pub async fn jobs(
+    version: Option<SlurmVersion>,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<(Table<Job>, Table<JobAllocation>, Table<JobResource>)> {
+    show::<JobTables>(version, warnings).await
+}
+
+// The steps of the running jobs, without their usage which comes from sstat
+pub async fn steps(
+    version: Option<SlurmVersion>,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<Table<JobStep>> {
+    show::<StepTables>(version, warnings).await
+}
+
+pub async fn update_nodes(update: &NodeUpdate<'_>) -> Result<()> {
+    let output = tokio::process::Command::new("scontrol")
+        .arg("update")
+        .args(parser::to_args(update)?)
         .output()
         .await?;
-    let output = String::from_utf8(output.stdout)?;
-    let node_infos: Vec<NodeInfo> = crate::parser::from_str(&output).unwrap_or_default();
+    if !output.status.success() {
+        anyhow::bail!(
+            "scontrol update failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+// Builds tables from the records of `scontrol show`, one record at a time
+trait Collector {
+    // What scontrol shows, e.g. "jobs", and the flags to show it with
+    const ENTITY: &'static str;
+    const FLAGS: &'static [&'static str] = &[];
+
+    type Info<'src>: Deserialize<'src> + Adapt;
+    type Tables;
+
+    fn new(updated_at: DateTime<Utc>) -> Self;
+    // A record that is read but can't be used is skipped, like one that can't be read
+    fn add(&mut self, info: Self::Info<'_>) -> Result<(), parser::Error>;
+    fn finish(self) -> Self::Tables;
+}
 
-    let mut nodes = Table::new();
-    let mut resources = Table::new();
-    let mut partitions = Table::new();
-    let updated_at = chrono::Utc::now();
+// Fixes up what serde can't in the records of a Slurm version, so the records of all
+// SUPPORTED_VERSIONS come out the same. Keys that were only renamed are serde aliases.
+// Without a version, the keys the record has decide.
+trait Adapt {
+    fn adapt(&mut self, _version: Option<SlurmVersion>) {}
+}
+
+impl Adapt for NodeInfo<'_> {
+    fn adapt(&mut self, version: Option<SlurmVersion>) {
+        // 22.05 added CPUEfctv, the CPUs left for jobs once the cores set aside for the
+        // system with CoreSpecCount are taken out. CPUTot still counts those.
+        if version.is_none_or(|v| v >= SlurmVersion::new(22, 5, 0)) {
+            self.cpus = self.cpus_effective.unwrap_or(self.cpus);
+        }
+        // Without AllocTRES, the CPUs and memory are all that is known to be allocated
+        if self.allocated.is_empty() && (self.cpu_alloc > 0 || self.alloc_mem > 0) {
+            self.allocated = BTreeMap::from([
+                (
+                    ResourceType::new("cpu"),
+                    ResourceQuantity(self.cpu_alloc as i64),
+                ),
+                (
+                    ResourceType::new("mem"),
+                    ResourceQuantity(self.alloc_mem as i64 * 1024 * 1024),
+                ),
+            ]);
+        }
+    }
+}
+
+impl Adapt for PartitionInfo<'_> {}
+impl Adapt for ReservationInfo<'_> {}
+impl Adapt for JobInfo<'_> {
+    fn adapt(&mut self, _version: Option<SlurmVersion>) {
+        // 21.08 split TRES into ReqTRES and AllocTRES. TRES was what the job asked for
+        // until it got its allocation, and what it got after that.
+        if let Some(tres) = self.tres.take() {
+            if self.alloc_res.is_none() && self.state != "PENDING" {
+                self.alloc_res = Some(tres.clone());
+            }
+            self.req_res.get_or_insert(tres);
+        }
+    }
+}
+impl Adapt for StepInfo<'_> {}
 
-    for info in node_infos {
+// Reads the records as scontrol prints them, so only the tables are kept in memory
+// and not the whole output, which is hundreds of MB for the jobs of a large cluster
+async fn show<C: Collector>(
+    version: Option<SlurmVersion>,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<C::Tables> {
+    let mut command = Command::new("scontrol");
+    command.arg("show").arg(C::ENTITY).args(C::FLAGS);
+    read_command::<C>(command, version, warnings).await
+}
+
+// When scontrol fails, e.g. because slurmctld is down, it prints nothing or only part
+// of the cluster. Tables built from that would make the rest look gone, so it is an error.
+async fn read_command<C: Collector>(
+    mut command: Command,
+    version: Option<SlurmVersion>,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<C::Tables> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let stdout = child.stdout.take().context("scontrol has no output")?;
+    let mut stderr = child
+        .stderr
+        .take()
+        .context("scontrol has no error output")?;
+    let read = async {
+        let mut reader = AsyncRecordReader::new(tokio::io::BufReader::new(stdout));
+        let mut collector = C::new(Utc::now());
+        while let Some(record) = reader.next_record().await? {
+            add(&mut collector, record, version, warnings);
+        }
+        Ok::<_, anyhow::Error>(collector)
+    };
+    // Both are read at once, so neither pipe fills up and blocks scontrol
+    let mut error = String::new();
+    let (collector, _) = tokio::try_join!(read, async {
+        Ok::<_, anyhow::Error>(stderr.read_to_string(&mut error).await?)
+    })?;
+    let status = child.wait().await?;
+    if !status.success() {
+        bail!(
+            "scontrol show {} failed with {}: {}",
+            C::ENTITY,
+            status,
+            error.trim()
+        );
+    }
+    Ok(collector.finish())
+}
+
+// One bad record should not hide the rest of the cluster, so a record that fails
+// to parse is skipped and reported as a warning
+fn add<C: Collector>(
+    collector: &mut C,
+    record: Record<'_>,
+    version: Option<SlurmVersion>,
+    warnings: &mut Vec<ParseWarning>,
+) {
+    // scontrol prints e.g. "No jobs in the system" instead of an empty list
+    if !record.as_str().contains('=') {
+        return;
+    }
+    let added = record.deserialize::<C::Info<'_>>().and_then(|mut info| {
+        info.adapt(version);
+        collector.add(info).map_err(|e| parser::Error {
+            record: Some(record.index()),
+            ..e
+        })
+    });
+    if let Err(e) = added {
+        let source = format!("scontrol show {}", C::ENTITY);
+        warnings.push(ParseWarning::new(&source, e));
+    }
+}
+
+#[derive(Default)]
+struct NodeTables {
+    updated_at: DateTime<Utc>,
+    nodes: Table<Node>,
+    resources: Table<NodeResource>,
+    partitions: Table<NodePartition>,
+}
+
+impl Collector for NodeTables {
+    const ENTITY: &'static str = "nodes";
+
+    type Info<'src> = NodeInfo<'src>;
+    type Tables = (Table<Node>, Table<NodeResource>, Table<NodePartition>);
+
+    fn new(updated_at: DateTime<Utc>) -> Self {
+        Self {
+            updated_at,
+            ..Default::default()
+        }
+    }
+
+    fn add(&mut self, info: NodeInfo) -> Result<(), parser::Error> {
         let name = crate::NodeName(info.name.to_string());
 
-        // Map scontrol state to our NodeStatus
-        let status = match info.state {
-            NodeStateInfo::Idle => crate::NodeStatus::Idle,
-            NodeStateInfo::Allocated => crate::NodeStatus::Alloc,
-            NodeStateInfo::Mix => crate::NodeStatus::Mix,
-            NodeStateInfo::Down => crate::NodeStatus::Down,
-            NodeStateInfo::Unknown => crate::NodeStatus::Unknown,
+        let (reason, reason_user, reason_time) = match info.reason {
+            Some(reason) => parse_reason(reason),
+            None => (None, None, None),
         };
 
         // Node
-        nodes.insert(Node {
+        self.nodes.insert(Node {
             name: name.clone(),
-            status,
+            state: NodeState::from(info.state),
             cpus: info.cpus,
             cpus_alloc: info.cpu_alloc,
             cpus_idle: info.cpus.saturating_sub(info.cpu_alloc),
@@ -139,12 +638,18 @@ pub async fn nodes() -> Result<(Table<Node>, Table<NodeResource>, Table<NodePart
             memory_alloc: info.alloc_mem as i64,
             memory_free: info.free_mem.unwrap_or(0) as i64,
             partitions: info.partitions.iter().map(|s| s.to_string()).collect(),
-            updated_at,
+            reason,
+            reason_user,
+            reason_time,
+            boot_time: info.boot_time,
+            slurmd_start_time: info.slurmd_start_time,
+            last_busy_time: info.last_busy_time,
+            updated_at: self.updated_at,
         });
 
         // Node Partitions
         for part_name in info.partitions {
-            partitions.insert(NodePartition {
+            self.partitions.insert(NodePartition {
                 node: name.clone(),
                 partition: part_name.to_string(),
             });
@@ -153,109 +658,1296 @@ pub async fn nodes() -> Result<(Table<Node>, Table<NodeResource>, Table<NodePart
         // Node Resources (CfgTRES vs AllocTRES)
         // We'll iterate over CfgTRES for 'total' and compare with AllocTRES for 'available'
         // But AllocTRES only shows allocated. Available = Total - Allocated.
-        for (res_name, total_qty) in info.resources {
+        for (resource, total_qty) in &info.resources {
             let total = total_qty.0 as u64;
-            let allocated = info.allocated.get(res_name).map(|q| q.0).unwrap_or(0);
+            let allocated = info.allocated.get(resource).map(|q| q.0).unwrap_or(0);
             let available = total.saturating_sub(allocated as u64);
 
-            resources.insert(NodeResource {
+            self.resources.insert(NodeResource {
+                node: name.clone(),
+                resource: resource.clone(),
+                total,
+                available,
+            });
+        }
+
+        let gres = untracked_gres(
+            &info.resources,
+            info.gres.unwrap_or_default(),
+            info.gres_used.unwrap_or_default(),
+        );
+        for (resource, total, available) in gres {
+            self.resources.insert(NodeResource {
                 node: name.clone(),
-                resource: crate::ResourceType(res_name.to_string()),
+                resource,
                 total,
                 available,
             });
         }
+        Ok(())
     }
 
-    Ok((nodes, resources, partitions))
+    fn finish(self) -> Self::Tables {
+        (self.nodes, self.resources, self.partitions)
+    }
 }
 
-pub async fn partitions() -> Result<Table<Partition>> {
-    let output = tokio::process::Command::new("scontrol")
-        .arg("show")
-        .arg("partitions")
-        .output()
-        .await?;
-    let output = String::from_utf8(output.stdout).unwrap();
-    let partitions: Vec<PartitionInfo> = crate::parser::from_str(&output).unwrap();
-    let mut table = Table::new();
-    for info in partitions {
-        let status = match info.state {
-            NodeStateInfo::Idle => PartitionStatus::Up,
-            NodeStateInfo::Allocated => PartitionStatus::Up,
-            NodeStateInfo::Mix => PartitionStatus::Up,
-            NodeStateInfo::Down => PartitionStatus::Down,
-            NodeStateInfo::Unknown => PartitionStatus::Down,
-        };
-        table.insert(Partition {
+#[derive(Default)]
+struct PartitionTables {
+    updated_at: DateTime<Utc>,
+    partitions: Table<Partition>,
+}
+
+impl Collector for PartitionTables {
+    const ENTITY: &'static str = "partitions";
+
+    type Info<'src> = PartitionInfo<'src>;
+    type Tables = Table<Partition>;
+
+    fn new(updated_at: DateTime<Utc>) -> Self {
+        Self {
+            updated_at,
+            ..Default::default()
+        }
+    }
+
+    fn add(&mut self, info: PartitionInfo) -> Result<(), parser::Error> {
+        self.partitions.insert(Partition {
             name: info.name.to_string(),
-            status,
+            status: PartitionStatus::from(info.state),
             access_qos: info.allow_qos.map(|s| s.to_string()),
             resource_qos: info.qos.map(|s| s.to_string()),
-            updated_at: chrono::Utc::now(),
+            nodes: info.nodes.map(|s| s.to_string()),
+            total_nodes: info.total_nodes,
+            total_cpus: info.total_cpus,
+            max_time: info.max_time,
+            default_time: info.default_time,
+            max_nodes: info.max_nodes.and_then(|n| n.parse().ok()),
+            min_nodes: info.min_nodes,
+            priority_tier: info.priority_tier,
+            preempt_mode: info.preempt_mode.map(|s| s.to_string()),
+            over_subscribe: info.over_subscribe.map(|s| s.to_string()),
+            allow_accounts: parse_allow_list(info.allow_accounts),
+            allow_groups: parse_allow_list(info.allow_groups),
+            billing_weights: info
+                .billing_weights
+                .map(parse_billing_weights)
+                .unwrap_or_default(),
+            updated_at: self.updated_at,
         });
+        Ok(())
+    }
+
+    fn finish(self) -> Self::Tables {
+        self.partitions
     }
-    Ok(table)
 }
 
-pub async fn jobs() -> Result<(Table<Job>, Table<JobAllocation>, Table<JobResource>)> {
-    let output = tokio::process::Command::new("scontrol")
-        .arg("show")
-        .arg("jobs")
-        .arg("--details")
-        .output()
-        .await?;
-    let output = String::from_utf8(output.stdout).unwrap();
-    let jobs: Vec<JobInfo> = crate::parser::from_str(&output).unwrap();
-    eprintln!("{:?}", jobs);
-    Ok((Table::new(), Table::new(), Table::new()))
+// scontrol only lists reservations that have not ended yet,
+// so every node of a listed reservation is in an active or upcoming one
+#[derive(Default)]
+struct ReservationTables {
+    updated_at: DateTime<Utc>,
+    reservations: Table<Reservation>,
+    nodes: Table<NodeReservation>,
 }
 
-// Will handle parsing memory M and G suffixes
-#[derive(Debug, Clone)]
-pub struct ResourceQuantity(i64);
+impl Collector for ReservationTables {
+    const ENTITY: &'static str = "reservations";
+
+    type Info<'src> = ReservationInfo<'src>;
+    type Tables = (Table<Reservation>, Table<NodeReservation>);
+
+    fn new(updated_at: DateTime<Utc>) -> Self {
+        Self {
+            updated_at,
+            ..Default::default()
+        }
+    }
+
+    fn add(&mut self, info: ReservationInfo) -> Result<(), parser::Error> {
+        let missing = |key| {
+            let message = format!("Reservation {} has no {}", info.name, key);
+            parser::Error::in_value(message, key, "")
+        };
+        let start_time = info.start_time.ok_or_else(|| missing("StartTime"))?;
+        let end_time = info.end_time.ok_or_else(|| missing("EndTime"))?;
+        for node in &info.nodes {
+            self.nodes.insert(NodeReservation {
+                node: node.clone(),
+                reservation: info.name.to_string(),
+            });
+        }
+        self.reservations.insert(Reservation {
+            name: info.name.to_string(),
+            start_time,
+            end_time,
+            active: info.state == Some("ACTIVE"),
+            nodes: info.nodes,
+            partition: info.partition.map(|p| p.to_string()),
+            users: info.users,
+            accounts: info.accounts,
+            flags: info.flags,
+            tres: info
+                .tres
+                .into_iter()
+                .map(|(resource, quantity)| (resource, quantity.into()))
+                .collect(),
+            updated_at: self.updated_at,
+        });
+        Ok(())
+    }
 
-impl Into<i64> for ResourceQuantity {
-    fn into(self) -> i64 {
-        self.0
+    fn finish(self) -> Self::Tables {
+        (self.reservations, self.nodes)
     }
 }
 
-impl<'de> Deserialize<'de> for ResourceQuantity {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        struct ResVisitor;
+impl JobInfo<'_> {
+    // Array tasks and het job components go by the id of the whole job,
+    // e.g. "1234_7" or "1240+1", rather than their own JobId
+    fn id(&self) -> JobId {
+        if let (Some(array_job_id), Some(task)) = (self.array_job_id, self.array_task_id) {
+            return match task.parse() {
+                Ok(task) => JobId::task(array_job_id, task),
+                Err(_) => JobId::tasks(array_job_id, task),
+            };
+        }
+        if let (Some(het_job_id), Some(offset)) = (self.het_job_id, self.het_job_offset) {
+            return JobId::component(het_job_id, offset);
+        }
+        JobId::new(self.job_id as i64)
+    }
+}
+
+#[derive(Default)]
+struct JobTables {
+    updated_at: DateTime<Utc>,
+    jobs: Table<Job>,
+    allocations: Table<JobAllocation>,
+    resources: Table<JobResource>,
+}
+
+impl Collector for JobTables {
+    const ENTITY: &'static str = "jobs";
+    const FLAGS: &'static [&'static str] = &["--details"];
 
-        impl<'de> serde::de::Visitor<'de> for ResVisitor {
-            type Value = ResourceQuantity;
-            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-                formatter.write_str("a string like '100M' or '1G' or a raw number")
+    type Info<'src> = JobInfo<'src>;
+    type Tables = (Table<Job>, Table<JobAllocation>, Table<JobResource>);
+
+    fn new(updated_at: DateTime<Utc>) -> Self {
+        Self {
+            updated_at,
+            ..Default::default()
+        }
+    }
+
+    fn add(&mut self, mut info: JobInfo) -> Result<(), parser::Error> {
+        let job_id = info.id();
+
+        let submit_time = info.submit_time.ok_or_else(|| {
+            let message = format!("Job {} has no SubmitTime", job_id);
+            parser::Error::in_value(message, "SubmitTime", "")
+        })?;
+
+        self.jobs.insert(Job {
+            job_id: job_id.clone(),
+            name: info.name.to_string(),
+            user: parse_user(info.user).to_string(),
+            account: info.account.map(|a| a.to_string()),
+            partition: info.partition.to_string(),
+            qos: info.qos.map(|q| q.to_string()),
+            status: JobStatus::from(info.state),
+            reason: info.reason.map(|r| r.to_string()),
+            time_limit: info.time_limit,
+            start_time: info.start_time,
+            end_time: info.end_time,
+            submit_time,
+            updated_at: self.updated_at,
+        });
+
+        // Job Resources (ReqTRES vs AllocTRES)
+        // A resource may only show up in one of the two (e.g. pending jobs have no AllocTRES)
+        let requested = info.req_res.take().unwrap_or_default();
+        let allocated = info.alloc_res.take().unwrap_or_default();
+        let mut res_types: Vec<&ResourceType> = requested.keys().chain(allocated.keys()).collect();
+        res_types.sort();
+        res_types.dedup();
+        for resource in res_types {
+            self.resources.insert(JobResource {
+                job: job_id.clone(),
+                resource: resource.clone(),
+                requested: requested.get(resource).map(|q| q.0).unwrap_or(0),
+                allocated: allocated.get(resource).map(|q| q.0).unwrap_or(0),
+            });
+        }
+
+        // Job Allocations
+        for (node, resource, used) in node_allocations(&info, &allocated) {
+            self.allocations.insert(JobAllocation {
+                job: job_id.clone(),
+                node,
+                resource,
+                used,
+            });
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Self::Tables {
+        (self.jobs, self.allocations, self.resources)
+    }
+}
+
+#[derive(Default)]
+struct StepTables {
+    updated_at: DateTime<Utc>,
+    steps: Table<JobStep>,
+}
+
+impl Collector for StepTables {
+    const ENTITY: &'static str = "steps";
+
+    type Info<'src> = StepInfo<'src>;
+    type Tables = Table<JobStep>;
+
+    fn new(updated_at: DateTime<Utc>) -> Self {
+        Self {
+            updated_at,
+            ..Default::default()
+        }
+    }
+
+    fn add(&mut self, info: StepInfo) -> Result<(), parser::Error> {
+        let (job, step) = parse_step_id(info.step_id)
+            .map_err(|e| parser::Error::in_value(e, "StepId", info.step_id))?;
+        self.steps.insert(JobStep {
+            job,
+            step: step.to_string(),
+            name: info.name.to_string(),
+            status: JobStatus::from(info.state),
+            nodes: info.node_list,
+            tasks: info.tasks,
+            start_time: info.start_time,
+            ave_cpu: None,
+            max_rss: None,
+            max_disk_read: None,
+            max_disk_write: None,
+            updated_at: self.updated_at,
+        });
+        Ok(())
+    }
+
+    fn finish(self) -> Self::Tables {
+        self.steps
+    }
+}
+
+// Splits a step id like "1234_7.batch" into the job and the step
+pub(crate) fn parse_step_id(step_id: &str) -> Result<(JobId, &str)> {
+    let (job, step) = step_id
+        .split_once('.')
+        .with_context(|| format!("Invalid step id: {}", step_id))?;
+    Ok((job.parse()?, step))
+}
+
+// Splits the allocated resources of a job across its nodes.
+// The --details lines give the exact CPUs, memory and GRES used on each node.
+// Everything else (or everything, if there are no detail lines) is split evenly.
+fn node_allocations(
+    info: &JobInfo,
+    allocated: &BTreeMap<ResourceType, ResourceQuantity>,
+) -> Vec<(NodeName, ResourceType, i64)> {
+    let mut result = Vec::new();
+    let detailed = !info.detail_nodes.0.is_empty();
+
+    for (i, nodes) in info.detail_nodes.0.iter().enumerate() {
+        let cpus = info
+            .detail_cpu_ids
+            .0
+            .get(i)
+            .copied()
+            .flatten()
+            .map(count_ids)
+            .unwrap_or(0);
+        let mem = info.detail_mem.0.get(i).copied().flatten().unwrap_or(0);
+        let gres = info
+            .detail_gres
+            .0
+            .get(i)
+            .copied()
+            .flatten()
+            .map(parse_gres)
+            .unwrap_or_default();
+        for node in nodes {
+            result.push((node.clone(), ResourceType::new("node"), 1));
+            result.push((node.clone(), ResourceType::new("cpu"), cpus));
+            // Mem is in megabytes, the same unit as the M suffix
+            result.push((node.clone(), ResourceType::new("mem"), mem * 1024 * 1024));
+            for (resource, count) in &gres {
+                result.push((node.clone(), resource.clone(), *count));
             }
-            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
-            where
-                E: serde::de::Error,
-            {
-                let mut value = v.trim();
-                let mut multiplier = 1;
-                if value.ends_with('M') {
-                    multiplier = 1000 * 1000;
-                    value = &value[..value.len() - 1];
-                } else if value.ends_with('G') {
-                    multiplier = 1000 * 1000 * 1000;
-                    value = &value[..value.len() - 1];
-                }
-                Ok(ResourceQuantity(
-                    (value.parse::<f64>().map_err(|_| {
-                        E::custom(format!(
-                            "Invalid resource quantity: {} in specifier {}",
-                            value, v
-                        ))
-                    })? * multiplier as f64) as i64,
-                ))
+        }
+    }
+
+    let remaining = allocated.iter().filter(|(resource, _)| {
+        let from_details = matches!(
+            resource.kind,
+            ResourceKind::Node | ResourceKind::Cpu | ResourceKind::Mem | ResourceKind::Gres
+        );
+        !(detailed && from_details)
+    });
+    result.extend(split_evenly(&info.node_list, remaining));
+    result
+}
+
+// Splits allocated resources evenly across the nodes of a job,
+// handing any remainder to the first nodes.
+pub(crate) fn split_evenly<'a>(
+    nodes: &[NodeName],
+    allocated: impl IntoIterator<Item = (&'a ResourceType, &'a ResourceQuantity)>,
+) -> Vec<(NodeName, ResourceType, i64)> {
+    let mut result = Vec::new();
+    let node_count = nodes.len() as i64;
+    if node_count == 0 {
+        return result;
+    }
+    for (resource, qty) in allocated {
+        for (i, node) in nodes.iter().enumerate() {
+            let used = qty.0 / node_count + i64::from((i as i64) < qty.0 % node_count);
+            result.push((node.clone(), resource.clone(), used));
+        }
+    }
+    result
+}
+
+// Typed GRES like gres/gpu:a100 are only in the TRES of a node if AccountingStorageTRES
+// tracks them, but its Gres and GresUsed always list them.
+// Returns the total and available count of the GRES missing from `tracked`.
+pub(crate) fn untracked_gres(
+    tracked: &BTreeMap<ResourceType, ResourceQuantity>,
+    gres: &str,
+    gres_used: &str,
+) -> Vec<(ResourceType, u64, u64)> {
+    let used: BTreeMap<ResourceType, i64> = parse_gres(gres_used).into_iter().collect();
+    parse_gres(gres)
+        .into_iter()
+        .filter(|(resource, _)| !tracked.contains_key(resource))
+        .map(|(resource, total)| {
+            let used = used.get(&resource).copied().unwrap_or(0);
+            (resource, total as u64, total.saturating_sub(used) as u64)
+        })
+        .collect()
+}
+
+// Counts the ids in a list like "0-3,8-11"
+fn count_ids(ids: &str) -> i64 {
+    ids.split(',')
+        .filter_map(|range| match range.split_once('-') {
+            Some((lo, hi)) => Some(hi.parse::<i64>().ok()? - lo.parse::<i64>().ok()? + 1),
+            None => range.parse::<i64>().ok().map(|_| 1),
+        })
+        .sum()
+}
+
+// Parses the GRES of a node or an allocation line, e.g. "gpu:a100:2(IDX:0-1),shard:0"
+// into the TRES used by AllocTRES: [("gres/gpu", 2), ("gres/gpu:a100", 2)].
+// The counts of several types of the same GRES add up in the untyped one.
+pub(crate) fn parse_gres(gres: &str) -> Vec<(ResourceType, i64)> {
+    let mut result: BTreeMap<ResourceType, i64> = BTreeMap::new();
+    // Split on the commas outside of the "(IDX:...)" suffix
+    let mut depth = 0;
+    let items = gres.split(|c| {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => (),
+        }
+        c == ',' && depth == 0
+    });
+    for item in items {
+        let (spec, index) = match item.split_once('(') {
+            Some((spec, index)) => (spec, Some(index.trim_end_matches(')'))),
+            None => (item, None),
+        };
+        let mut parts: Vec<&str> = spec.split(':').collect();
+        // The count is optional, older versions only list the indices
+        let count = match parts.last().and_then(|c| c.parse::<i64>().ok()) {
+            Some(count) => {
+                parts.pop();
+                count
             }
+            None => index
+                .and_then(|i| i.strip_prefix("IDX:"))
+                .map(count_ids)
+                .unwrap_or(1),
+        };
+        if count == 0 || parts[0].is_empty() {
+            continue;
+        }
+        *result
+            .entry(ResourceType::gres(parts[0], None))
+            .or_default() += count;
+        if let Some(gres_type) = parts.get(1) {
+            *result
+                .entry(ResourceType::gres(parts[0], Some(gres_type)))
+                .or_default() += count;
+        }
+    }
+    result.into_iter().collect()
+}
+
+// Splits a node reason like "Kill task failed [root@2026-10-01T12:00:00]"
+// into the text, the user who set it and when
+pub(crate) fn parse_reason(
+    reason: &str,
+) -> (Option<String>, Option<String>, Option<DateTime<Utc>>) {
+    let set_by = reason
+        .strip_suffix(']')
+        .and_then(|r| r.rsplit_once(" ["))
+        .and_then(|(text, set_by)| Some((text, set_by.split_once('@')?)));
+    match set_by {
+        Some((text, (user, time))) => (
+            Some(text.trim().to_string()),
+            Some(user.to_string()),
+            parse_time(time),
+        ),
+        None => (Some(reason.to_string()), None, None),
+    }
+}
+
+// A list of allowed accounts or groups, None for "ALL"
+pub(crate) fn parse_allow_list(list: Option<&str>) -> Option<Vec<String>> {
+    let list = list.filter(|l| !l.is_empty() && *l != "ALL")?;
+    Some(list.split(',').map(|s| s.to_string()).collect())
+}
+
+// "UserId=alice(1234)" includes the uid, we only want the name
+pub(crate) fn parse_user(user: &str) -> &str {
+    user.split_once('(').map(|(name, _)| name).unwrap_or(user)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::Keyed;
+    use crate::{NodeFlag, NodeStatus};
+
+    const JOBS: &str = "JobId=8601779 JobName=8445fb49-9088-4fd5-b463-65b76bf6c4bb
+   UserId=cysteine(135712) GroupId=cysteine(100135712) MCS_label=N/A
+   Priority=21212 Nice=0 Account=mit_general QOS=normal
+   JobState=RUNNING Reason=None Dependency=(null)
+   Requeue=0 Restarts=0 BatchFlag=1 Reboot=0 ExitCode=0:0
+   RunTime=00:15:41 TimeLimit=08:00:00 TimeMin=N/A
+   SubmitTime=2026-01-31T12:44:31 EligibleTime=2026-01-31T12:44:31
+   AccrueTime=2026-01-31T12:44:31
+   StartTime=2026-01-31T12:45:05 EndTime=2026-01-31T20:45:05 Deadline=N/A
+   SuspendTime=None SecsPreSuspend=0 LastSchedEval=2026-01-31T12:45:05 Scheduler=Main
+   Partition=sched_mit_hill AllocNode:Sid=node2429:26654
+   ReqNodeList=(null) ExcNodeList=(null)
+   NodeList=node156
+   BatchHost=node156
+   NumNodes=1 NumCPUs=1 NumTasks=1 CPUs/Task=1 ReqB:S:C:T=0:0:*:*
+   ReqTRES=cpu=1,mem=15000M,node=1,billing=1
+   AllocTRES=cpu=1,mem=15000M,node=1,billing=1
+   Socks/Node=* NtasksPerN:B:S:C=0:0:*:* CoreSpec=*
+   MinCPUsNode=1 MinMemoryNode=15000M MinTmpDiskNode=0
+   Features=(null) DelayBoot=00:00:00
+   OverSubscribe=OK Contiguous=0 Licenses=(null) LicensesAlloc=(null) Network=(null)
+   Command=(null)
+   WorkDir=/orcd/data/tami/003/projects/cysteine/dnds
+   Comment=rule_pairwise_batch_wildcards__orcd_data_tami_003_projects_cysteine_dnds_bfragilis_396
+
+JobId=8602011 JobName=train resnet
+   UserId=alice(140001) GroupId=alice(100140001) MCS_label=N/A
+   Priority=10450 Nice=0 Account=mit_general QOS=normal
+   JobState=PENDING Reason=Resources Dependency=(null)
+   Requeue=0 Restarts=0 BatchFlag=1 Reboot=0 ExitCode=0:0
+   RunTime=00:00:00 TimeLimit=2-00:00:00 TimeMin=N/A
+   SubmitTime=2026-01-31T13:02:10 EligibleTime=2026-01-31T13:02:10
+   AccrueTime=2026-01-31T13:02:10
+   StartTime=Unknown EndTime=Unknown Deadline=N/A
+   SuspendTime=None SecsPreSuspend=0 LastSchedEval=2026-01-31T13:05:00 Scheduler=Main
+   Partition=mit_normal_gpu AllocNode:Sid=node2429:31337
+   ReqNodeList=(null) ExcNodeList=(null)
+   NodeList=
+   NumNodes=1-1 NumCPUs=16 NumTasks=1 CPUs/Task=16 ReqB:S:C:T=0:0:*:*
+   ReqTRES=cpu=16,mem=64G,node=1,billing=16,gres/gpu=1
+   Socks/Node=* NtasksPerN:B:S:C=0:0:*:* CoreSpec=*
+   MinCPUsNode=16 MinMemoryNode=64G MinTmpDiskNode=0
+   Features=(null) DelayBoot=00:00:00
+   OverSubscribe=OK Contiguous=0 Licenses=(null) LicensesAlloc=(null) Network=(null)
+   Command=/home/alice/train.sh
+   WorkDir=/home/alice
+
+JobId=8602044 JobName=mpi
+   UserId=bob(140002) GroupId=bob(100140002) MCS_label=N/A
+   Priority=9000 Nice=0 Account=mit_general QOS=normal
+   JobState=RUNNING Reason=None Dependency=(null)
+   Requeue=0 Restarts=0 BatchFlag=1 Reboot=0 ExitCode=0:0
+   RunTime=01:00:00 TimeLimit=UNLIMITED TimeMin=N/A
+   SubmitTime=2026-01-31T11:00:00 EligibleTime=2026-01-31T11:00:00
+   AccrueTime=2026-01-31T11:00:00
+   StartTime=2026-01-31T11:00:01 EndTime=Unknown Deadline=N/A
+   Partition=sched_mit_hill AllocNode:Sid=node2429:4242
+   ReqNodeList=(null) ExcNodeList=(null)
+   NodeList=node[101,205]
+   BatchHost=node101
+   NumNodes=2 NumCPUs=9 NumTasks=9 CPUs/Task=1 ReqB:S:C:T=0:0:*:*
+   ReqTRES=cpu=9,mem=18G,node=2,billing=9,gres/gpu=3
+   AllocTRES=cpu=9,mem=18G,node=2,billing=9,gres/gpu=3,gres/gpu:a100=3
+   Socks/Node=* NtasksPerN:B:S:C=0:0:*:* CoreSpec=*
+   JOB_GRES=gpu:a100:3
+     Nodes=node101 CPU_IDs=0-3,8 Mem=10000 GRES=gpu:a100:2(IDX:0-1)
+     Nodes=node205 CPU_IDs=0-3 Mem=8000 GRES=gpu:a100:1(IDX:3)
+   MinCPUsNode=1 MinMemoryNode=0 MinTmpDiskNode=0
+   Command=/home/bob/mpi.sh
+   WorkDir=/home/bob
+
+JobId=8602050 JobName=sweep
+   UserId=bob(140002) GroupId=bob(100140002) MCS_label=N/A
+   JobState=RUNNING Reason=None Dependency=(null)
+   RunTime=00:10:00 TimeLimit=04:00:00 TimeMin=N/A
+   SubmitTime=2026-01-31T12:00:00 EligibleTime=2026-01-31T12:00:00
+   StartTime=2026-01-31T12:00:05 EndTime=2026-01-31T16:00:05 Deadline=N/A
+   Partition=sched_mit_hill AllocNode:Sid=node2429:4243
+   NodeList=node[301-303]
+   BatchHost=node301
+   NumNodes=3 NumCPUs=192 NumTasks=3 CPUs/Task=64 ReqB:S:C:T=0:0:*:*
+   ReqTRES=cpu=192,mem=750G,node=3,billing=192
+   AllocTRES=cpu=192,mem=750G,node=3,billing=192
+   Socks/Node=* NtasksPerN:B:S:C=0:0:*:* CoreSpec=*
+     Nodes=node[301-303] CPU_IDs=0-63 Mem=256000 GRES=
+   MinCPUsNode=64 MinMemoryNode=250G MinTmpDiskNode=0
+   Command=/home/bob/sweep.sh
+   WorkDir=/home/bob
+
+JobId=8602061 JobName=assemble
+   UserId=alice(140001) GroupId=alice(100140001) MCS_label=N/A
+   JobState=OUT_OF_MEMORY Reason=OutOfMemory Dependency=(null)
+   RunTime=00:03:12 TimeLimit=01:00:00 TimeMin=N/A
+   SubmitTime=2026-01-31T12:30:00 EligibleTime=2026-01-31T12:30:00
+   StartTime=2026-01-31T12:30:02 EndTime=2026-01-31T12:33:14 Deadline=N/A
+   Partition=sched_mit_hill AllocNode:Sid=node2429:4244
+   NodeList=node156
+   NumNodes=1 NumCPUs=4 NumTasks=1 CPUs/Task=4 ReqB:S:C:T=0:0:*:*
+   ReqTRES=cpu=4,mem=8G,node=1,billing=4
+   AllocTRES=cpu=4,mem=8G,node=1,billing=4
+   Command=/home/alice/assemble.sh
+   WorkDir=/home/alice
+
+JobId=8602101 ArrayJobId=8602100 ArrayTaskId=1 JobName=array
+   UserId=alice(140001) GroupId=alice(100140001) MCS_label=N/A
+   JobState=RUNNING Reason=None Dependency=(null)
+   RunTime=00:01:00 TimeLimit=01:00:00 TimeMin=N/A
+   SubmitTime=2026-01-31T13:00:00 EligibleTime=2026-01-31T13:00:00
+   StartTime=2026-01-31T13:00:10 EndTime=2026-01-31T14:00:10 Deadline=N/A
+   Partition=sched_mit_hill AllocNode:Sid=node2429:4245
+   NodeList=node156
+   NumNodes=1 NumCPUs=1 NumTasks=1 CPUs/Task=1 ReqB:S:C:T=0:0:*:*
+   ReqTRES=cpu=1,mem=4G,node=1,billing=1
+   AllocTRES=cpu=1,mem=4G,node=1,billing=1
+   Command=/home/alice/array.sh
+   WorkDir=/home/alice
+
+JobId=8602100 ArrayJobId=8602100 ArrayTaskId=2-100%10 JobName=array
+   UserId=alice(140001) GroupId=alice(100140001) MCS_label=N/A
+   JobState=PENDING Reason=JobArrayTaskLimit Dependency=(null)
+   RunTime=00:00:00 TimeLimit=01:00:00 TimeMin=N/A
+   SubmitTime=2026-01-31T13:00:00 EligibleTime=2026-01-31T13:00:00
+   StartTime=Unknown EndTime=Unknown Deadline=N/A
+   Partition=sched_mit_hill AllocNode:Sid=node2429:4245
+   NodeList=(null)
+   NumNodes=1 NumCPUs=1 NumTasks=1 CPUs/Task=1 ReqB:S:C:T=0:0:*:*
+   ReqTRES=cpu=1,mem=4G,node=1,billing=1
+   Command=/home/alice/array.sh
+   WorkDir=/home/alice
+
+JobId=8602201 HetJobId=8602200 HetJobOffset=1 JobName=het
+   UserId=bob(140002) GroupId=bob(100140002) MCS_label=N/A
+   JobState=PENDING Reason=Priority Dependency=(null)
+   RunTime=00:00:00 TimeLimit=01:00:00 TimeMin=N/A
+   SubmitTime=2026-01-31T13:10:00 EligibleTime=2026-01-31T13:10:00
+   StartTime=Unknown EndTime=Unknown Deadline=N/A
+   Partition=sched_mit_hill AllocNode:Sid=node2429:4246
+   NodeList=(null)
+   NumNodes=1 NumCPUs=8 NumTasks=1 CPUs/Task=8 ReqB:S:C:T=0:0:*:*
+   ReqTRES=cpu=8,mem=16G,node=1,billing=8
+   Command=/home/bob/het.sh
+   WorkDir=/home/bob";
+
+    #[test]
+    fn test_parse_jobs() {
+        let updated_at = Utc::now();
+        let (jobs, allocations, resources) =
+            parse::<JobTables>(JOBS, updated_at, &mut Vec::new()).unwrap();
+
+        let running = jobs.get(&JobId::new(8601779)).unwrap();
+        assert_eq!(running.name, "8445fb49-9088-4fd5-b463-65b76bf6c4bb");
+        assert_eq!(running.user, "cysteine");
+        assert_eq!(running.partition, "sched_mit_hill");
+        assert_eq!(running.account.as_deref(), Some("mit_general"));
+        assert_eq!(running.qos.as_deref(), Some("normal"));
+        assert_eq!(running.status, JobStatus::Running);
+        assert_eq!(running.reason, None);
+        assert_eq!(running.time_limit, Some(8 * 60 * 60));
+        assert_eq!(
+            running.submit_time,
+            parse_time("2026-01-31T12:44:31").unwrap()
+        );
+        assert_eq!(
+            running.start_time.unwrap() - running.submit_time,
+            chrono::Duration::seconds(34)
+        );
+        assert_eq!(
+            running.end_time.unwrap() - running.start_time.unwrap(),
+            chrono::Duration::hours(8)
+        );
+        assert_eq!(running.updated_at, updated_at);
+
+        let pending = jobs.get(&JobId::new(8602011)).unwrap();
+        // Job names may have spaces
+        assert_eq!(pending.name, "train resnet");
+        assert_eq!(pending.status, JobStatus::Pending);
+        assert_eq!(pending.reason.as_deref(), Some("Resources"));
+        assert_eq!(pending.time_limit, Some(2 * 24 * 60 * 60));
+        assert_eq!((pending.start_time, pending.end_time), (None, None));
+
+        let multi = jobs.get(&JobId::new(8602044)).unwrap();
+        assert_eq!(multi.time_limit, None);
+
+        let oom = jobs.get(&JobId::new(8602061)).unwrap();
+        assert_eq!(oom.status, JobStatus::OutOfMemory);
+        assert!(oom.status.is_failed());
+        assert_eq!(oom.reason.as_deref(), Some("OutOfMemory"));
+
+        // Array tasks and het job components go by the id of the whole job
+        let task = jobs.get(&JobId::task(8602100, 1)).unwrap();
+        assert_eq!(task.status, JobStatus::Running);
+        assert!(allocations.iter().any(|a| a.job == task.job_id));
+        let tasks = jobs.get(&JobId::tasks(8602100, "2-100%10")).unwrap();
+        assert_eq!(tasks.job_id.task_count(), 99);
+        assert_eq!(tasks.reason.as_deref(), Some("JobArrayTaskLimit"));
+        assert!(jobs.get(&JobId::new(8602100)).is_none());
+        assert!(jobs.get(&JobId::component(8602200, 1)).is_some());
+
+        // Pending jobs only have requested resources
+        let gpu = resources
+            .get(&(JobId::new(8602011), ResourceType::new("gres/gpu")))
+            .unwrap();
+        assert_eq!((gpu.requested, gpu.allocated), (1, 0));
+        let mem = resources
+            .get(&(JobId::new(8601779), ResourceType::new("mem")))
+            .unwrap();
+        assert_eq!(
+            (mem.requested, mem.allocated),
+            (15000 * 1024 * 1024, 15000 * 1024 * 1024)
+        );
+
+        // Multi-node jobs use the --details lines per node
+        let used = |job: i64, node: &str, res: &str| {
+            allocations
+                .get(&(JobId::new(job), NodeName::new(node), ResourceType::new(res)))
+                .map(|a| a.used)
+        };
+        assert_eq!(used(8602044, "node101", "cpu"), Some(5));
+        assert_eq!(used(8602044, "node205", "cpu"), Some(4));
+        assert_eq!(used(8602044, "node101", "mem"), Some(10000 * 1024 * 1024));
+        assert_eq!(used(8602044, "node205", "mem"), Some(8000 * 1024 * 1024));
+        assert_eq!(used(8602044, "node101", "gres/gpu"), Some(2));
+        assert_eq!(used(8602044, "node205", "gres/gpu:a100"), Some(1));
+        assert_eq!(used(8602044, "node205", "node"), Some(1));
+        // and split the rest evenly
+        assert_eq!(used(8602044, "node101", "billing"), Some(5));
+        assert_eq!(used(8602044, "node205", "billing"), Some(4));
+
+        // A detail line can cover several identical nodes
+        for node in ["node301", "node302", "node303"] {
+            assert_eq!(used(8602050, node, "cpu"), Some(64));
+            assert_eq!(used(8602050, node, "mem"), Some(256000 * 1024 * 1024));
+            assert_eq!(used(8602050, node, "billing"), Some(64));
+            assert_eq!(used(8602050, node, "gres/gpu"), None);
         }
-        deserializer.deserialize_str(ResVisitor)
+
+        // The single node job has no details and takes all of its allocation
+        assert_eq!(used(8601779, "node156", "cpu"), Some(1));
+        assert_eq!(used(8601779, "node156", "mem"), Some(15000 * 1024 * 1024));
+        assert!(allocations.iter().all(|a| a.job != JobId::new(8602011)));
+    }
+
+    #[test]
+    fn test_parse_job_without_submit_time() {
+        let count = parse::<JobTables>(JOBS, Utc::now(), &mut Vec::new())
+            .unwrap()
+            .0
+            .len();
+        // The first job, which reads fine but can't be stored without a SubmitTime
+        let output = JOBS.replacen("SubmitTime=2026-01-31T12:44:31 ", "", 1);
+        let mut warnings = Vec::new();
+        let (jobs, allocations, resources) =
+            parse::<JobTables>(&output, Utc::now(), &mut warnings).unwrap();
+        assert_eq!(jobs.len(), count - 1);
+        assert!(jobs.get(&JobId::new(8601779)).is_none());
+        assert!(allocations.iter().all(|a| a.job != JobId::new(8601779)));
+        assert!(resources.iter().all(|r| r.job != JobId::new(8601779)));
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].source, "scontrol show jobs");
+        assert_eq!(warnings[0].error.record, Some(1));
+        assert_eq!(warnings[0].error.key.as_deref(), Some("SubmitTime"));
+    }
+
+    const NODES: &str = "NodeName=node101 Arch=x86_64 CoresPerSocket=32
+   CPUAlloc=5 CPUEfctv=64 CPUTot=64 CPULoad=4.90
+   Gres=gpu:a100:4(S:0-1)
+   RealMemory=512000 AllocMem=10000 FreeMem=400000 Sockets=2 Boards=1
+   State=MIXED+DRAIN ThreadsPerCore=2 TmpDisk=0 Weight=1 Owner=N/A MCS_label=N/A
+   Partitions=sched_mit_hill,mit_normal_gpu
+   BootTime=2026-09-01T08:00:00 SlurmdStartTime=2026-09-01T08:01:30
+   LastBusyTime=2026-10-01T11:58:00 ResumeAfterTime=None
+   CfgTRES=cpu=64,mem=500G,billing=64,gres/gpu=4
+   AllocTRES=cpu=5,mem=10000M,gres/gpu=2
+   GresUsed=gpu:a100:2(IDX:0-1)
+   Reason=Kill task failed [root@2026-10-01T12:00:00]
+
+NodeName=node156 Arch=x86_64 CoresPerSocket=16
+   CPUAlloc=0 CPUEfctv=32 CPUTot=32 CPULoad=0.00
+   Gres=(null)
+   RealMemory=128000 AllocMem=0 FreeMem=N/A Sockets=2 Boards=1
+   State=DOWN* ThreadsPerCore=1 TmpDisk=0 Weight=1 Owner=N/A MCS_label=N/A
+   Partitions=sched_mit_hill
+   BootTime=None SlurmdStartTime=None
+   LastBusyTime=2026-09-30T22:10:00 ResumeAfterTime=None
+   CfgTRES=cpu=32,mem=125G,billing=32
+   AllocTRES=
+   Reason=Not responding
+";
+
+    #[test]
+    fn test_parse_nodes() {
+        let (nodes, resources, partitions) =
+            parse::<NodeTables>(NODES, Utc::now(), &mut Vec::new()).unwrap();
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(partitions.len(), 3);
+        let draining = nodes.get(&NodeName::new("node101")).unwrap();
+        assert_eq!(
+            draining.state,
+            NodeState::new(NodeStatus::Mix, [NodeFlag::Drain])
+        );
+        assert!(draining.state.is_draining());
+        assert_eq!(draining.reason.as_deref(), Some("Kill task failed"));
+        assert_eq!(draining.reason_user.as_deref(), Some("root"));
+        assert_eq!(draining.reason_time, parse_time("2026-10-01T12:00:00"));
+        assert_eq!(
+            draining.slurmd_start_time.unwrap() - draining.boot_time.unwrap(),
+            chrono::Duration::seconds(90)
+        );
+        assert_eq!(draining.last_busy_time, parse_time("2026-10-01T11:58:00"));
+        let down = nodes.get(&NodeName::new("node156")).unwrap();
+        assert_eq!(
+            down.state,
+            NodeState::new(NodeStatus::Down, [NodeFlag::NotResponding])
+        );
+        assert_eq!(down.reason.as_deref(), Some("Not responding"));
+        assert_eq!((down.reason_user.as_ref(), down.reason_time), (None, None));
+        assert_eq!(down.boot_time, None);
+
+        let resource = |node: &str, res: &str| {
+            resources
+                .get(&(NodeName::new(node), ResourceType::new(res)))
+                .map(|r| (r.total, r.available))
+        };
+        assert_eq!(
+            resource("node101", "mem"),
+            Some((500 << 30, (500 << 30) - 10000 * 1024 * 1024))
+        );
+        assert_eq!(resource("node101", "gres/gpu"), Some((4, 2)));
+        // The GPU type is only known from Gres and GresUsed
+        assert_eq!(resource("node101", "gres/gpu:a100"), Some((4, 2)));
+        assert_eq!(resource("node156", "cpu"), Some((32, 32)));
+        assert_eq!(resource("node156", "gres/gpu"), None);
+    }
+
+    #[test]
+    fn test_parse_no_jobs() {
+        let (jobs, allocations, resources) =
+            parse::<JobTables>("No jobs in the system\n", Utc::now(), &mut Vec::new()).unwrap();
+        assert!(jobs.is_empty() && allocations.is_empty() && resources.is_empty());
+    }
+
+    const PARTITIONS: &str = "PartitionName=mit_normal_gpu
+   AllowGroups=ALL AllowAccounts=mit_general,mit_gpu AllowQos=normal,gpu
+   AllocNodes=ALL Default=NO QoS=gpu-normal
+   DefaultTime=01:00:00 DisableRootJobs=NO ExclusiveUser=NO GraceTime=0 Hidden=NO
+   MaxNodes=2 MaxTime=2-00:00:00 MinNodes=0 LLN=NO MaxCPUsPerNode=UNLIMITED MaxCPUsPerSocket=UNLIMITED
+   Nodes=node[101,205]
+   PriorityJobFactor=1 PriorityTier=10 RootOnly=NO ReqResv=NO OverSubscribe=NO
+   OverTimeLimit=NONE PreemptMode=OFF
+   State=UP TotalCPUs=128 TotalNodes=2 SelectTypeParameters=NONE
+   JobDefaults=(null)
+   DefMemPerNode=UNLIMITED MaxMemPerNode=UNLIMITED
+   TRES=cpu=128,mem=1000G,node=2,billing=128,gres/gpu=8
+   TRESBillingWeights=CPU=1.0,Mem=0.25G,GRES/gpu=2.0
+
+PartitionName=mit_preemptable
+   AllowGroups=ALL AllowAccounts=ALL AllowQos=ALL
+   AllocNodes=ALL Default=NO QoS=N/A
+   DefaultTime=NONE DisableRootJobs=NO ExclusiveUser=NO GraceTime=0 Hidden=NO
+   MaxNodes=UNLIMITED MaxTime=UNLIMITED MinNodes=1 LLN=NO MaxCPUsPerNode=UNLIMITED MaxCPUsPerSocket=UNLIMITED
+   Nodes=node[101,205,301-303]
+   PriorityJobFactor=1 PriorityTier=1 RootOnly=NO ReqResv=NO OverSubscribe=FORCE:4
+   OverTimeLimit=NONE PreemptMode=REQUEUE
+   State=DRAIN TotalCPUs=320 TotalNodes=5 SelectTypeParameters=NONE
+   JobDefaults=(null)
+   DefMemPerNode=UNLIMITED MaxMemPerNode=UNLIMITED
+   TRES=cpu=320,mem=1750G,node=5,billing=320";
+
+    #[test]
+    fn test_parse_partitions() {
+        let partitions = parse::<PartitionTables>(PARTITIONS, Utc::now(), &mut Vec::new()).unwrap();
+
+        let gpu = partitions.get(&"mit_normal_gpu".to_string()).unwrap();
+        assert_eq!(gpu.status, PartitionStatus::Up);
+        assert_eq!(gpu.access_qos.as_deref(), Some("normal,gpu"));
+        assert_eq!(gpu.resource_qos.as_deref(), Some("gpu-normal"));
+        assert_eq!(gpu.nodes.as_deref(), Some("node[101,205]"));
+        assert_eq!((gpu.total_nodes, gpu.total_cpus), (2, 128));
+        assert_eq!(gpu.max_time, Some(2 * 24 * 60 * 60));
+        assert_eq!(gpu.default_time, Some(60 * 60));
+        assert_eq!((gpu.max_nodes, gpu.min_nodes), (Some(2), 0));
+        assert_eq!(gpu.priority_tier, 10);
+        assert_eq!(gpu.preempt_mode.as_deref(), Some("OFF"));
+        assert_eq!(gpu.over_subscribe.as_deref(), Some("NO"));
+        assert_eq!(
+            gpu.allow_accounts,
+            Some(vec!["mit_general".to_string(), "mit_gpu".to_string()])
+        );
+        assert_eq!(gpu.allow_groups, None);
+        assert_eq!(
+            gpu.billing_weights.get(&ResourceType::new("gres/gpu")),
+            Some(&2.0)
+        );
+
+        let preemptable = partitions.get(&"mit_preemptable".to_string()).unwrap();
+        assert_eq!(preemptable.status, PartitionStatus::Drain);
+        assert_eq!(preemptable.resource_qos, None);
+        assert_eq!(
+            (preemptable.max_time, preemptable.default_time),
+            (None, None)
+        );
+        assert_eq!((preemptable.max_nodes, preemptable.min_nodes), (None, 1));
+        assert_eq!(preemptable.over_subscribe.as_deref(), Some("FORCE:4"));
+        assert_eq!(preemptable.allow_accounts, None);
+        assert!(preemptable.billing_weights.is_empty());
+    }
+
+    const RESERVATIONS: &str = "ReservationName=maint StartTime=2026-02-10T08:00:00 EndTime=2026-02-10T20:00:00 Duration=12:00:00
+   Nodes=node[100-102] NodeCnt=3 CoreCnt=192 Features=(null) PartitionName=(null) Flags=MAINT,IGNORE_JOBS,SPEC_NODES
+   TRES=cpu=192
+   Users=root Groups=(null) Accounts=(null) Licenses=(null) State=INACTIVE BurstBuffer=(null) Watts=n/a
+   MaxStartDelay=(null)
+
+ReservationName=course StartTime=2026-01-31T09:00:00 EndTime=2026-05-31T09:00:00 Duration=120-00:00:00
+   Nodes=node101,node205 NodeCnt=2 CoreCnt=128 Features=(null) PartitionName=mit_normal_gpu Flags=
+   TRES=cpu=128,gres/gpu=8
+   Users=(null) Groups=(null) Accounts=course101,-staff Licenses=(null) State=ACTIVE BurstBuffer=(null) Watts=n/a
+   MaxStartDelay=(null)
+";
+
+    #[test]
+    fn test_parse_reservations() {
+        let (reservations, nodes) =
+            parse::<ReservationTables>(RESERVATIONS, Utc::now(), &mut Vec::new()).unwrap();
+        assert_eq!(reservations.len(), 2);
+
+        let maint = reservations.get(&"maint".to_string()).unwrap();
+        assert!(!maint.active);
+        assert_eq!(
+            maint.end_time - maint.start_time,
+            chrono::Duration::hours(12)
+        );
+        assert_eq!(maint.flags, vec!["MAINT", "IGNORE_JOBS", "SPEC_NODES"]);
+        assert_eq!(maint.users, vec!["root"]);
+        assert!(maint.accounts.is_empty());
+        assert_eq!(maint.partition, None);
+
+        let course = reservations.get(&"course".to_string()).unwrap();
+        assert!(course.active);
+        assert_eq!(course.partition.as_deref(), Some("mit_normal_gpu"));
+        assert_eq!(course.accounts, vec!["course101", "-staff"]);
+        assert!(course.flags.is_empty());
+        assert_eq!(course.tres.get(&ResourceType::new("gres/gpu")), Some(&8));
+
+        // node101 is in both reservations
+        assert_eq!(nodes.len(), 5);
+        let key = |node: &str, res: &str| (NodeName::new(node), res.to_string());
+        assert!(nodes.get(&key("node101", "maint")).is_some());
+        assert!(nodes.get(&key("node101", "course")).is_some());
+        assert!(nodes.get(&key("node205", "maint")).is_none());
+    }
+
+    const STEPS: &str =
+        "StepId=8602044.batch UserId=140002 StartTime=2026-01-31T13:00:01 TimeLimit=UNLIMITED
+   State=RUNNING Partition=sched_mit_hill NodeList=node101
+   Nodes=1 CPUs=5 Tasks=1 Name=batch Network=(null)
+   TRES=cpu=5,mem=10000M,node=1
+   ResvPorts=(null)
+   CPUFreqReq=Default Dist=(null)
+   SrunHost:Pid=(null):0
+
+StepId=8602044.0 UserId=140002 StartTime=2026-01-31T13:00:02 TimeLimit=UNLIMITED
+   State=RUNNING Partition=sched_mit_hill NodeList=node[101,205]
+   Nodes=2 CPUs=9 Tasks=2 Name=hydra Network=(null)
+   TRES=cpu=9,mem=18000M,node=2,gres/gpu=3
+   ResvPorts=(null)
+   CPUFreqReq=Default Dist=Block
+   SrunHost:Pid=node101:51234
+
+StepId=8602100_1.extern UserId=140001 StartTime=2026-01-31T13:00:10 TimeLimit=UNLIMITED
+   State=RUNNING Partition=sched_mit_hill NodeList=node156
+   Nodes=1 CPUs=1 Tasks=1 Name=extern Network=(null)
+   TRES=cpu=1,mem=4G,node=1
+   ResvPorts=(null)
+   CPUFreqReq=Default Dist=Block
+   SrunHost:Pid=(null):0
+";
+
+    #[test]
+    fn test_parse_steps() {
+        let steps = parse::<StepTables>(STEPS, Utc::now(), &mut Vec::new()).unwrap();
+        assert_eq!(steps.len(), 3);
+
+        let step = |job: JobId, step: &str| steps.get(&(job, step.to_string())).unwrap();
+        let batch = step(JobId::new(8602044), "batch");
+        assert_eq!(batch.status, JobStatus::Running);
+        assert_eq!(batch.nodes, vec![NodeName::new("node101")]);
+        assert_eq!(batch.tasks, 1);
+        assert_eq!(batch.max_rss, None);
+
+        let srun = step(JobId::new(8602044), "0");
+        assert_eq!(srun.name, "hydra");
+        assert_eq!(srun.tasks, 2);
+        assert_eq!(
+            srun.nodes,
+            vec![NodeName::new("node101"), NodeName::new("node205")]
+        );
+        assert_eq!(
+            srun.start_time,
+            Some(parse_time("2026-01-31T13:00:02").unwrap())
+        );
+
+        let extern_step = step(JobId::task(8602100, 1), "extern");
+        assert_eq!(extern_step.name, "extern");
+
+        assert!(
+            parse::<StepTables>("No job steps in the system\n", Utc::now(), &mut Vec::new())
+                .unwrap()
+                .is_empty()
+        );
+        assert!(parse_step_id("8602044").is_err());
+
+        // A record that does not parse is skipped with a warning
+        let output = format!("{}\nStepId=8602300.0 State=RUNNING Tasks=many\n", STEPS);
+        let mut warnings = Vec::new();
+        let steps = parse::<StepTables>(&output, Utc::now(), &mut warnings).unwrap();
+        assert_eq!(steps.len(), 3);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].source, "scontrol show steps");
+        assert_eq!(warnings[0].error.record, Some(4));
+        assert_eq!(warnings[0].error.key.as_deref(), Some("Tasks"));
+    }
+
+    // Reads a fixture the way `show` reads the output of scontrol
+    fn parse<C: Collector>(
+        output: &str,
+        updated_at: DateTime<Utc>,
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Result<C::Tables> {
+        parse_as::<C>(output, None, updated_at, warnings)
+    }
+
+    fn parse_as<C: Collector>(
+        output: &str,
+        version: Option<SlurmVersion>,
+        updated_at: DateTime<Utc>,
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Result<C::Tables> {
+        let mut reader = parser::RecordReader::new(output.as_bytes());
+        let mut collector = C::new(updated_at);
+        while let Some(record) = reader.next_record()? {
+            add(&mut collector, record, version, warnings);
+        }
+        Ok(collector.finish())
+    }
+
+    // Reads the records of a fixture and writes them back
+    fn rewrite<'a, T: Deserialize<'a> + Serialize>(fixture: &'a str, one_line: bool) -> String {
+        let records: Vec<T> = parser::from_str(fixture).unwrap();
+        if one_line {
+            parser::to_string_one_line(&records).unwrap()
+        } else {
+            parser::to_string(&records).unwrap()
+        }
+    }
+
+    fn assert_same_rows<V: Keyed + PartialEq + std::fmt::Debug>(a: &Table<V>, b: &Table<V>) {
+        assert_eq!(a.len(), b.len());
+        for row in a.iter() {
+            assert_eq!(b.get(&V::clone_key(row.key())), Some(row));
+        }
+    }
+
+    #[test]
+    fn test_write_fixtures() {
+        let now = Utc::now();
+        let warnings = &mut Vec::new();
+        for one_line in [false, true] {
+            let (nodes, resources, partitions) = parse::<NodeTables>(NODES, now, warnings).unwrap();
+            let text = rewrite::<NodeInfo>(NODES, one_line);
+            let written = parse::<NodeTables>(&text, now, warnings).unwrap();
+            assert_same_rows(&nodes, &written.0);
+            assert_same_rows(&resources, &written.1);
+            assert_same_rows(&partitions, &written.2);
+
+            let (jobs, allocations, resources) = parse::<JobTables>(JOBS, now, warnings).unwrap();
+            let text = rewrite::<JobInfo>(JOBS, one_line);
+            let written = parse::<JobTables>(&text, now, warnings).unwrap();
+            assert_same_rows(&jobs, &written.0);
+            assert_same_rows(&allocations, &written.1);
+            assert_same_rows(&resources, &written.2);
+
+            let partitions = parse::<PartitionTables>(PARTITIONS, now, warnings).unwrap();
+            let text = rewrite::<PartitionInfo>(PARTITIONS, one_line);
+            assert_same_rows(
+                &partitions,
+                &parse::<PartitionTables>(&text, now, warnings).unwrap(),
+            );
+
+            let (reservations, nodes) =
+                parse::<ReservationTables>(RESERVATIONS, now, warnings).unwrap();
+            let text = rewrite::<ReservationInfo>(RESERVATIONS, one_line);
+            let written = parse::<ReservationTables>(&text, now, warnings).unwrap();
+            assert_same_rows(&reservations, &written.0);
+            assert_same_rows(&nodes, &written.1);
+
+            let steps = parse::<StepTables>(STEPS, now, warnings).unwrap();
+            let text = rewrite::<StepInfo>(STEPS, one_line);
+            assert_same_rows(&steps, &parse::<StepTables>(&text, now, warnings).unwrap());
+        }
+        assert!(warnings.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_read_command() {
+        let mut command = Command::new("cat");
+        command.arg(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/fixtures/24.05/nodes.txt"
+        ));
+        let (nodes, _, _) = read_command::<NodeTables>(command, None, &mut Vec::new())
+            .await
+            .unwrap();
+        assert_eq!(nodes.len(), 2);
+
+        // Part of the nodes, then slurmctld goes away
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(
+            "echo 'NodeName=node101 CPUAlloc=0 CPUTot=64 State=IDLE'; \
+             echo 'slurm_load_node error: Unable to contact slurm controller' >&2; exit 1",
+        );
+        let err = read_command::<NodeTables>(command, None, &mut Vec::new())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("scontrol show nodes failed"));
+        assert!(err
+            .to_string()
+            .contains("Unable to contact slurm controller"));
+    }
+
+    #[test]
+    fn test_parse_no_reservations() {
+        let (reservations, nodes) = parse::<ReservationTables>(
+            "No reservations in the system\n",
+            Utc::now(),
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert!(reservations.is_empty() && nodes.is_empty());
+    }
+
+    #[test]
+    fn test_parse_version() {
+        let version: SlurmVersion = "slurm 23.02.7".parse().unwrap();
+        assert_eq!(version, SlurmVersion::new(23, 2, 7));
+        assert!(version.supports_json());
+        let version: SlurmVersion = "slurm-wlm 20.11.9\n".trim().parse().unwrap();
+        assert_eq!(version, SlurmVersion::new(20, 11, 9));
+        assert!(!version.supports_json());
+        assert_eq!(version.to_string(), "20.11.9");
+        assert!("slurm".parse::<SlurmVersion>().is_err());
+
+        assert!(version.is_supported());
+        assert!(SlurmVersion::new(24, 11, 0).is_supported());
+        assert!(!SlurmVersion::new(20, 2, 7).is_supported());
+        let json = serde_json::to_string(&version).unwrap();
+        assert_eq!(json, "\"20.11.9\"");
+        assert_eq!(
+            serde_json::from_str::<SlurmVersion>(&json).unwrap(),
+            version
+        );
+
+        let config = "Configuration data as of 2026-10-01T12:00:00
+AccountingStorageBackupHost = (null)
+ClusterName             = engaging
+SLURM_CONF              = /etc/slurm/slurm.conf
+SLURM_VERSION           = 23.11.10
+SlurmctldAddr           = (null)
+";
+        assert_eq!(
+            parse_config_version(config).unwrap(),
+            SlurmVersion::new(23, 11, 10)
+        );
+        assert!(parse_config_version("SLURM_CONF = /etc/slurm/slurm.conf").is_err());
+        assert_eq!(config_value(config, "ClusterName"), Some("engaging"));
+        assert_eq!(config_value(config, "SlurmctldAddr"), Some("(null)"));
+    }
+
+    // The same cluster as each supported major version of scontrol shows it
+    const CORPUS: &[(&str, &str, &str, &str)] = &[
+        (
+            "20.11.9",
+            include_str!("../fixtures/20.11/nodes.txt"),
+            include_str!("../fixtures/20.11/partitions.txt"),
+            include_str!("../fixtures/20.11/jobs.txt"),
+        ),
+        (
+            "22.05.11",
+            include_str!("../fixtures/22.05/nodes.txt"),
+            include_str!("../fixtures/22.05/partitions.txt"),
+            include_str!("../fixtures/22.05/jobs.txt"),
+        ),
+        (
+            "23.11.10",
+            include_str!("../fixtures/23.11/nodes.txt"),
+            include_str!("../fixtures/23.11/partitions.txt"),
+            include_str!("../fixtures/23.11/jobs.txt"),
+        ),
+        (
+            "24.05.4",
+            include_str!("../fixtures/24.05/nodes.txt"),
+            include_str!("../fixtures/24.05/partitions.txt"),
+            include_str!("../fixtures/24.05/jobs.txt"),
+        ),
+    ];
+
+    #[test]
+    fn test_version_corpus() {
+        let now = Utc::now();
+        let warnings = &mut Vec::new();
+        let mut tables = Vec::new();
+        for &(version, nodes, partitions, jobs) in CORPUS {
+            let version = Some(version.parse().unwrap());
+            tables.push((
+                parse_as::<NodeTables>(nodes, version, now, warnings).unwrap(),
+                parse_as::<PartitionTables>(partitions, version, now, warnings).unwrap(),
+                parse_as::<JobTables>(jobs, version, now, warnings).unwrap(),
+            ));
+        }
+        assert!(warnings.is_empty());
+
+        let ((nodes, resources, node_partitions), partitions, (jobs, allocations, job_res)) =
+            &tables[0];
+        assert_eq!((nodes.len(), partitions.len(), jobs.len()), (2, 2, 2));
+        let pending = jobs.get(&JobId::new(4102)).unwrap();
+        assert_eq!(pending.name, "assemble contigs");
+        let resource = |node: &str, res: &str| {
+            resources
+                .get(&(NodeName::new(node), ResourceType::new(res)))
+                .map(|r| (r.total, r.available))
+        };
+        assert_eq!(resource("gpu01", "cpu"), Some((32, 24)));
+        assert_eq!(resource("gpu01", "gres/gpu"), Some((4, 2)));
+        assert_eq!(resource("cpu01", "mem"), Some((500 << 30, 500 << 30)));
+        assert_eq!(node_partitions.len(), 3);
+        let drained = nodes.get(&NodeName::new("cpu01")).unwrap();
+        assert_eq!(drained.reason.as_deref(), Some("disk replacement"));
+        assert!(partitions
+            .iter()
+            .any(|p| p.over_subscribe.as_deref() == Some("FORCE:4")));
+        assert!(!allocations.is_empty());
+        assert!(allocations.iter().all(|a| a.node == NodeName::new("gpu01")));
+        assert!(!job_res.is_empty());
+
+        for other in &tables[1..] {
+            assert_same_rows(nodes, &other.0 .0);
+            assert_same_rows(resources, &other.0 .1);
+            assert_same_rows(node_partitions, &other.0 .2);
+            assert_same_rows(partitions, &other.1);
+            assert_same_rows(jobs, &other.2 .0);
+            assert_same_rows(allocations, &other.2 .1);
+            assert_same_rows(job_res, &other.2 .2);
+        }
+    }
+
+    #[test]
+    fn test_adapt_nodes() {
+        // Two cores of node201 are set aside for the system
+        const NODE: &str = "NodeName=node201 CPUAlloc=0 CPUTot=64 CPUEfctv=62
+   RealMemory=512000 AllocMem=0 State=IDLE Partitions=batch
+   CfgTRES=cpu=64,mem=500G,billing=64
+";
+        let cpus = |version: Option<SlurmVersion>| {
+            let (nodes, _, _) =
+                parse_as::<NodeTables>(NODE, version, Utc::now(), &mut Vec::new()).unwrap();
+            nodes.get(&NodeName::new("node201")).unwrap().cpus
+        };
+        assert_eq!(cpus(None), 62);
+        assert_eq!(cpus(Some(SlurmVersion::new(23, 11, 10))), 62);
+        assert_eq!(cpus(Some(SlurmVersion::new(21, 8, 8))), 64);
+
+        // A node of a cluster without AllocTRES
+        const BUSY: &str = "NodeName=node202 CPUAlloc=16 CPUTot=64
+   RealMemory=512000 AllocMem=32000 State=MIXED Partitions=batch
+   CfgTRES=cpu=64,mem=500G,billing=64
+";
+        let (_, resources, _) =
+            parse_as::<NodeTables>(BUSY, None, Utc::now(), &mut Vec::new()).unwrap();
+        let available = |res: &str| {
+            resources
+                .get(&(NodeName::new("node202"), ResourceType::new(res)))
+                .map(|r| r.available)
+        };
+        assert_eq!(available("cpu"), Some(48));
+        assert_eq!(available("mem"), Some((500 << 30) - 32000 * 1024 * 1024));
+
+        let partitions = parse::<PartitionTables>(
+            "PartitionName=old Nodes=node202 State=UP Shared=FORCE:4\n",
+            Utc::now(),
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            partitions.iter().next().unwrap().over_subscribe.as_deref(),
+            Some("FORCE:4")
+        );
+    }
+
+    #[test]
+    fn test_parse_gres() {
+        let gres = |name: &str, count: i64| (ResourceType::new(name), count);
+        assert_eq!(
+            parse_gres("gpu:a100:2(IDX:0-1),shard:0"),
+            vec![gres("gres/gpu", 2), gres("gres/gpu:a100", 2)]
+        );
+        assert_eq!(parse_gres("gpu:4"), vec![gres("gres/gpu", 4)]);
+        assert_eq!(parse_gres("gpu(IDX:0,2-3)"), vec![gres("gres/gpu", 3)]);
+        assert_eq!(
+            parse_gres("gpu:a100:2(S:0),gpu:h100:4(S:1)"),
+            vec![
+                gres("gres/gpu", 6),
+                gres("gres/gpu:a100", 2),
+                gres("gres/gpu:h100", 4)
+            ]
+        );
+        assert_eq!(count_ids("0-3,8-11,16"), 9);
     }
 }
@@ -0,0 +1,137 @@
+// Priority factors of the pending jobs, from sprio
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::{table::Table, JobId, JobPriority};
+
+// sprio has no parsable mode and prints "PARTITION" for both %r and %P,
+// so the header is ours and the output is requested without one
+const FORMAT: &str = "%i|%r|%Y|%A|%F|%J|%P|%Q|%T";
+const HEADER: &str = "JobId|Partition|Priority|Age|FairShare|JobSize|PartitionFactor|QOS|TRES";
+
+// A line of sprio, there is one per partition a job is pending in
+#[derive(Debug, Clone, Deserialize)]
+struct PriorityInfo<'src> {
+    #[serde(rename = "JobId")]
+    job_id: JobId,
+    #[serde(rename = "Partition")]
+    partition: &'src str,
+    #[serde(rename = "Priority")]
+    priority: i64,
+    #[serde(rename = "Age", default)]
+    age: i64,
+    #[serde(rename = "FairShare", default)]
+    fairshare: i64,
+    #[serde(rename = "JobSize", default)]
+    job_size: i64,
+    #[serde(rename = "PartitionFactor", default)]
+    partition_factor: i64,
+    #[serde(rename = "QOS", default)]
+    qos: i64,
+    // The weighted factor of each TRES, e.g. "cpu=12,gres/gpu=200"
+    #[serde(rename = "TRES", default)]
+    tres: HashMap<&'src str, i64>,
+}
+
+pub async fn priorities() -> Result<Table<JobPriority>> {
+    let output = tokio::process::Command::new("sprio")
+        .args(["--noheader", "--format", FORMAT])
+        .output()
+        .await?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "sprio failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let output = String::from_utf8(output.stdout)?;
+    parse_priorities(&output, Utc::now())
+}
+
+fn parse_priorities(output: &str, updated_at: DateTime<Utc>) -> Result<Table<JobPriority>> {
+    let input = format!("{}\n{}", HEADER, output);
+    let infos: Vec<PriorityInfo> = crate::parser::from_parsable(&input)?;
+
+    // Rank the jobs of each partition by priority, ties go to the older job id
+    let mut by_partition: HashMap<&str, Vec<&PriorityInfo>> = HashMap::new();
+    for info in &infos {
+        by_partition.entry(info.partition).or_default().push(info);
+    }
+    let mut ranks: HashMap<(&JobId, &str), u32> = HashMap::new();
+    for (partition, mut jobs) in by_partition {
+        jobs.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.job_id.cmp(&b.job_id)));
+        for (i, job) in jobs.into_iter().enumerate() {
+            ranks.insert((&job.job_id, partition), i as u32 + 1);
+        }
+    }
+
+    // A job pending in several partitions is reported with its highest priority
+    let mut best: HashMap<&JobId, &PriorityInfo> = HashMap::new();
+    for info in &infos {
+        let entry = best.entry(&info.job_id).or_insert(info);
+        if info.priority > entry.priority {
+            *entry = info;
+        }
+    }
+
+    let mut table = Table::new();
+    for info in best.into_values() {
+        table.insert(JobPriority {
+            job: info.job_id.clone(),
+            partition: info.partition.to_string(),
+            priority: info.priority,
+            age_factor: info.age,
+            fairshare_factor: info.fairshare,
+            job_size_factor: info.job_size,
+            partition_factor: info.partition_factor,
+            qos_factor: info.qos,
+            tres_factor: info.tres.values().sum(),
+            rank: ranks[&(&info.job_id, info.partition)],
+            updated_at,
+        });
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPRIO: &str = "8602011|mit_normal_gpu|21212|1000|20000|200|0|0|cpu=2,gres/gpu=10
+8602012|mit_normal_gpu|31212|1000|30000|200|0|0|cpu=2,gres/gpu=10
+8602013|mit_normal_gpu|21212|500|20500|200|0|0|
+8602013|mit_preemptable|41212|500|20500|200|20000|0|
+8602014|mit_preemptable|1000|1000|0|0|0|0|
+";
+
+    #[test]
+    fn test_parse_priorities() {
+        let table = parse_priorities(SPRIO, Utc::now()).unwrap();
+        assert_eq!(table.len(), 4);
+
+        let job = table.get(&JobId::new(8602011)).unwrap();
+        assert_eq!(job.priority, 21212);
+        assert_eq!(
+            (job.age_factor, job.fairshare_factor, job.job_size_factor),
+            (1000, 20000, 200)
+        );
+        assert_eq!(job.tres_factor, 12);
+        assert_eq!(job.rank, 2);
+        assert_eq!(table.get(&JobId::new(8602012)).unwrap().rank, 1);
+
+        // Reported in the partition where it has the highest priority
+        let job = table.get(&JobId::new(8602013)).unwrap();
+        assert_eq!(job.partition, "mit_preemptable");
+        assert_eq!(job.partition_factor, 20000);
+        assert_eq!(job.tres_factor, 0);
+        assert_eq!(job.rank, 1);
+        assert_eq!(table.get(&JobId::new(8602014)).unwrap().rank, 2);
+    }
+
+    #[test]
+    fn test_parse_no_priorities() {
+        assert!(parse_priorities("", Utc::now()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,96 @@
+// Fairshare of the accounts and users, from sshare
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::{table::Table, Association};
+
+// A line of `sshare -a -P`. Account names are indented by their depth in the tree.
+#[derive(Debug, Clone, Deserialize)]
+struct ShareInfo<'src> {
+    #[serde(rename = "Account")]
+    account: &'src str,
+    #[serde(rename = "User")]
+    user: Option<&'src str>,
+    // "parent" if the shares are those of the parent account
+    #[serde(rename = "RawShares")]
+    raw_shares: Option<&'src str>,
+    #[serde(rename = "NormShares", default)]
+    norm_shares: f64,
+    #[serde(rename = "RawUsage", default)]
+    raw_usage: i64,
+    #[serde(rename = "EffectvUsage", default)]
+    effective_usage: f64,
+    #[serde(rename = "FairShare")]
+    fairshare: Option<f64>,
+}
+
+pub async fn associations() -> Result<Table<Association>> {
+    let output = tokio::process::Command::new("sshare")
+        .args(["-a", "-P"])
+        .output()
+        .await?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "sshare failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let output = String::from_utf8(output.stdout)?;
+    parse_associations(&output, Utc::now())
+}
+
+fn parse_associations(output: &str, updated_at: DateTime<Utc>) -> Result<Table<Association>> {
+    let infos: Vec<ShareInfo> = crate::parser::from_parsable(output)?;
+    let mut table = Table::new();
+    for info in infos {
+        table.insert(Association {
+            account: info.account.to_string(),
+            user: info.user.unwrap_or_default().to_string(),
+            raw_shares: info.raw_shares.and_then(|s| s.parse().ok()),
+            norm_shares: info.norm_shares,
+            raw_usage: info.raw_usage,
+            effective_usage: info.effective_usage,
+            fairshare: info.fairshare,
+            updated_at,
+        });
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHARES: &str = "Account|User|RawShares|NormShares|RawUsage|EffectvUsage|FairShare
+root|||0.000000|1843391412||1.000000
+ root|root|1|0.500000|0|0.000000|1.000000
+ mit_general||100|0.500000|1843391412|1.000000|0.500000
+  mit_general|alice|1|0.010000|23457210|0.012725|0.312500
+  mit_general|bob|parent|0.500000|0|0.000000|0.687500
+";
+
+    #[test]
+    fn test_parse_associations() {
+        let table = parse_associations(SHARES, Utc::now()).unwrap();
+        assert_eq!(table.len(), 5);
+
+        let key = |account: &str, user: &str| (account.to_string(), user.to_string());
+        let account = table.get(&key("mit_general", "")).unwrap();
+        assert_eq!(account.raw_shares, Some(100));
+        assert_eq!(account.raw_usage, 1843391412);
+        assert_eq!(account.effective_usage, 1.0);
+
+        let alice = table.get(&key("mit_general", "alice")).unwrap();
+        assert_eq!(alice.norm_shares, 0.01);
+        assert_eq!(alice.fairshare, Some(0.3125));
+
+        let bob = table.get(&key("mit_general", "bob")).unwrap();
+        assert_eq!(bob.raw_shares, None);
+        assert_eq!(bob.raw_usage, 0);
+
+        let root = table.get(&key("root", "")).unwrap();
+        assert_eq!(root.norm_shares, 0.0);
+        assert_eq!(root.effective_usage, 0.0);
+    }
+}
@@ -0,0 +1,129 @@
+// Live usage of the running job steps, from sstat
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::resource::ResourceQuantity;
+use crate::sacct::parse_cpu_time;
+use crate::scontrol::parse_step_id;
+use crate::{table::Table, JobId, JobStep};
+
+const FORMAT: &str = "JobID,AveCPU,MaxRSS,MaxDiskRead,MaxDiskWrite";
+
+// A line of `sstat --parsable2`, one per step
+#[derive(Debug, Clone, Deserialize)]
+struct StepUsageRow<'src> {
+    // e.g. "1234.batch" or "1234_7.0"
+    #[serde(rename = "JobID")]
+    step_id: &'src str,
+    #[serde(rename = "AveCPU")]
+    ave_cpu: Option<&'src str>,
+    #[serde(rename = "MaxRSS")]
+    max_rss: Option<ResourceQuantity>,
+    #[serde(rename = "MaxDiskRead")]
+    max_disk_read: Option<ResourceQuantity>,
+    #[serde(rename = "MaxDiskWrite")]
+    max_disk_write: Option<ResourceQuantity>,
+}
+
+// Returns the steps with the usage sstat reports for them
+pub async fn usage(steps: &Table<JobStep>) -> Result<Table<JobStep>> {
+    let jobs: BTreeSet<&JobId> = steps.iter().map(|s| &s.job).collect();
+    if jobs.is_empty() {
+        return Ok(Table::new());
+    }
+    let jobs: Vec<String> = jobs.iter().map(|j| j.to_string()).collect();
+    let output = tokio::process::Command::new("sstat")
+        .args(["--allsteps", "--parsable2"])
+        .arg(format!("--format={}", FORMAT))
+        .arg(format!("--jobs={}", jobs.join(",")))
+        .output()
+        .await?;
+    // A job that ends while sstat runs fails the command, but not the other jobs
+    if !output.status.success() && output.stdout.is_empty() {
+        anyhow::bail!(
+            "sstat failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let output = String::from_utf8(output.stdout)?;
+    add_usage(steps, &output)
+}
+
+fn add_usage(steps: &Table<JobStep>, output: &str) -> Result<Table<JobStep>> {
+    let rows: Vec<StepUsageRow> = crate::parser::from_parsable(output)?;
+    let mut usage: HashMap<(JobId, &str), &StepUsageRow> = HashMap::new();
+    for row in &rows {
+        let (job, step) = parse_step_id(row.step_id)?;
+        usage.insert((job, step), row);
+    }
+
+    let mut table = Table::new();
+    for step in steps.iter() {
+        let mut step = step.clone();
+        if let Some(row) = usage.get(&(step.job.clone(), step.step.as_str())) {
+            step.ave_cpu = row.ave_cpu.and_then(parse_cpu_time);
+            step.max_rss = row.max_rss.clone().map(i64::from);
+            step.max_disk_read = row.max_disk_read.clone().map(i64::from);
+            step.max_disk_write = row.max_disk_write.clone().map(i64::from);
+        }
+        table.insert(step);
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JobStatus;
+    use chrono::Utc;
+
+    const SSTAT: &str = "JobID|AveCPU|MaxRSS|MaxDiskRead|MaxDiskWrite
+8602044.batch|00:00:02|2048K|1.50M|512K
+8602044.0|01:02.345|18000M|2.00G|0
+";
+
+    fn step(job: JobId, step: &str) -> JobStep {
+        JobStep {
+            job,
+            step: step.to_string(),
+            name: step.to_string(),
+            status: JobStatus::Running,
+            nodes: Vec::new(),
+            tasks: 1,
+            start_time: None,
+            ave_cpu: None,
+            max_rss: None,
+            max_disk_read: None,
+            max_disk_write: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_add_usage() {
+        let steps = Table::from(vec![
+            step(JobId::new(8602044), "batch"),
+            step(JobId::new(8602044), "0"),
+            step(JobId::task(8602100, 1), "extern"),
+        ]);
+        let steps = add_usage(&steps, SSTAT).unwrap();
+        assert_eq!(steps.len(), 3);
+
+        let get = |job: JobId, step: &str| steps.get(&(job, step.to_string())).unwrap();
+        let batch = get(JobId::new(8602044), "batch");
+        assert_eq!(batch.ave_cpu, Some(2));
+        assert_eq!(batch.max_rss, Some(2048 * 1024));
+        assert_eq!(batch.max_disk_read, Some(1536 * 1024));
+        assert_eq!(batch.max_disk_write, Some(512 * 1024));
+
+        let srun = get(JobId::new(8602044), "0");
+        assert_eq!(srun.ave_cpu, Some(62));
+        assert_eq!(srun.max_rss, Some(18000 * 1024 * 1024));
+        assert_eq!(srun.max_disk_write, Some(0));
+
+        // Steps sstat did not report on keep their usage unset
+        let extern_step = get(JobId::task(8602100, 1), "extern");
+        assert_eq!((extern_step.ave_cpu, extern_step.max_rss), (None, None));
+    }
+}
@@ -0,0 +1,430 @@
+// The states of nodes, jobs and partitions as reported by Slurm.
+// Node states are a base state plus flags like "MIXED+DRAIN".
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum NodeStatus {
+    Idle,
+    Alloc,
+    Mix,
+    Down,
+    Future,
+    Unknown,
+}
+
+// The flags Slurm adds to the base state of a node
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum NodeFlag {
+    Drain,
+    Completing,
+    Reserved,
+    Maint,
+    NotResponding,
+    Fail,
+    Cloud,
+    Dynamic,
+    Planned,
+    InvalidReg,
+    RebootRequested,
+    RebootIssued,
+    // Power saving: asked to power down, powering down, powered down and powering up
+    PowerDown,
+    PoweringDown,
+    PoweredDown,
+    PoweringUp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NodeState {
+    pub base: NodeStatus,
+    pub flags: BTreeSet<NodeFlag>,
+}
+
+impl NodeState {
+    pub fn new(base: NodeStatus, flags: impl IntoIterator<Item = NodeFlag>) -> Self {
+        Self {
+            base,
+            flags: flags.into_iter().collect(),
+        }
+    }
+
+    // Parses the parts of a state, as listed by the JSON output, e.g. ["MIXED", "DRAIN"].
+    // The first part that names a base state is the base state, unknown parts are ignored.
+    pub fn from_parts<'a>(parts: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut base = None;
+        let mut flags = BTreeSet::new();
+        for part in parts {
+            let (part_base, part_flags) = parse_part(part);
+            base = base.or(part_base);
+            flags.extend(part_flags);
+        }
+        Self {
+            base: base.unwrap_or(NodeStatus::Unknown),
+            flags,
+        }
+    }
+
+    pub fn has(&self, flag: NodeFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    // Drained nodes have no jobs left, draining ones are waiting for their jobs to end
+    pub fn is_drained(&self) -> bool {
+        self.has(NodeFlag::Drain)
+            && matches!(self.base, NodeStatus::Idle | NodeStatus::Down)
+            && !self.has(NodeFlag::Completing)
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.has(NodeFlag::Drain) && !self.is_drained()
+    }
+
+    // Whether new jobs can be started on the node
+    pub fn is_available(&self) -> bool {
+        let unavailable = [
+            NodeFlag::Drain,
+            NodeFlag::Fail,
+            NodeFlag::Maint,
+            NodeFlag::NotResponding,
+        ];
+        matches!(
+            self.base,
+            NodeStatus::Idle | NodeStatus::Alloc | NodeStatus::Mix
+        ) && !unavailable.iter().any(|f| self.has(*f))
+    }
+}
+
+impl From<NodeStatus> for NodeState {
+    fn from(base: NodeStatus) -> Self {
+        Self::new(base, [])
+    }
+}
+
+// Parses the states of scontrol ("IDLE+DRAIN", "DOWN*") and sinfo ("drng", "idle~")
+impl From<&str> for NodeState {
+    fn from(s: &str) -> Self {
+        Self::from_parts(s.split('+'))
+    }
+}
+
+// The state of a job. Slurm reports some flags of a job, e.g. COMPLETING or REQUEUED,
+// in place of its base state, so they are states of their own here.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Suspended,
+    Completed,
+    Cancelled,
+    Failed,
+    Timeout,
+    NodeFail,
+    Preempted,
+    BootFail,
+    Deadline,
+    OutOfMemory,
+    // Flags reported as the state
+    Completing,
+    Configuring,
+    Requeued,
+    RequeueHold,
+    RequeueFed,
+    ResvDelHold,
+    Resizing,
+    Revoked,
+    Signaling,
+    SpecialExit,
+    StageOut,
+    Stopped,
+    Unknown,
+}
+
+impl JobStatus {
+    // Parses the parts of a state, as listed by the JSON output, e.g. ["RUNNING", "COMPLETING"].
+    // Like scontrol, a flag that is reported as a state wins over the base state.
+    pub fn from_parts<'a>(parts: impl IntoIterator<Item = &'a str>) -> Self {
+        let statuses: Vec<JobStatus> = parts
+            .into_iter()
+            .map(JobStatus::from)
+            .filter(|s| *s != JobStatus::Unknown)
+            .collect();
+        statuses
+            .iter()
+            .find(|s| s.is_flag())
+            .or(statuses.first())
+            .copied()
+            .unwrap_or(JobStatus::Unknown)
+    }
+
+    fn is_flag(&self) -> bool {
+        use JobStatus::*;
+        matches!(
+            self,
+            Completing
+                | Configuring
+                | Requeued
+                | RequeueHold
+                | RequeueFed
+                | ResvDelHold
+                | Resizing
+                | Revoked
+                | Signaling
+                | SpecialExit
+                | StageOut
+                | Stopped
+        )
+    }
+
+    // Waiting to be started, including jobs that were requeued or are held
+    pub fn is_pending(&self) -> bool {
+        use JobStatus::*;
+        matches!(
+            self,
+            Pending | Requeued | RequeueHold | RequeueFed | ResvDelHold
+        )
+    }
+
+    // Started and still holding its allocation
+    pub fn is_running(&self) -> bool {
+        use JobStatus::*;
+        matches!(
+            self,
+            Running
+                | Suspended
+                | Completing
+                | Configuring
+                | Resizing
+                | Signaling
+                | StageOut
+                | Stopped
+        )
+    }
+
+    // Done, successfully or not. Finished jobs only stay in the queue for a few minutes.
+    pub fn is_finished(&self) -> bool {
+        !self.is_pending() && !self.is_running() && *self != JobStatus::Unknown
+    }
+
+    // Ended without completing, other than by being cancelled
+    pub fn is_failed(&self) -> bool {
+        use JobStatus::*;
+        matches!(
+            self,
+            Failed
+                | Timeout
+                | NodeFail
+                | Preempted
+                | BootFail
+                | Deadline
+                | OutOfMemory
+                | SpecialExit
+        )
+    }
+}
+
+// Parses the long names of scontrol and sacct ("OUT_OF_MEMORY", "CANCELLED by 1234")
+// and the short ones of squeue ("PD", "OOM")
+impl From<&str> for JobStatus {
+    fn from(s: &str) -> Self {
+        use JobStatus::*;
+        let name = s.split_whitespace().next().unwrap_or_default();
+        match name.to_ascii_uppercase().as_str() {
+            "PENDING" | "PD" => Pending,
+            "RUNNING" | "R" => Running,
+            "SUSPENDED" | "S" => Suspended,
+            "COMPLETED" | "CD" => Completed,
+            "CANCELLED" | "CA" => Cancelled,
+            "FAILED" | "F" => Failed,
+            "TIMEOUT" | "TO" => Timeout,
+            "NODE_FAIL" | "NF" => NodeFail,
+            "PREEMPTED" | "PR" => Preempted,
+            "BOOT_FAIL" | "BF" => BootFail,
+            "DEADLINE" | "DL" => Deadline,
+            "OUT_OF_MEMORY" | "OOM" => OutOfMemory,
+            "COMPLETING" | "CG" => Completing,
+            "CONFIGURING" | "CF" => Configuring,
+            "REQUEUED" | "RQ" => Requeued,
+            "REQUEUE_HOLD" | "RH" => RequeueHold,
+            "REQUEUE_FED" | "RF" => RequeueFed,
+            "RESV_DEL_HOLD" | "RD" => ResvDelHold,
+            "RESIZING" | "RS" => Resizing,
+            "REVOKED" | "RV" => Revoked,
+            "SIGNALING" | "SI" => Signaling,
+            "SPECIAL_EXIT" | "SE" => SpecialExit,
+            "STAGE_OUT" | "SO" => StageOut,
+            "STOPPED" | "ST" => Stopped,
+            _ => Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PartitionStatus {
+    // Jobs are accepted and scheduled
+    Up,
+    // Jobs are accepted but not scheduled
+    Down,
+    // No new jobs are accepted, queued jobs are still scheduled
+    Drain,
+    // Jobs are neither accepted nor scheduled
+    Inactive,
+    Unknown,
+}
+
+impl From<&str> for PartitionStatus {
+    fn from(s: &str) -> Self {
+        match s.to_ascii_uppercase().as_str() {
+            "UP" => PartitionStatus::Up,
+            "DOWN" => PartitionStatus::Down,
+            "DRAIN" => PartitionStatus::Drain,
+            "INACTIVE" => PartitionStatus::Inactive,
+            _ => PartitionStatus::Unknown,
+        }
+    }
+}
+
+// The base state and flags of one part of a state string.
+// A part may end with the symbols sinfo and scontrol use for some flags.
+fn parse_part(part: &str) -> (Option<NodeStatus>, Vec<NodeFlag>) {
+    let name = part.trim_end_matches(['*', '~', '#', '%', '!', '$', '@', '^', '-']);
+    let mut flags: Vec<NodeFlag> = part[name.len()..]
+        .chars()
+        .map(|symbol| match symbol {
+            '*' => NodeFlag::NotResponding,
+            '~' => NodeFlag::PoweredDown,
+            '#' => NodeFlag::PoweringUp,
+            '%' => NodeFlag::PoweringDown,
+            '!' => NodeFlag::PowerDown,
+            '$' => NodeFlag::Maint,
+            '@' => NodeFlag::RebootRequested,
+            '^' => NodeFlag::RebootIssued,
+            _ => NodeFlag::Planned,
+        })
+        .collect();
+
+    use NodeFlag::*;
+    use NodeStatus::*;
+    let (base, flag) = match name.to_ascii_uppercase().as_str() {
+        "IDLE" => (Some(Idle), None),
+        "ALLOCATED" | "ALLOC" => (Some(Alloc), None),
+        "MIXED" | "MIX" => (Some(Mix), None),
+        "DOWN" => (Some(Down), None),
+        "FUTURE" | "FUTR" => (Some(Future), None),
+        "UNKNOWN" | "UNK" => (Some(Unknown), None),
+        // sinfo names a base state with a flag by the flag
+        "DRAINED" => (Some(Idle), Some(Drain)),
+        "DRAINING" | "DRNG" => (Some(Alloc), Some(Drain)),
+        "FAILING" => (Some(Alloc), Some(Fail)),
+        "DRAIN" => (None, Some(Drain)),
+        "COMPLETING" | "COMP" => (None, Some(Completing)),
+        "RESERVED" | "RESV" => (None, Some(Reserved)),
+        "MAINT" | "MAINTENANCE" => (None, Some(Maint)),
+        "NOT_RESPONDING" | "NO_RESPOND" => (None, Some(NotResponding)),
+        "FAIL" => (None, Some(Fail)),
+        "CLOUD" => (None, Some(Cloud)),
+        "DYNAMIC" | "DYNAMIC_FUTURE" | "DYNAMIC_NORM" => (None, Some(Dynamic)),
+        "PLANNED" | "PLND" => (None, Some(Planned)),
+        "INVALID_REG" | "INVAL" => (None, Some(InvalidReg)),
+        "REBOOT" | "REBOOT_REQUESTED" => (None, Some(RebootRequested)),
+        "REBOOT_ISSUED" => (None, Some(RebootIssued)),
+        "POWER_DOWN" | "POWER_DRAIN" => (None, Some(PowerDown)),
+        "POWERING_DOWN" | "POW_DN" => (None, Some(PoweringDown)),
+        "POWERED_DOWN" | "POWER_SAVE" => (None, Some(PoweredDown)),
+        "POWERING_UP" | "POWER_UP" | "POW_UP" => (None, Some(PoweringUp)),
+        _ => (None, None),
+    };
+    flags.extend(flag);
+    (base, flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> NodeState {
+        NodeState::from(s)
+    }
+
+    #[test]
+    fn test_parse_node_state() {
+        assert_eq!(parse("IDLE"), NodeState::from(NodeStatus::Idle));
+        assert_eq!(
+            parse("MIXED+DRAIN"),
+            NodeState::new(NodeStatus::Mix, [NodeFlag::Drain])
+        );
+        assert_eq!(
+            parse("IDLE+COMPLETING"),
+            NodeState::new(NodeStatus::Idle, [NodeFlag::Completing])
+        );
+        assert_eq!(
+            parse("DOWN*"),
+            NodeState::new(NodeStatus::Down, [NodeFlag::NotResponding])
+        );
+        assert_eq!(
+            parse("IDLE+CLOUD+POWERED_DOWN"),
+            NodeState::new(NodeStatus::Idle, [NodeFlag::Cloud, NodeFlag::PoweredDown])
+        );
+        assert_eq!(
+            parse("ALLOCATED+RESERVED+MAINT"),
+            NodeState::new(NodeStatus::Alloc, [NodeFlag::Reserved, NodeFlag::Maint])
+        );
+        assert_eq!(
+            parse("idle~"),
+            NodeState::new(NodeStatus::Idle, [NodeFlag::PoweredDown])
+        );
+        assert_eq!(parse("mix-").flags, BTreeSet::from([NodeFlag::Planned]));
+        assert_eq!(
+            NodeState::from_parts(["DOWN", "DRAIN", "NOT_RESPONDING"]),
+            NodeState::new(NodeStatus::Down, [NodeFlag::Drain, NodeFlag::NotResponding])
+        );
+        assert_eq!(parse("BOGUS").base, NodeStatus::Unknown);
+    }
+
+    #[test]
+    fn test_drained_and_draining() {
+        let drained = parse("IDLE+DRAIN");
+        assert!(drained.is_drained() && !drained.is_draining());
+        assert!(parse("DRAINED").is_drained());
+        assert!(parse("DOWN+DRAIN").is_drained());
+
+        let draining = parse("MIXED+DRAIN");
+        assert!(draining.is_draining() && !draining.is_drained());
+        assert!(parse("DRAINING").is_draining());
+        assert!(parse("IDLE+DRAIN+COMPLETING").is_draining());
+
+        let down = parse("DOWN*");
+        assert!(!down.is_drained() && !down.is_draining());
+        assert!(!down.is_available() && !drained.is_available());
+        assert!(parse("MIXED").is_available());
+        assert!(parse("IDLE+CLOUD+POWERED_DOWN").is_available());
+    }
+
+    #[test]
+    fn test_parse_job_status() {
+        assert_eq!(JobStatus::from("OUT_OF_MEMORY"), JobStatus::OutOfMemory);
+        assert_eq!(JobStatus::from("CANCELLED by 1234"), JobStatus::Cancelled);
+        assert_eq!(JobStatus::from("NODE_FAIL"), JobStatus::NodeFail);
+        assert_eq!(JobStatus::from("PD"), JobStatus::Pending);
+        assert_eq!(JobStatus::from("CG"), JobStatus::Completing);
+        assert_eq!(JobStatus::from("BOGUS"), JobStatus::Unknown);
+        assert_eq!(
+            JobStatus::from_parts(["RUNNING", "COMPLETING"]),
+            JobStatus::Completing
+        );
+        assert_eq!(
+            JobStatus::from_parts(["PENDING", "REQUEUED"]),
+            JobStatus::Requeued
+        );
+        assert_eq!(
+            JobStatus::from_parts(["TIMEOUT", "BOGUS"]),
+            JobStatus::Timeout
+        );
+
+        assert!(JobStatus::Requeued.is_pending());
+        assert!(JobStatus::Suspended.is_running() && JobStatus::Completing.is_running());
+        assert!(JobStatus::Timeout.is_finished() && JobStatus::Timeout.is_failed());
+        assert!(JobStatus::Cancelled.is_finished() && !JobStatus::Cancelled.is_failed());
+        assert!(!JobStatus::Unknown.is_finished());
+    }
+}
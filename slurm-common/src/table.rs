@@ -25,15 +25,32 @@ impl<V: Keyed> Table<V> {
     pub fn insert(&mut self, value: V) {
         self.map.insert(V::clone_key(value.key()), value);
     }
-    pub fn diff(&self, other: &Table<V>) -> TableDiff<V, V::Key> {
+    pub fn get(&self, key: &V::Key) -> Option<&V> {
+        self.map.get(key)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &V> {
+        self.map.values()
+    }
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+    // The changes that turn this table into the other one. Changed rows hold the
+    // other table's values, rows that are the same in both are left out.
+    pub fn diff(&self, other: &Table<V>) -> TableDiff<V, V::Key>
+    where
+        V: PartialEq,
+    {
         let mut added = Vec::new();
         let mut changed = Vec::new();
         let mut removed = Vec::new();
         for (key, value) in self.map.iter() {
-            if other.map.contains_key(key) {
-                changed.push(value.clone());
-            } else {
-                removed.push(key.clone());
+            match other.map.get(key) {
+                Some(new) if new != value => changed.push(new.clone()),
+                Some(_) => {}
+                None => removed.push(key.clone()),
             }
         }
         for (key, value) in other.map.iter() {
@@ -79,30 +96,29 @@ impl<V: Keyed> Default for Table<V> {
     }
 }
 
+// A table is written as the list of its rows. Every row holds its own key, and keys
+// like (NodeName, ResourceType) could not be the keys of a JSON object anyway.
 impl<V: Keyed> Serialize for Table<V>
 where
     V: Serialize,
-    V::Key: Serialize,
 {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        self.map.serialize(serializer)
+        serializer.collect_seq(self.map.values())
     }
 }
 
 impl<'de, V: Keyed> Deserialize<'de> for Table<V>
 where
     V: Deserialize<'de>,
-    V::Key: Deserialize<'de>,
 {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        let map = HashMap::deserialize(deserializer)?;
-        Ok(Table { map })
+        Ok(Table::from(Vec::<V>::deserialize(deserializer)?))
     }
 }
 
@@ -112,3 +128,46 @@ pub struct TableDiff<V, K> {
     pub changed: Vec<V>,
     pub removed: Vec<K>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NodeName, NodeResource, ResourceType};
+
+    fn resource(node: &str, available: u64) -> NodeResource {
+        NodeResource {
+            node: NodeName::new(node),
+            resource: ResourceType::new("cpu"),
+            total: 64,
+            available,
+        }
+    }
+
+    fn key(node: &str) -> (NodeName, ResourceType) {
+        (NodeName::new(node), ResourceType::new("cpu"))
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = Table::from(vec![
+            resource("node1", 64),
+            resource("node2", 64),
+            resource("node3", 64),
+        ]);
+        let new = Table::from(vec![
+            resource("node1", 64),
+            resource("node2", 8),
+            resource("node4", 64),
+        ]);
+        let diff = old.diff(&new);
+        assert_eq!(diff.added, vec![resource("node4", 64)]);
+        assert_eq!(diff.changed, vec![resource("node2", 8)]);
+        assert_eq!(diff.removed, vec![key("node3")]);
+
+        let mut table = old.clone();
+        table.apply(diff);
+        assert_eq!(table.get(&key("node2")).unwrap().available, 8);
+        assert!(table.get(&key("node3")).is_none());
+        assert!(old.diff(&old).changed.is_empty());
+    }
+}
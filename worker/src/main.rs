@@ -1,12 +1,19 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::Utc;
 use clap::Parser;
 use rand::Rng;
+use slurm_common::protocol::{Hello, LogLevel, Message, Request, Sequencer};
+use slurm_common::sacct::HistoryWindow;
+use slurm_common::scontrol::SlurmVersion;
 use slurm_common::{
-    table::Table, ClusterState, Job, JobAllocation, JobId, JobResource, JobStatus, Node, NodeName,
-    NodePartition, NodeResource, NodeStatus, Partition, PartitionStatus, ResourceType,
+    table::Table, Association, ClusterState, Job, JobAllocation, JobId, JobPriority, JobResource,
+    JobStatus, JobStep, Node, NodeFlag, NodeName, NodePartition, NodeReservation, NodeResource,
+    NodeState, NodeStatus, ParseWarning, Partition, PartitionStatus, Qos, Reservation,
+    ResourceType,
 };
+use std::collections::BTreeMap;
 use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::time;
 
 #[derive(Parser, Debug)]
@@ -19,51 +26,321 @@ struct Args {
     #[arg(long, default_value = "30")]
     /// Polling interval in seconds
     interval: u64,
+
+    #[arg(long, default_value = "600")]
+    /// Seconds between snapshots of the whole state, which the diffs in between build on
+    snapshot_interval: u64,
+
+    #[arg(long, default_value = "24")]
+    /// How many hours of finished jobs to fetch from sacct on startup
+    history_hours: i64,
+
+    #[arg(long, env = "SLURM_TIME_ZONE")]
+    /// Time zone of the cluster, e.g. "America/New_York", if it differs from the local one
+    time_zone: Option<chrono_tz::Tz>,
+
+    #[arg(long, value_enum, default_value_t = Collector::Scontrol)]
+    /// How to collect the cluster state
+    collector: Collector,
+
+    #[arg(long, env = "SLURMRESTD_URL")]
+    /// Base url of slurmrestd, for the rest collector
+    slurmrestd_url: Option<String>,
+
+    #[arg(long, default_value = slurm_common::rest::DEFAULT_API_VERSION)]
+    /// The slurmrestd api version to request
+    slurmrestd_version: String,
+
+    #[arg(long, env = "SLURM_JWT_USER")]
+    /// User name to send along with the token, if it differs from the token's user
+    slurmrestd_user: Option<String>,
+
+    #[arg(long, env = "SLURM_JWT", hide_env_values = true)]
+    /// JWT for slurmrestd, as printed by `scontrol token`
+    slurmrestd_token: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum Collector {
+    /// Use json if the Slurm version supports it, scontrol otherwise
+    Auto,
+    /// Parse the key=value output of scontrol, skipping the records it can't read
+    Scontrol,
+    /// Parse the --json output of scontrol and squeue (Slurm 21.08+). Unlike scontrol,
+    /// a record that can't be read fails the whole poll
+    Json,
+    /// Poll the slurmrestd REST API
+    Rest,
+}
+
+// The resolved collector, with the client if it talks to slurmrestd
+enum Source {
+    Scontrol,
+    Json,
+    Rest(slurm_common::rest::Client),
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
+    if let Some(tz) = args.time_zone {
+        slurm_common::parser::set_time_zone(tz);
+    }
+
+    // The version is asked once, slurmrestd has a version of its own.
+    // Problems are logged after the handshake, which has to come first.
+    let mut problems = Vec::new();
+    let (version, cluster) = if args.mock || args.collector == Collector::Rest {
+        (None, None)
+    } else {
+        let version = match slurm_common::scontrol::version().await {
+            Ok(version) => {
+                if !version.is_supported() {
+                    problems.push(format!(
+                        "Slurm {} is not a supported version, its output may be misread",
+                        version
+                    ));
+                }
+                Some(version)
+            }
+            Err(e) => {
+                problems.push(format!("Error detecting the Slurm version: {}", e));
+                None
+            }
+        };
+        let cluster = match slurm_common::scontrol::cluster_name().await {
+            Ok(cluster) => Some(cluster),
+            Err(e) => {
+                problems.push(format!("Error reading the cluster name: {}", e));
+                None
+            }
+        };
+        (version, cluster)
+    };
+    send(Message::Hello(Hello::new(
+        env!("CARGO_PKG_VERSION"),
+        version,
+        cluster,
+        &hostname(),
+    )));
+    for problem in problems {
+        log(LogLevel::Warn, problem);
+    }
+
+    let source = match args.collector {
+        Collector::Auto if args.mock => Source::Scontrol,
+        Collector::Auto => {
+            if version.is_some_and(|v| v.supports_json()) {
+                Source::Json
+            } else {
+                Source::Scontrol
+            }
+        }
+        Collector::Scontrol => Source::Scontrol,
+        Collector::Json => Source::Json,
+        Collector::Rest => {
+            let url = args
+                .slurmrestd_url
+                .as_deref()
+                .context("--slurmrestd-url is required for the rest collector")?;
+            let token = args
+                .slurmrestd_token
+                .as_deref()
+                .context("--slurmrestd-token or SLURM_JWT is required for the rest collector")?;
+            Source::Rest(slurm_common::rest::Client::new(
+                url,
+                &args.slurmrestd_version,
+                args.slurmrestd_user.as_deref(),
+                token,
+            )?)
+        }
+    };
 
     let mut interval = time::interval(Duration::from_secs(args.interval));
-    let mut last_state = ClusterState::default();
+    // sacct is a command line tool, so it is not used alongside slurmrestd
+    let collect_history = !args.mock && !matches!(source, Source::Rest(_));
+    let mut history = HistoryWindow::new(Utc::now() - chrono::Duration::hours(args.history_hours));
+    let mut sequencer = Sequencer::new(Duration::from_secs(args.snapshot_interval));
+    let mut requests = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdin_open = true;
     loop {
-        interval.tick().await;
+        tokio::select! {
+            _ = interval.tick() => {}
+            line = requests.next_line(), if stdin_open => {
+                match line {
+                    Ok(Some(line)) => match Request::from_line(&line) {
+                        Ok(Request::Snapshot) => {
+                            sequencer.request_snapshot();
+                            history.rewind();
+                            interval.reset_immediately();
+                        }
+                        Err(e) => log(LogLevel::Warn, format!("Unknown request {}: {}", line, e)),
+                    },
+                    // Without a monitor, e.g. run by hand, snapshots are only sent on time
+                    Ok(None) | Err(_) => stdin_open = false,
+                }
+                continue;
+            }
+        }
+        send(Message::Heartbeat { time: Utc::now() });
 
+        let mut warnings = Vec::new();
         let state = if args.mock {
             Ok(generate_mock_data())
         } else {
-            // Rewrite to use try blocks when that is stabilized.
-            (async || -> Result<ClusterState> {
-                let (nodes, node_resources, node_partitions) =
-                    slurm_common::scontrol::nodes().await?;
-                let partitions = slurm_common::scontrol::partitions().await?;
-                let (jobs, job_allocations, job_resources) = slurm_common::scontrol::jobs().await?;
-                Ok(ClusterState {
-                    nodes,
-                    partitions,
-                    jobs,
-                    node_resources,
-                    node_partitions,
-                    job_allocations,
-                    job_resources,
-                    updated_at: Some(Utc::now()),
-                })
-            })()
-            .await
+            collect(&source, version, &mut warnings).await
         };
         match state {
             Ok(state) => {
-                let diff = last_state.diff(&state);
-                let json = serde_json::to_string(&diff)?;
-                println!("{}", json);
-                last_state = state;
+                // Sent with every snapshot or diff, so no poll moves the window without it
+                let mut job_history = Vec::new();
+                let mut fetched = None;
+                if collect_history {
+                    let (since, until) = history.span(Utc::now());
+                    match slurm_common::sacct::history(since, until, &mut warnings).await {
+                        Ok(records) => {
+                            job_history = records;
+                            fetched = Some(until);
+                        }
+                        Err(e) => log(
+                            LogLevel::Warn,
+                            format!("Error collecting job history: {}", e),
+                        ),
+                    }
+                }
+                let message = sequencer.next(state, job_history, warnings);
+                if let Some(until) = fetched {
+                    history.sent(until, matches!(message, Message::Snapshot { .. }));
+                }
+                send(message);
             }
-            Err(e) => eprintln!("Error: {}", e),
+            Err(e) => send(Message::Error {
+                message: e.to_string(),
+            }),
         }
     }
 }
 
+// Everything for the monitor goes to stdout, one message per line
+fn send(message: Message) {
+    match message.to_line() {
+        Ok(line) => println!("{}", line),
+        Err(e) => eprintln!("Error encoding a {} message: {}", message.kind(), e),
+    }
+}
+
+fn log(level: LogLevel, message: String) {
+    send(Message::Log { level, message });
+}
+
+fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|name| name.trim().to_string())
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+// Records scontrol prints that cannot be parsed are left out and added to the warnings
+async fn collect(
+    source: &Source,
+    version: Option<SlurmVersion>,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<ClusterState> {
+    let (
+        (nodes, node_resources, node_partitions),
+        partitions,
+        (jobs, job_allocations, job_resources),
+    ) = match source {
+        Source::Json => (
+            slurm_common::json::nodes().await?,
+            slurm_common::json::partitions().await?,
+            slurm_common::json::jobs().await?,
+        ),
+        Source::Scontrol => (
+            slurm_common::scontrol::nodes(version, warnings).await?,
+            slurm_common::scontrol::partitions(version, warnings).await?,
+            slurm_common::scontrol::jobs(version, warnings).await?,
+        ),
+        Source::Rest(client) => (
+            client.nodes().await?,
+            client.partitions().await?,
+            client.jobs().await?,
+        ),
+    };
+    // Fairshare needs the accounting database, which not every cluster has,
+    // so a failure leaves the table empty instead of failing the whole poll
+    let associations = match source {
+        Source::Scontrol | Source::Json => slurm_common::sshare::associations()
+            .await
+            .unwrap_or_else(|e| {
+                log(LogLevel::Warn, format!("Error collecting fairshare: {}", e));
+                Table::new()
+            }),
+        Source::Rest(_) => Table::new(),
+    };
+    let (reservations, node_reservations) = match source {
+        Source::Scontrol | Source::Json => {
+            slurm_common::scontrol::reservations(version, warnings).await?
+        }
+        Source::Rest(_) => (Table::new(), Table::new()),
+    };
+    let qos = match source {
+        Source::Scontrol | Source::Json => {
+            slurm_common::sacctmgr::qos().await.unwrap_or_else(|e| {
+                log(LogLevel::Warn, format!("Error collecting QOS: {}", e));
+                Table::new()
+            })
+        }
+        Source::Rest(_) => Table::new(),
+    };
+    // Without the priority/multifactor plugin sprio has nothing to report
+    let job_priorities = match source {
+        Source::Scontrol | Source::Json => {
+            slurm_common::sprio::priorities().await.unwrap_or_else(|e| {
+                log(
+                    LogLevel::Warn,
+                    format!("Error collecting job priorities: {}", e),
+                );
+                Table::new()
+            })
+        }
+        Source::Rest(_) => Table::new(),
+    };
+    // sstat only reports on steps that are still running, a failure leaves
+    // the usage of the steps unset
+    let job_steps = match source {
+        Source::Scontrol | Source::Json => {
+            let steps = slurm_common::scontrol::steps(version, warnings).await?;
+            slurm_common::sstat::usage(&steps)
+                .await
+                .unwrap_or_else(|e| {
+                    log(
+                        LogLevel::Warn,
+                        format!("Error collecting step usage: {}", e),
+                    );
+                    steps
+                })
+        }
+        Source::Rest(_) => Table::new(),
+    };
+    Ok(ClusterState {
+        nodes,
+        partitions,
+        jobs,
+        node_resources,
+        node_partitions,
+        job_allocations,
+        job_resources,
+        associations,
+        job_priorities,
+        job_steps,
+        qos,
+        reservations,
+        node_reservations,
+        updated_at: Some(Utc::now()),
+    })
+}
+
 fn generate_mock_data() -> ClusterState {
     let mut rng = rand::thread_rng();
     let updated_at = Utc::now();
@@ -75,6 +352,22 @@ fn generate_mock_data() -> ClusterState {
             status: PartitionStatus::Up,
             access_qos: Some("normal".to_string()),
             resource_qos: Some("normal".to_string()),
+            nodes: Some("node[02,04,06,08,10]".to_string()),
+            total_nodes: 5,
+            total_cpus: 320,
+            max_time: Some(2 * 24 * 60 * 60),
+            default_time: Some(60 * 60),
+            max_nodes: Some(2),
+            min_nodes: 0,
+            priority_tier: 10,
+            preempt_mode: Some("OFF".to_string()),
+            over_subscribe: Some("NO".to_string()),
+            allow_accounts: Some(vec!["research".to_string()]),
+            allow_groups: None,
+            billing_weights: BTreeMap::from([
+                (ResourceType::new("cpu"), 1.0),
+                (ResourceType::gres("gpu", None), 2.0),
+            ]),
             updated_at,
         },
         Partition {
@@ -82,15 +375,30 @@ fn generate_mock_data() -> ClusterState {
             status: PartitionStatus::Up,
             access_qos: Some("normal".to_string()),
             resource_qos: Some("normal".to_string()),
+            nodes: Some("node[01-10]".to_string()),
+            total_nodes: 10,
+            total_cpus: 640,
+            max_time: Some(7 * 24 * 60 * 60),
+            default_time: Some(60 * 60),
+            max_nodes: None,
+            min_nodes: 1,
+            priority_tier: 1,
+            preempt_mode: Some("REQUEUE".to_string()),
+            over_subscribe: Some("NO".to_string()),
+            allow_accounts: None,
+            allow_groups: None,
+            billing_weights: BTreeMap::new(),
             updated_at,
         },
     ];
 
     let node_states = [
-        NodeStatus::Idle,
-        NodeStatus::Alloc,
-        NodeStatus::Mix,
-        NodeStatus::Down,
+        "IDLE",
+        "ALLOCATED",
+        "MIXED",
+        "DOWN*",
+        "IDLE+DRAIN",
+        "MIXED+DRAIN",
     ];
 
     let mut nodes_vec = Vec::new();
@@ -100,11 +408,15 @@ fn generate_mock_data() -> ClusterState {
     for i in 1..=10 {
         let name = format!("node{:02}", i);
         let node_name = NodeName::new(&name);
-        let status = node_states[rng.gen_range(0..node_states.len())];
+        let state = NodeState::from(node_states[rng.gen_range(0..node_states.len())]);
+        let down = state.base == NodeStatus::Down || state.has(NodeFlag::Drain);
 
         nodes_vec.push(Node {
             name: node_name.clone(),
-            status,
+            state,
+            reason: down.then(|| "Kill task failed".to_string()),
+            reason_user: down.then(|| "root".to_string()),
+            reason_time: down.then(|| updated_at - chrono::Duration::hours(2)),
             cpus: 64,
             cpus_alloc: 0,
             cpus_idle: 64,
@@ -112,6 +424,9 @@ fn generate_mock_data() -> ClusterState {
             memory_alloc: 0,
             memory_free: 256000,
             partitions: vec![], // This field is technically redundant if we have node_partitions, but let's leave it empty
+            boot_time: Some(updated_at - chrono::Duration::days(7)),
+            slurmd_start_time: Some(updated_at - chrono::Duration::days(7)),
+            last_busy_time: Some(updated_at - chrono::Duration::minutes(rng.gen_range(0..60))),
             updated_at,
         });
 
@@ -131,12 +446,14 @@ fn generate_mock_data() -> ClusterState {
 
         // Add GPU resource and partition to even nodes
         if i % 2 == 0 {
-            node_resources_vec.push(NodeResource {
-                node: node_name.clone(),
-                resource: ResourceType::new("gpu"),
-                available: 4,
-                total: 4,
-            });
+            for gpu_type in [None, Some("a100")] {
+                node_resources_vec.push(NodeResource {
+                    node: node_name.clone(),
+                    resource: ResourceType::gres("gpu", gpu_type),
+                    available: 4,
+                    total: 4,
+                });
+            }
             node_partitions_vec.push(NodePartition {
                 node: node_name.clone(),
                 partition: "gpu".to_string(),
@@ -147,24 +464,32 @@ fn generate_mock_data() -> ClusterState {
     let mut jobs_vec = Vec::new();
     let mut job_resources_vec = Vec::new();
     let mut job_allocations_vec = Vec::new();
+    let mut job_priorities_vec = Vec::new();
+    let mut job_steps_vec = Vec::new();
 
     for i in 1..=5 {
         let job_id_val = 1000 + i;
         let job_id = JobId::new(job_id_val);
-        let status = match rng.gen_range(0..3) {
-            0 => JobStatus::Pending,
-            1 => JobStatus::Running,
-            _ => JobStatus::Completed,
+        let (status, reason) = match rng.gen_range(0..5) {
+            0 => (JobStatus::Pending, Some("Resources")),
+            1 => (JobStatus::Running, None),
+            2 => (JobStatus::Timeout, Some("TimeLimit")),
+            3 => (JobStatus::OutOfMemory, Some("OutOfMemory")),
+            _ => (JobStatus::Completed, None),
         };
 
         jobs_vec.push(Job {
             job_id: job_id.clone(),
             name: format!("job{}", job_id_val),
             user: format!("user{}", rng.gen_range(1..5)),
+            account: Some("research".to_string()),
             partition: "gpu".to_string(),
+            qos: Some("normal".to_string()),
             status,
+            reason: reason.map(|r| r.to_string()),
             time_limit: Some(43200),
             start_time: Some(Utc::now()),
+            end_time: Some(Utc::now() + chrono::Duration::seconds(43200)),
             submit_time: Utc::now(),
             updated_at,
         });
@@ -177,10 +502,44 @@ fn generate_mock_data() -> ClusterState {
             allocated: if status == JobStatus::Running { 64 } else { 0 },
         });
 
+        if status == JobStatus::Pending {
+            let age_factor = rng.gen_range(0..1000);
+            let fairshare_factor = rng.gen_range(0..20000);
+            job_priorities_vec.push(JobPriority {
+                job: job_id.clone(),
+                partition: "gpu".to_string(),
+                priority: age_factor + fairshare_factor,
+                age_factor,
+                fairshare_factor,
+                job_size_factor: 0,
+                partition_factor: 0,
+                qos_factor: 0,
+                tres_factor: 0,
+                rank: 0,
+                updated_at,
+            });
+        }
+
         if status == JobStatus::Running {
             // Allocate to a random node (simplified)
             let node_idx = rng.gen_range(1..=10);
             let node_name = NodeName::new(&format!("node{:02}", node_idx));
+            for step in ["batch", "0"] {
+                job_steps_vec.push(JobStep {
+                    job: job_id.clone(),
+                    step: step.to_string(),
+                    name: if step == "batch" { "batch" } else { "python" }.to_string(),
+                    status,
+                    nodes: vec![node_name.clone()],
+                    tasks: 1,
+                    start_time: Some(Utc::now()),
+                    ave_cpu: Some(rng.gen_range(0..3600)),
+                    max_rss: Some(rng.gen_range(1..64) << 30),
+                    max_disk_read: Some(rng.gen_range(0..1 << 30)),
+                    max_disk_write: Some(rng.gen_range(0..1 << 30)),
+                    updated_at,
+                });
+            }
             job_allocations_vec.push(JobAllocation {
                 job: job_id.clone(),
                 node: node_name,
@@ -190,6 +549,86 @@ fn generate_mock_data() -> ClusterState {
         }
     }
 
+    // A job array with two running tasks and the rest pending
+    let array_tasks = [
+        (JobId::task(2000, 1), JobStatus::Running, None),
+        (JobId::task(2000, 2), JobStatus::Running, None),
+        (
+            JobId::tasks(2000, "3-20%2"),
+            JobStatus::Pending,
+            Some("JobArrayTaskLimit"),
+        ),
+    ];
+    for (job_id, status, reason) in array_tasks {
+        jobs_vec.push(Job {
+            job_id,
+            name: "sweep".to_string(),
+            user: "user1".to_string(),
+            account: Some("research".to_string()),
+            partition: "gpu".to_string(),
+            qos: Some("normal".to_string()),
+            status,
+            reason: reason.map(|r| r.to_string()),
+            time_limit: Some(3600),
+            start_time: (status == JobStatus::Running).then(Utc::now),
+            end_time: None,
+            submit_time: Utc::now(),
+            updated_at,
+        });
+    }
+
+    // A maintenance window on the last two nodes, starting tomorrow
+    let maintenance = Reservation {
+        name: "maint".to_string(),
+        start_time: updated_at + chrono::Duration::days(1),
+        end_time: updated_at + chrono::Duration::days(1) + chrono::Duration::hours(8),
+        active: false,
+        nodes: vec![NodeName::new("node09"), NodeName::new("node10")],
+        partition: None,
+        users: vec!["root".to_string()],
+        accounts: vec![],
+        flags: vec!["MAINT".to_string(), "IGNORE_JOBS".to_string()],
+        tres: BTreeMap::from([(ResourceType::new("cpu"), 128)]),
+        updated_at,
+    };
+    let node_reservations_vec = maintenance
+        .nodes
+        .iter()
+        .map(|node| NodeReservation {
+            node: node.clone(),
+            reservation: maintenance.name.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    job_priorities_vec.sort_by_key(|p| std::cmp::Reverse(p.priority));
+    for (i, priority) in job_priorities_vec.iter_mut().enumerate() {
+        priority.rank = i as u32 + 1;
+    }
+
+    // Fairshare of one account and its users
+    let mut associations_vec = vec![Association {
+        account: "research".to_string(),
+        user: String::new(),
+        raw_shares: Some(100),
+        norm_shares: 1.0,
+        raw_usage: rng.gen_range(0..1_000_000),
+        effective_usage: 1.0,
+        fairshare: Some(0.5),
+        updated_at,
+    }];
+    for i in 1..5 {
+        associations_vec.push(Association {
+            account: "research".to_string(),
+            user: format!("user{}", i),
+            raw_shares: Some(1),
+            norm_shares: 0.25,
+            raw_usage: rng.gen_range(0..250_000),
+            effective_usage: rng.gen_range(0.0..0.5),
+            fairshare: Some(rng.gen_range(0.0..1.0)),
+            updated_at,
+        });
+    }
+
     ClusterState {
         partitions: Table::from(partitions_vec),
         nodes: Table::from(nodes_vec),
@@ -198,6 +637,25 @@ fn generate_mock_data() -> ClusterState {
         jobs: Table::from(jobs_vec),
         job_resources: Table::from(job_resources_vec),
         job_allocations: Table::from(job_allocations_vec),
+        associations: Table::from(associations_vec),
+        job_priorities: Table::from(job_priorities_vec),
+        job_steps: Table::from(job_steps_vec),
+        qos: Table::from(vec![Qos {
+            name: "normal".to_string(),
+            priority: 0,
+            preempt: vec![],
+            preempt_mode: Some("cluster".to_string()),
+            grp_tres: BTreeMap::new(),
+            grp_jobs: None,
+            max_tres_per_job: BTreeMap::new(),
+            max_wall: Some(2 * 24 * 60 * 60),
+            max_tres_per_user: BTreeMap::from([(ResourceType::new("cpu"), 256)]),
+            max_jobs_per_user: Some(10),
+            max_submit_per_user: None,
+            updated_at,
+        }]),
+        reservations: Table::from(vec![maintenance]),
+        node_reservations: Table::from(node_reservations_vec),
         updated_at: Some(updated_at),
     }
 }